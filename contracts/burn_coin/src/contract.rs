@@ -1,43 +1,951 @@
 #[cfg(not(feature = "library"))]
-use cosmwasm_std::{entry_point, Env, DepsMut, MessageInfo, Response, BankMsg };
-use cw2::set_contract_version;
-use crate::msg::{InstantiateMsg, ExecuteMsg};
+use cosmwasm_std::{entry_point, to_binary, from_binary, BlockInfo, Env, Deps, DepsMut, MessageInfo, Response, BankMsg, WasmMsg, CosmosMsg, SubMsg, Binary, StdResult, Storage, Addr, Coin, Uint128, Decimal, OverflowError, OverflowOperation, Order };
+use cw2::{get_contract_version, set_contract_version};
+use semver::Version;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_utils::{must_pay, Duration, Expiration, Scheduled};
+use cw1155::Cw1155ExecuteMsg;
+use crate::msg::{InstantiateMsg, ExecuteMsg, MigrateMsg, QueryMsg, ConfigResponse, ReceiveMsg, BurnHookMsg, NextBurnResponse, ScheduleResponse, MintRewardResponse, TotalBurnedResponse, BurnedByResponse, TopBurnersResponse, Burner, BurnableDenomsResponse, BurnableCw20sResponse};
+use crate::state::{burns, BurnRecord, BurnableDenom, BurnableCw20, BurnReceipt, BurnSchedule, MintReward, RewardToken, TOTAL_BURNED, ADMIN, HOOKS, BURNABLE_DENOMS, BURNABLE_CW20S, RECEIPT_COUNT, RECEIPTS, SCHEDULE, MINT_REWARDS};
 use crate::ContractError;
 const CONTRACT_NAME: &str = "burn_coin";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const DEFAULT_LIMIT : u32 = 10;
+const MAX_LIMIT : u32 = 30;
+
 #[entry_point]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
-    _msg: InstantiateMsg,
+    info: MessageInfo,
+    msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender,
+    };
+    ADMIN.set(deps.branch(), Some(admin))?;
+    for burnable in msg.burnable_denoms {
+        BURNABLE_DENOMS.save(deps.storage, &burnable.denom.clone(), &burnable)?;
+    }
+    for burnable in msg.burnable_cw20s {
+        let address = deps.api.addr_validate(&burnable.address)?;
+        BURNABLE_CW20S.save(deps.storage, &address, &burnable)?;
+    }
+
     Ok(Response::default())
 }
 
 #[entry_point]
 pub fn execute (
-    _deps: DepsMut,
-    _env: Env,
+    deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    msg: ExecuteMsg,    
+    msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
 
     match msg {
-        ExecuteMsg::BurnMyDarc {} => exec_burn (info),
+        ExecuteMsg::BurnMyDarc {} => exec_burn (deps, env, info),
+        ExecuteMsg::UpdateAdmin { admin } => exec_update_admin(deps, info, admin),
+        ExecuteMsg::UpdateBurnableDenoms { add, remove } => exec_update_burnable_denoms(deps, info, add, remove),
+        ExecuteMsg::UpdateBurnableCw20s { add, remove } => exec_update_burnable_cw20s(deps, info, add, remove),
+        ExecuteMsg::Receive(msg) => exec_receive(deps, env, info, msg),
+        ExecuteMsg::AddHook { addr } => exec_add_hook(deps, info, addr),
+        ExecuteMsg::RemoveHook { addr } => exec_remove_hook(deps, info, addr),
+        ExecuteMsg::ScheduleBurn { amount, interval, until } => exec_schedule_burn(deps, env, info, amount, interval, until),
+        ExecuteMsg::Tick {} => exec_tick(deps, env),
+        ExecuteMsg::SetMintReward { denom, reward } => exec_set_mint_reward(deps, info, denom, reward),
     }
 
 }
 
 fn exec_burn (
+    deps: DepsMut,
+    env: Env,
     info: MessageInfo,
 ) -> Result<Response, ContractError> {
+    if info.funds.is_empty() {
+        return Err(ContractError::NoFunds {});
+    }
+    for coin in info.funds.iter() {
+        assert_burnable(deps.storage, coin)?;
+        record_burn(deps.storage, &info.sender, coin)?;
+    }
+    let receipt = issue_receipt(deps.storage, &env, &info.sender, info.funds.clone())?;
+    let hooks = prepare_burn_hooks(deps.storage, &receipt)?;
+    let rewards = prepare_mint_rewards(deps.storage, &receipt)?;
     let res = Response::new()
     .add_message(BankMsg::Burn { amount: info.funds})
+    .add_messages(rewards)
+    .add_submessages(hooks)
     .add_attribute("action", "burn")
+    .add_attribute("burner", &info.sender)
+    .add_attribute("receipt_id", receipt.id.to_string())
+    ;
+    Ok(res)
+}
+
+// info.sender is the cw20 token contract, msg.sender is the burner
+fn exec_receive (
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    match from_binary(&wrapper.msg)? {
+        ReceiveMsg::Burn {} => exec_burn_cw20(deps, env, info.sender, wrapper),
+    }
+}
+
+fn exec_burn_cw20 (
+    deps: DepsMut,
+    env: Env,
+    token: Addr,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let burnable = BURNABLE_CW20S
+        .may_load(deps.storage, &token)?
+        .ok_or_else(|| ContractError::DisallowedCw20 { address: token.to_string() })?;
+    let coin = Coin { denom: token.to_string(), amount: wrapper.amount };
+    assert_min_amount(&coin, burnable.min_amount)?;
+    let burner = deps.api.addr_validate(&wrapper.sender)?;
+    record_burn(deps.storage, &burner, &coin)?;
+    let receipt = issue_receipt(deps.storage, &env, &burner, vec![coin])?;
+    let hooks = prepare_burn_hooks(deps.storage, &receipt)?;
+    let rewards = prepare_mint_rewards(deps.storage, &receipt)?;
+
+    let res = Response::new()
+    .add_message(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn { amount: wrapper.amount })?,
+        funds: vec![],
+    })
+    .add_messages(rewards)
+    .add_submessages(hooks)
+    .add_attribute("action", "burn_cw20")
+    .add_attribute("burner", &burner)
+    .add_attribute("token", &token)
+    .add_attribute("amount", wrapper.amount)
+    .add_attribute("receipt_id", receipt.id.to_string())
+    ;
+    Ok(res)
+}
+
+fn exec_update_admin (
+    deps: DepsMut,
+    info: MessageInfo,
+    admin: Option<String>,
+) -> Result<Response, ContractError> {
+    let admin = admin.map(|a| deps.api.addr_validate(&a)).transpose()?;
+    Ok(ADMIN.execute_update_admin(deps, info, admin)?)
+}
+
+fn exec_update_burnable_denoms (
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<BurnableDenom>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    for burnable in add {
+        BURNABLE_DENOMS.save(deps.storage, &burnable.denom.clone(), &burnable)?;
+    }
+    for denom in remove {
+        BURNABLE_DENOMS.remove(deps.storage, &denom);
+    }
+    let res = Response::new()
+    .add_attribute("action", "update_burnable_denoms")
+    .add_attribute("sender", &info.sender)
+    ;
+    Ok(res)
+}
+
+fn exec_update_burnable_cw20s (
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<BurnableCw20>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    for burnable in add {
+        let address = deps.api.addr_validate(&burnable.address)?;
+        BURNABLE_CW20S.save(deps.storage, &address, &burnable)?;
+    }
+    for address in remove {
+        let address = deps.api.addr_validate(&address)?;
+        BURNABLE_CW20S.remove(deps.storage, &address);
+    }
+    let res = Response::new()
+    .add_attribute("action", "update_burnable_cw20s")
+    .add_attribute("sender", &info.sender)
+    ;
+    Ok(res)
+}
+
+fn exec_add_hook (
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    let addr = deps.api.addr_validate(&addr)?;
+    Ok(HOOKS.execute_add_hook(&ADMIN, deps, info, addr)?)
+}
+
+fn exec_remove_hook (
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    let addr = deps.api.addr_validate(&addr)?;
+    Ok(HOOKS.execute_remove_hook(&ADMIN, deps, info, addr)?)
+}
+
+fn exec_schedule_burn (
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Coin,
+    interval: Duration,
+    until: Expiration,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if SCHEDULE.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::ScheduleExists {});
+    }
+    assert_burnable(deps.storage, &amount)?;
+    let next = match (interval, until) {
+        (Duration::Height(0), _) | (Duration::Time(0), _) => return Err(ContractError::InvalidSchedule {}),
+        (Duration::Height(h), Expiration::AtHeight(_)) | (Duration::Height(h), Expiration::Never {}) => Scheduled::AtHeight(env.block.height + h),
+        (Duration::Time(t), Expiration::AtTime(_)) | (Duration::Time(t), Expiration::Never {}) => Scheduled::AtTime(env.block.time.plus_seconds(t)),
+        _ => return Err(ContractError::InvalidSchedule {}),
+    };
+    let deposit = must_pay(&info, &amount.denom)?;
+    if deposit < amount.amount {
+        return Err(ContractError::TooFewTokens {});
+    }
+
+    let schedule = BurnSchedule {
+        depositor: info.sender.clone(),
+        amount,
+        interval,
+        next,
+        until,
+        remaining: deposit,
+    };
+    SCHEDULE.save(deps.storage, &schedule)?;
+    let res = Response::new()
+    .add_attribute("action", "schedule_burn")
+    .add_attribute("depositor", &info.sender)
+    .add_attribute("deposit", deposit)
+    .add_attribute("next", schedule.next.to_string())
     ;
     Ok(res)
-}
\ No newline at end of file
+}
+
+fn exec_tick (
+    deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let mut schedule = SCHEDULE
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoSchedule {})?;
+    // no more intervals than needed to burn the rest of the deposit
+    let needed = match schedule.amount.amount.u128() {
+        0 => 0,
+        amount => ((schedule.remaining.u128() + amount - 1) / amount) as u64,
+    };
+    let count = due_intervals(&schedule, &env.block).min(needed);
+    let due = schedule.amount.amount.checked_mul(Uint128::from(count))?.min(schedule.remaining);
+    if count > 0 {
+        let elapsed = match schedule.interval {
+            Duration::Height(h) => Duration::Height(h * count),
+            Duration::Time(t) => Duration::Time(t * count),
+        };
+        schedule.next = (schedule.next + elapsed)?;
+    }
+    schedule.remaining -= due;
+    let finished = schedule.remaining.is_zero() || !is_burn_allowed(&schedule);
+    if due.is_zero() && !finished {
+        return Err(ContractError::NoBurnDue {});
+    }
+
+    let mut res = Response::new()
+    .add_attribute("action", "tick")
+    .add_attribute("burned", due)
+    ;
+    if !due.is_zero() {
+        let coin = Coin { denom: schedule.amount.denom.clone(), amount: due };
+        record_burn(deps.storage, &schedule.depositor, &coin)?;
+        let receipt = issue_receipt(deps.storage, &env, &schedule.depositor, vec![coin.clone()])?;
+        let hooks = prepare_burn_hooks(deps.storage, &receipt)?;
+        res = res
+        .add_message(BankMsg::Burn { amount: vec![coin] })
+        .add_submessages(hooks)
+        .add_attribute("receipt_id", receipt.id.to_string());
+    }
+    if finished {
+        // refunds what is left once no more burns can happen
+        if !schedule.remaining.is_zero() {
+            res = res
+            .add_message(BankMsg::Send {
+                to_address: schedule.depositor.to_string(),
+                amount: vec![Coin { denom: schedule.amount.denom.clone(), amount: schedule.remaining }],
+            })
+            .add_attribute("refunded", schedule.remaining);
+        }
+        SCHEDULE.remove(deps.storage);
+    } else {
+        SCHEDULE.save(deps.storage, &schedule)?;
+    }
+    Ok(res)
+}
+
+fn exec_set_mint_reward (
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    reward: Option<MintReward>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match reward {
+        Some(reward) => {
+            if reward.ratio.is_zero() {
+                return Err(ContractError::InvalidRewardRatio {});
+            }
+            match &reward.token {
+                RewardToken::Cw20 { address } => deps.api.addr_validate(address)?,
+                RewardToken::Cw1155 { address, .. } => deps.api.addr_validate(address)?,
+            };
+            MINT_REWARDS.save(deps.storage, &denom, &reward)?;
+        }
+        None => MINT_REWARDS.remove(deps.storage, &denom),
+    }
+    let res = Response::new()
+    .add_attribute("action", "set_mint_reward")
+    .add_attribute("denom", denom)
+    ;
+    Ok(res)
+}
+
+// number of scheduled burns triggered by `block` that are still before the schedule ends
+fn due_intervals (schedule: &BurnSchedule, block: &BlockInfo) -> u64 {
+    let (next, now, step, until) = match (schedule.next, schedule.interval, schedule.until) {
+        (Scheduled::AtHeight(next), Duration::Height(step), Expiration::AtHeight(until)) => (next, block.height, step, Some(until)),
+        (Scheduled::AtHeight(next), Duration::Height(step), Expiration::Never {}) => (next, block.height, step, None),
+        (Scheduled::AtTime(next), Duration::Time(step), Expiration::AtTime(until)) => (next.nanos(), block.time.nanos(), step.saturating_mul(1_000_000_000), Some(until.nanos())),
+        (Scheduled::AtTime(next), Duration::Time(step), Expiration::Never {}) => (next.nanos(), block.time.nanos(), step.saturating_mul(1_000_000_000), None),
+        _ => return 0,
+    };
+    if step == 0 || now < next {
+        return 0;
+    }
+    let count = (now - next) / step + 1;
+    match until {
+        // burns at next, next + step, ... strictly before until
+        Some(until) if until <= next => 0,
+        Some(until) => count.min((until - next - 1) / step + 1),
+        None => count,
+    }
+}
+
+// whether the next scheduled burn happens before the schedule ends
+fn is_burn_allowed (schedule: &BurnSchedule) -> bool {
+    match (schedule.until, schedule.next) {
+        (Expiration::Never {}, _) => true,
+        (Expiration::AtHeight(until), Scheduled::AtHeight(next)) => next < until,
+        (Expiration::AtTime(until), Scheduled::AtTime(next)) => next < until,
+        _ => false,
+    }
+}
+
+fn assert_burnable (
+    storage: &dyn Storage,
+    coin: &Coin,
+) -> Result<(), ContractError> {
+    let burnable = BURNABLE_DENOMS
+        .may_load(storage, &coin.denom)?
+        .ok_or_else(|| ContractError::DisallowedDenom { denom: coin.denom.clone() })?;
+    assert_min_amount(coin, burnable.min_amount)
+}
+
+fn assert_min_amount (
+    coin: &Coin,
+    min_amount: Option<Uint128>,
+) -> Result<(), ContractError> {
+    if coin.amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    match min_amount {
+        Some(min_amount) if coin.amount < min_amount => Err(ContractError::BelowMinimum { denom: coin.denom.clone(), min_amount }),
+        _ => Ok(()),
+    }
+}
+
+// stores a receipt under the next sequential id
+fn issue_receipt (
+    storage: &mut dyn Storage,
+    env: &Env,
+    burner: &Addr,
+    coins: Vec<Coin>,
+) -> Result<BurnReceipt, ContractError> {
+    let id = RECEIPT_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    let receipt = BurnReceipt {
+        id,
+        burner: burner.clone(),
+        coins,
+        height: env.block.height,
+        time: env.block.time,
+    };
+    RECEIPT_COUNT.save(storage, &id)?;
+    RECEIPTS.save(storage, id, &receipt)?;
+    Ok(receipt)
+}
+
+// hooks run in the same transaction, a failing hook reverts the burn
+fn prepare_burn_hooks (
+    storage: &dyn Storage,
+    receipt: &BurnReceipt,
+) -> StdResult<Vec<SubMsg>> {
+    HOOKS.prepare_hooks(storage, |h| {
+        let msg = BurnHookMsg { receipt: receipt.clone() };
+        msg.into_cosmos_msg(h).map(SubMsg::new)
+    })
+}
+
+// mints configured reward tokens to the burner, proportional to each burned coin
+fn prepare_mint_rewards (
+    storage: &dyn Storage,
+    receipt: &BurnReceipt,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let mut msgs = vec![];
+    for coin in receipt.coins.iter() {
+        let reward = match MINT_REWARDS.may_load(storage, &coin.denom)? {
+            Some(reward) => reward,
+            None => continue,
+        };
+        let amount = coin.amount
+            .checked_multiply_ratio(reward.ratio.atomics(), Decimal::one().atomics())
+            .map_err(|_| OverflowError::new(OverflowOperation::Mul, coin.amount, reward.ratio))?;
+        if amount.is_zero() {
+            continue;
+        }
+        let (contract_addr, msg) = match reward.token {
+            RewardToken::Cw20 { address } => (address, to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: receipt.burner.to_string(),
+                amount,
+            })?),
+            RewardToken::Cw1155 { address, token_id } => (address, to_binary(&Cw1155ExecuteMsg::Mint {
+                to: receipt.burner.to_string(),
+                token_id,
+                value: amount,
+                msg: None,
+            })?),
+        };
+        msgs.push(WasmMsg::Execute { contract_addr, msg, funds: vec![] }.into());
+    }
+    Ok(msgs)
+}
+
+// adds a burn to the per denom total and to the burner's cumulative amount
+fn record_burn (
+    storage: &mut dyn Storage,
+    burner: &Addr,
+    coin: &Coin,
+) -> Result<(), ContractError> {
+    TOTAL_BURNED.update(storage, &coin.denom, |x| -> Result<_, ContractError> {
+        Ok(x.unwrap_or_default().checked_add(coin.amount)?)
+    })?;
+    burns().update(storage, (burner, &coin.denom), |x| -> Result<_, ContractError> {
+        let mut record = x.unwrap_or(BurnRecord {
+            burner: burner.clone(),
+            denom: coin.denom.clone(),
+            amount: Uint128::zero(),
+        });
+        record.amount = record.amount.checked_add(coin.amount)?;
+        Ok(record)
+    })?;
+    Ok(())
+}
+
+#[entry_point]
+pub fn migrate(
+    mut deps: DepsMut,
+    _env: Env,
+    msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let previous = get_contract_version(deps.storage)?;
+    if previous.contract != CONTRACT_NAME {
+        return Err(ContractError::WrongContract { contract: previous.contract });
+    }
+    let previous_version = parse_version(&previous.version)?;
+    if previous_version > parse_version(CONTRACT_VERSION)? {
+        return Err(ContractError::CannotDowngrade { previous_version: previous.version });
+    }
+
+    // versions before the admin was added have nothing stored, and burned any denom
+    let legacy = ADMIN.get(deps.as_ref()).is_err();
+    if legacy && msg.admin.is_none() {
+        return Err(ContractError::MissingAdmin {});
+    }
+    let burnable_denoms = msg.burnable_denoms.unwrap_or_default();
+    if legacy && burnable_denoms.is_empty() {
+        return Err(ContractError::MissingBurnableDenoms {});
+    }
+
+    if let Some(admin) = msg.admin {
+        let admin = deps.api.addr_validate(&admin)?;
+        ADMIN.set(deps.branch(), Some(admin))?;
+    }
+    for burnable in burnable_denoms {
+        BURNABLE_DENOMS.save(deps.storage, &burnable.denom.clone(), &burnable)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let res = Response::new()
+    .add_attribute("action", "migrate")
+    .add_attribute("from_version", previous.version)
+    .add_attribute("to_version", CONTRACT_VERSION)
+    ;
+    Ok(res)
+}
+
+fn parse_version(version: &str) -> Result<Version, ContractError> {
+    Version::parse(version).map_err(|_| ContractError::InvalidVersion { version: version.to_string() })
+}
+
+#[entry_point]
+pub fn query(
+    deps: Deps,
+    _env: Env,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::TotalBurned { denom } => to_binary(&query_total_burned(deps, denom)?),
+        QueryMsg::BurnedBy { address } => to_binary(&query_burned_by(deps, address)?),
+        QueryMsg::TopBurners { denom, limit } => to_binary(&query_top_burners(deps, denom, limit)?),
+        QueryMsg::BurnableDenoms {} => to_binary(&query_burnable_denoms(deps)?),
+        QueryMsg::BurnableCw20s {} => to_binary(&query_burnable_cw20s(deps)?),
+        QueryMsg::Receipt { id } => to_binary(&RECEIPTS.load(deps.storage, id)?),
+        QueryMsg::Hooks {} => to_binary(&HOOKS.query_hooks(deps)?),
+        QueryMsg::NextBurn {} => to_binary(&query_next_burn(deps)?),
+        QueryMsg::MintReward { denom } => to_binary(&MintRewardResponse { reward: MINT_REWARDS.may_load(deps.storage, &denom)?, denom }),
+        QueryMsg::Schedule {} => to_binary(&ScheduleResponse { schedule: SCHEDULE.may_load(deps.storage)? }),
+    }
+}
+
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    Ok(ConfigResponse {
+        admin: ADMIN.query_admin(deps)?.admin,
+        burnable_denoms: query_burnable_denoms(deps)?.denoms,
+        burnable_cw20s: query_burnable_cw20s(deps)?.tokens,
+    })
+}
+
+fn query_total_burned(deps: Deps, denom: String) -> StdResult<TotalBurnedResponse> {
+    let amount = TOTAL_BURNED.may_load(deps.storage, &denom)?.unwrap_or_default();
+    Ok(TotalBurnedResponse { denom, amount })
+}
+
+fn query_burned_by(deps: Deps, address: Addr) -> StdResult<BurnedByResponse> {
+    let burned = burns()
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, record)| Coin { denom, amount: record.amount }))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BurnedByResponse { address, burned })
+}
+
+fn query_top_burners(deps: Deps, denom: String, limit: Option<u32>) -> StdResult<TopBurnersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let burners = burns()
+        .idx
+        .amount
+        .sub_prefix(denom)
+        .range(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|item| item.map(|(_, record)| Burner { address: record.burner, amount: record.amount }))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(TopBurnersResponse { burners })
+}
+
+fn query_burnable_denoms(deps: Deps) -> StdResult<BurnableDenomsResponse> {
+    let denoms = BURNABLE_DENOMS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, burnable)| burnable))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BurnableDenomsResponse { denoms })
+}
+
+fn query_next_burn(deps: Deps) -> StdResult<NextBurnResponse> {
+    let schedule = SCHEDULE
+        .may_load(deps.storage)?
+        .filter(|s| !s.remaining.is_zero() && is_burn_allowed(s));
+    Ok(match schedule {
+        Some(s) => NextBurnResponse {
+            next: Some(s.next),
+            amount: Some(Coin { denom: s.amount.denom, amount: s.amount.amount.min(s.remaining) }),
+        },
+        None => NextBurnResponse { next: None, amount: None },
+    })
+}
+
+fn query_burnable_cw20s(deps: Deps) -> StdResult<BurnableCw20sResponse> {
+    let tokens = BURNABLE_CW20S
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, burnable)| burnable))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(BurnableCw20sResponse { tokens })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{coin, coins, Decimal, OwnedDeps};
+
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), InstantiateMsg {
+            admin: None,
+            burnable_denoms: vec![
+                BurnableDenom { denom: "udarc".to_string(), min_amount: Some(Uint128::new(10)) },
+                BurnableDenom { denom: "uother".to_string(), min_amount: None },
+            ],
+            burnable_cw20s: vec![
+                BurnableCw20 { address: "cw20darc".to_string(), min_amount: Some(Uint128::new(10)) },
+            ],
+        }).unwrap();
+        deps
+    }
+
+    fn burn(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, burner: &str, funds: &[Coin]) {
+        execute(deps.as_mut(), mock_env(), mock_info(burner, funds), ExecuteMsg::BurnMyDarc {}).unwrap();
+    }
+
+    #[test]
+    fn burns_are_accounted_per_denom_and_burner() {
+        let mut deps = setup();
+        burn(&mut deps, "alice", &[coin(100, "udarc"), coin(5, "uother")]);
+        burn(&mut deps, "alice", &coins(50, "udarc"));
+        burn(&mut deps, "bob", &coins(70, "udarc"));
+
+        let total: TotalBurnedResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalBurned { denom: "udarc".to_string() }).unwrap()).unwrap();
+        assert_eq!(total.amount, Uint128::new(220));
+        let total: TotalBurnedResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalBurned { denom: "unknown".to_string() }).unwrap()).unwrap();
+        assert_eq!(total.amount, Uint128::zero());
+
+        let burned: BurnedByResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BurnedBy { address: Addr::unchecked("alice") }).unwrap()).unwrap();
+        assert_eq!(burned.burned, vec![coin(150, "udarc"), coin(5, "uother")]);
+    }
+
+    #[test]
+    fn top_burners_are_ordered_by_amount() {
+        let mut deps = setup();
+        burn(&mut deps, "alice", &coins(100, "udarc"));
+        burn(&mut deps, "bob", &coins(70, "udarc"));
+        burn(&mut deps, "carol", &coins(300, "uother"));
+        burn(&mut deps, "bob", &coins(50, "udarc"));
+        burn(&mut deps, "dave", &coins(10, "udarc"));
+
+        let top: TopBurnersResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TopBurners { denom: "udarc".to_string(), limit: None }).unwrap()).unwrap();
+        assert_eq!(top.burners, vec![
+            Burner { address: Addr::unchecked("bob"), amount: Uint128::new(120) },
+            Burner { address: Addr::unchecked("alice"), amount: Uint128::new(100) },
+            Burner { address: Addr::unchecked("dave"), amount: Uint128::new(10) },
+        ]);
+
+        let top: TopBurnersResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TopBurners { denom: "udarc".to_string(), limit: Some(1) }).unwrap()).unwrap();
+        assert_eq!(top.burners.len(), 1);
+    }
+
+    #[test]
+    fn burn_rejects_invalid_funds() {
+        let mut deps = setup();
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), ExecuteMsg::BurnMyDarc {}).unwrap_err();
+        assert_eq!(err, ContractError::NoFunds {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[coin(100, "udarc"), coin(1, "uatom")]), ExecuteMsg::BurnMyDarc {}).unwrap_err();
+        assert_eq!(err, ContractError::DisallowedDenom { denom: "uatom".to_string() });
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &coins(9, "udarc")), ExecuteMsg::BurnMyDarc {}).unwrap_err();
+        assert_eq!(err, ContractError::BelowMinimum { denom: "udarc".to_string(), min_amount: Uint128::new(10) });
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &coins(0, "uother")), ExecuteMsg::BurnMyDarc {}).unwrap_err();
+        assert_eq!(err, ContractError::InvalidZeroAmount {});
+    }
+
+    #[test]
+    fn admin_updates_burnable_denoms() {
+        let mut deps = setup();
+        let update = ExecuteMsg::UpdateBurnableDenoms {
+            add: vec![
+                BurnableDenom { denom: "uatom".to_string(), min_amount: None },
+                BurnableDenom { denom: "udarc".to_string(), min_amount: Some(Uint128::new(1000)) },
+            ],
+            remove: vec!["uother".to_string()],
+        };
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), update.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Admin(cw_controllers::AdminError::NotAdmin {}));
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let res: BurnableDenomsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BurnableDenoms {}).unwrap()).unwrap();
+        assert_eq!(res.denoms, vec![
+            BurnableDenom { denom: "uatom".to_string(), min_amount: None },
+            BurnableDenom { denom: "udarc".to_string(), min_amount: Some(Uint128::new(1000)) },
+        ]);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &coins(100, "uother")), ExecuteMsg::BurnMyDarc {}).unwrap_err();
+        assert_eq!(err, ContractError::DisallowedDenom { denom: "uother".to_string() });
+    }
+
+    #[test]
+    fn cw20_burns_share_accounting() {
+        let mut deps = setup();
+        let receive = |sender: &str, amount: u128| ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&ReceiveMsg::Burn {}).unwrap(),
+        });
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("cw20darc", &[]), receive("alice", 100)).unwrap();
+        assert_eq!(res.messages[0].msg, WasmMsg::Execute {
+            contract_addr: "cw20darc".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount: Uint128::new(100) }).unwrap(),
+            funds: vec![],
+        }.into());
+        burn(&mut deps, "alice", &coins(30, "udarc"));
+
+        let total: TotalBurnedResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalBurned { denom: "cw20darc".to_string() }).unwrap()).unwrap();
+        assert_eq!(total.amount, Uint128::new(100));
+        let burned: BurnedByResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BurnedBy { address: Addr::unchecked("alice") }).unwrap()).unwrap();
+        assert_eq!(burned.burned, vec![coin(100, "cw20darc"), coin(30, "udarc")]);
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("cw20other", &[]), receive("alice", 100)).unwrap_err();
+        assert_eq!(err, ContractError::DisallowedCw20 { address: "cw20other".to_string() });
+        let err = execute(deps.as_mut(), mock_env(), mock_info("cw20darc", &[]), receive("alice", 9)).unwrap_err();
+        assert_eq!(err, ContractError::BelowMinimum { denom: "cw20darc".to_string(), min_amount: Uint128::new(10) });
+
+        let update = ExecuteMsg::UpdateBurnableCw20s {
+            add: vec![BurnableCw20 { address: "cw20other".to_string(), min_amount: None }],
+            remove: vec!["cw20darc".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+        let res: BurnableCw20sResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::BurnableCw20s {}).unwrap()).unwrap();
+        assert_eq!(res.tokens, vec![BurnableCw20 { address: "cw20other".to_string(), min_amount: None }]);
+    }
+
+    #[test]
+    fn burns_issue_receipts_and_fire_hooks() {
+        let mut deps = setup();
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), ExecuteMsg::AddHook { addr: "partner".to_string() }).unwrap_err();
+        assert_eq!(err, ContractError::Hook(cw_controllers::HookError::Admin(cw_controllers::AdminError::NotAdmin {})));
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::AddHook { addr: "partner".to_string() }).unwrap();
+
+        let env = mock_env();
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &coins(100, "udarc")), ExecuteMsg::BurnMyDarc {}).unwrap();
+        let receipt = BurnReceipt {
+            id: 1,
+            burner: Addr::unchecked("alice"),
+            coins: coins(100, "udarc"),
+            height: env.block.height,
+            time: env.block.time,
+        };
+        assert_eq!(res.messages[1], SubMsg::new(BurnHookMsg { receipt: receipt.clone() }.into_cosmos_msg("partner").unwrap()));
+        let stored: BurnReceipt = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Receipt { id: 1 }).unwrap()).unwrap();
+        assert_eq!(stored, receipt);
+
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::RemoveHook { addr: "partner".to_string() }).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("cw20darc", &[]), ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "bob".to_string(),
+            amount: Uint128::new(50),
+            msg: to_binary(&ReceiveMsg::Burn {}).unwrap(),
+        })).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        let stored: BurnReceipt = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Receipt { id: 2 }).unwrap()).unwrap();
+        assert_eq!(stored.burner, Addr::unchecked("bob"));
+        assert_eq!(stored.coins, coins(50, "cw20darc"));
+        query(deps.as_ref(), mock_env(), QueryMsg::Receipt { id: 3 }).unwrap_err();
+    }
+
+    #[test]
+    fn scheduled_burns_are_burned_on_tick() {
+        const DAY: u64 = 86400;
+        let mut deps = setup();
+        let start = mock_env();
+        let at = |seconds: u64| {
+            let mut env = mock_env();
+            env.block.time = start.block.time.plus_seconds(seconds);
+            env
+        };
+        let schedule = ExecuteMsg::ScheduleBurn {
+            amount: coin(100, "udarc"),
+            interval: Duration::Time(DAY),
+            until: Expiration::AtTime(start.block.time.plus_seconds(DAY * 7 / 2)),
+        };
+        let err = execute(deps.as_mut(), start.clone(), mock_info("alice", &coins(1000, "udarc")), schedule.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Admin(cw_controllers::AdminError::NotAdmin {}));
+        let err = execute(deps.as_mut(), start.clone(), mock_info("creator", &coins(1000, "udarc")), ExecuteMsg::ScheduleBurn {
+            amount: coin(100, "udarc"),
+            interval: Duration::Height(10),
+            until: Expiration::AtTime(start.block.time),
+        }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSchedule {});
+        execute(deps.as_mut(), start.clone(), mock_info("creator", &coins(1000, "udarc")), schedule.clone()).unwrap();
+        let err = execute(deps.as_mut(), start.clone(), mock_info("creator", &coins(1000, "udarc")), schedule).unwrap_err();
+        assert_eq!(err, ContractError::ScheduleExists {});
+
+        let err = execute(deps.as_mut(), at(DAY - 1), mock_info("anyone", &[]), ExecuteMsg::Tick {}).unwrap_err();
+        assert_eq!(err, ContractError::NoBurnDue {});
+        let res = execute(deps.as_mut(), at(DAY * 2), mock_info("anyone", &[]), ExecuteMsg::Tick {}).unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(BankMsg::Burn { amount: coins(200, "udarc") })]);
+        let next: NextBurnResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::NextBurn {}).unwrap()).unwrap();
+        assert_eq!(next, NextBurnResponse { next: Some(Scheduled::AtTime(start.block.time.plus_seconds(DAY * 3))), amount: Some(coin(100, "udarc")) });
+
+        // only the burn at day 3 is before the end, the rest of the deposit goes back
+        let res = execute(deps.as_mut(), at(DAY * 10), mock_info("anyone", &[]), ExecuteMsg::Tick {}).unwrap();
+        assert_eq!(res.messages, vec![
+            SubMsg::new(BankMsg::Burn { amount: coins(100, "udarc") }),
+            SubMsg::new(BankMsg::Send { to_address: "creator".to_string(), amount: coins(700, "udarc") }),
+        ]);
+        let total: TotalBurnedResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalBurned { denom: "udarc".to_string() }).unwrap()).unwrap();
+        assert_eq!(total.amount, Uint128::new(300));
+        let schedule: ScheduleResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Schedule {}).unwrap()).unwrap();
+        assert_eq!(schedule.schedule, None);
+        let err = execute(deps.as_mut(), at(DAY * 11), mock_info("anyone", &[]), ExecuteMsg::Tick {}).unwrap_err();
+        assert_eq!(err, ContractError::NoSchedule {});
+    }
+
+    #[test]
+    fn tick_burns_many_missed_intervals_at_once() {
+        let mut deps = setup();
+        let start = mock_env();
+        let at = |height: u64| {
+            let mut env = mock_env();
+            env.block.height = start.block.height + height;
+            env
+        };
+        execute(deps.as_mut(), start.clone(), mock_info("creator", &coins(100_000_000, "udarc")), ExecuteMsg::ScheduleBurn {
+            amount: coin(10, "udarc"),
+            interval: Duration::Height(1),
+            until: Expiration::Never {},
+        }).unwrap();
+
+        let res = execute(deps.as_mut(), at(1_000_000), mock_info("anyone", &[]), ExecuteMsg::Tick {}).unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(BankMsg::Burn { amount: coins(10_000_000, "udarc") })]);
+        let next: NextBurnResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::NextBurn {}).unwrap()).unwrap();
+        assert_eq!(next.next, Some(Scheduled::AtHeight(start.block.height + 1_000_001)));
+
+        // far more intervals than the deposit covers burns the rest and ends the schedule
+        let res = execute(deps.as_mut(), at(u32::MAX as u64), mock_info("anyone", &[]), ExecuteMsg::Tick {}).unwrap();
+        assert_eq!(res.messages, vec![SubMsg::new(BankMsg::Burn { amount: coins(90_000_000, "udarc") })]);
+        let schedule: ScheduleResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Schedule {}).unwrap()).unwrap();
+        assert_eq!(schedule.schedule, None);
+    }
+
+    #[test]
+    fn burns_mint_configured_rewards() {
+        let mut deps = setup();
+        let set_reward = ExecuteMsg::SetMintReward {
+            denom: "udarc".to_string(),
+            reward: Some(MintReward {
+                token: RewardToken::Cw1155 { address: "collectible".to_string(), token_id: "campaign-1".to_string() },
+                ratio: Decimal::percent(1),
+            }),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), set_reward.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Admin(cw_controllers::AdminError::NotAdmin {}));
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), set_reward).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetMintReward {
+            denom: "cw20darc".to_string(),
+            reward: Some(MintReward { token: RewardToken::Cw20 { address: "rdarc".to_string() }, ratio: Decimal::percent(50) }),
+        }).unwrap();
+
+        // 50 uother has no reward, 150 udarc mints 1
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[coin(150, "udarc"), coin(50, "uother")]), ExecuteMsg::BurnMyDarc {}).unwrap();
+        assert_eq!(res.messages[1..], [SubMsg::new(WasmMsg::Execute {
+            contract_addr: "collectible".to_string(),
+            msg: to_binary(&Cw1155ExecuteMsg::Mint { to: "alice".to_string(), token_id: "campaign-1".to_string(), value: Uint128::new(1), msg: None }).unwrap(),
+            funds: vec![],
+        })]);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("cw20darc", &[]), ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "bob".to_string(),
+            amount: Uint128::new(30),
+            msg: to_binary(&ReceiveMsg::Burn {}).unwrap(),
+        })).unwrap();
+        assert_eq!(res.messages[1..], [SubMsg::new(WasmMsg::Execute {
+            contract_addr: "rdarc".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint { recipient: "bob".to_string(), amount: Uint128::new(15) }).unwrap(),
+            funds: vec![],
+        })]);
+
+        // a zero ratio is rejected, and a reward that does not fit errors instead of panicking
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetMintReward {
+            denom: "cw20darc".to_string(),
+            reward: Some(MintReward { token: RewardToken::Cw20 { address: "rdarc".to_string() }, ratio: Decimal::zero() }),
+        }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidRewardRatio {});
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetMintReward {
+            denom: "cw20darc".to_string(),
+            reward: Some(MintReward { token: RewardToken::Cw20 { address: "rdarc".to_string() }, ratio: Decimal::percent(200) }),
+        }).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info("cw20darc", &[]), ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "bob".to_string(),
+            amount: Uint128::new(u128::MAX / 2 + 1),
+            msg: to_binary(&ReceiveMsg::Burn {}).unwrap(),
+        })).unwrap_err();
+        assert!(matches!(err, ContractError::Overflow(OverflowError { operation: OverflowOperation::Mul, .. })), "{:?}", err);
+
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SetMintReward { denom: "udarc".to_string(), reward: None }).unwrap();
+        let res: MintRewardResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::MintReward { denom: "udarc".to_string() }).unwrap()).unwrap();
+        assert_eq!(res.reward, None);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &coins(150, "udarc")), ExecuteMsg::BurnMyDarc {}).unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
+
+    #[test]
+    fn admin_can_be_updated() {
+        let mut deps = setup();
+        let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), ExecuteMsg::UpdateAdmin { admin: Some("alice".to_string()) }).unwrap_err();
+        assert_eq!(err, ContractError::Admin(cw_controllers::AdminError::NotAdmin {}));
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::UpdateAdmin { admin: Some("alice".to_string()) }).unwrap();
+
+        let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(config.admin, Some("alice".to_string()));
+        assert_eq!(config.burnable_denoms.len(), 2);
+        assert_eq!(config.burnable_cw20s, vec![BurnableCw20 { address: "cw20darc".to_string(), min_amount: Some(Uint128::new(10)) }]);
+    }
+
+    #[test]
+    fn migrate_checks_previous_version() {
+        let mut deps = mock_dependencies();
+        // deployment from before the admin was added
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.1.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { admin: None, burnable_denoms: None }).unwrap_err();
+        assert_eq!(err, ContractError::MissingAdmin {});
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { admin: Some("creator".to_string()), burnable_denoms: Some(vec![]) }).unwrap_err();
+        assert_eq!(err, ContractError::MissingBurnableDenoms {});
+        assert_eq!(get_contract_version(&deps.storage).unwrap().version, "0.1.0");
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {
+            admin: Some("creator".to_string()),
+            burnable_denoms: Some(vec![BurnableDenom { denom: "udarc".to_string(), min_amount: None }]),
+        }).unwrap();
+        let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(config.admin, Some("creator".to_string()));
+        assert_eq!(get_contract_version(&deps.storage).unwrap().version, CONTRACT_VERSION);
+        // burning works right after the migration
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &coins(100, "udarc")), ExecuteMsg::BurnMyDarc {}).unwrap();
+        assert_eq!(res.messages[0], SubMsg::new(BankMsg::Burn { amount: coins(100, "udarc") }));
+        // the stored admin is kept when none is given
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.1.0").unwrap();
+        migrate(deps.as_mut(), mock_env(), MigrateMsg { admin: None, burnable_denoms: None }).unwrap();
+        let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(config.admin, Some("creator".to_string()));
+
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { admin: None, burnable_denoms: None }).unwrap_err();
+        assert_eq!(err, ContractError::CannotDowngrade { previous_version: "99.0.0".to_string() });
+        set_contract_version(deps.as_mut().storage, "cw20-base", "0.1.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg { admin: None, burnable_denoms: None }).unwrap_err();
+        assert_eq!(err, ContractError::WrongContract { contract: "cw20-base".to_string() });
+    }
+}
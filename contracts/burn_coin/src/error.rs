@@ -1,4 +1,6 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{OverflowError, StdError, Uint128};
+use cw_controllers::{AdminError, HookError};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +8,18 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    Admin(#[from] AdminError),
+
+    #[error("{0}")]
+    Hook(#[from] HookError),
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -62,4 +76,46 @@ pub enum ContractError {
 
     #[error("Tokens are too few to execute")]
     TooFewTokens {},
-}
\ No newline at end of file
+
+    #[error("No tokens sent to burn")]
+    NoFunds {},
+
+    #[error("Sent Tokens {} are not burnable", denom)]
+    DisallowedDenom { denom: String },
+
+    #[error("Cw20 token {} is not burnable", address)]
+    DisallowedCw20 { address: String },
+
+    #[error("Burn amount of {} is below minimum {}", denom, min_amount)]
+    BelowMinimum { denom: String, min_amount: Uint128 },
+
+    #[error("Cannot migrate from contract {}", contract)]
+    WrongContract { contract: String },
+
+    #[error("Invalid contract version {}", version)]
+    InvalidVersion { version: String },
+
+    #[error("Cannot migrate from newer version {}", previous_version)]
+    CannotDowngrade { previous_version: String },
+
+    #[error("An admin is required when migrating from a version without admin")]
+    MissingAdmin {},
+
+    #[error("Burnable denoms are required when migrating from a version without admin")]
+    MissingBurnableDenoms {},
+
+    #[error("A burn schedule is already running")]
+    ScheduleExists {},
+
+    #[error("Schedule interval and end must both be heights or times")]
+    InvalidSchedule {},
+
+    #[error("No burn schedule")]
+    NoSchedule {},
+
+    #[error("No burn is due yet")]
+    NoBurnDue {},
+
+    #[error("Mint reward ratio must be above zero")]
+    InvalidRewardRatio {},
+}
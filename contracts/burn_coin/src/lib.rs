@@ -1,3 +1,4 @@
+pub mod state;
 pub mod contract;
 pub mod msg;
 mod error;
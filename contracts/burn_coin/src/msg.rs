@@ -1,16 +1,156 @@
 use serde::{Serialize, Deserialize};
 use schemars::JsonSchema;
+use cosmwasm_std::{to_binary, Addr, Binary, Coin, CosmosMsg, StdResult, Uint128, WasmMsg};
+use cw20::Cw20ReceiveMsg;
+use cw_utils::{Duration, Expiration, Scheduled};
+use crate::state::{BurnableDenom, BurnableCw20, BurnReceipt, BurnSchedule, MintReward};
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
-pub struct InstantiateMsg {}
+pub struct InstantiateMsg {
+    // defaults to the instantiator
+    pub admin: Option<String>,
+    pub burnable_denoms: Vec<BurnableDenom>,
+    pub burnable_cw20s: Vec<BurnableCw20>,
+}
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    BurnMyDarc {}
-    // Decompound {validator: String, amount: Uint128},
+    BurnMyDarc {},
+    // admin only, None leaves the contract without admin
+    UpdateAdmin { admin: Option<String> },
+    // admin only, `add` also replaces minimum of already burnable denoms
+    UpdateBurnableDenoms { add: Vec<BurnableDenom>, remove: Vec<String> },
+    // admin only, `remove` takes cw20 contract addresses
+    UpdateBurnableCw20s { add: Vec<BurnableCw20>, remove: Vec<String> },
+    Receive(Cw20ReceiveMsg),
+    // admin only, registered contracts receive BurnHookMsg on every burn
+    AddHook { addr: String },
+    RemoveHook { addr: String },
+    // admin only, deposit sent with this message is burned `amount` per `interval`
+    ScheduleBurn { amount: Coin, interval: Duration, until: Expiration },
+    // anyone can execute, burns what is due and refunds the deposit left when the schedule ends
+    Tick {},
+    // admin only, None stops minting rewards for burns of `denom`
+    SetMintReward { denom: String, reward: Option<MintReward> },
+}
+
+// message embedded in Cw20ReceiveMsg
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    Burn {},
+}
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct MigrateMsg {
+    // replaces the admin, required when migrating from a version without admin
+    pub admin: Option<String>,
+    // added to the burnable denoms, required when migrating from a version without admin,
+    // which burned any denom and has none stored
+    pub burnable_denoms: Option<Vec<BurnableDenom>>,
 }
+
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
+    Config {},
+    TotalBurned { denom: String },
+    BurnedBy { address: Addr },
+    TopBurners { denom: String, limit: Option<u32> },
+    BurnableDenoms {},
+    BurnableCw20s {},
+    Receipt { id: u64 },
+    Hooks {},
+    NextBurn {},
+    Schedule {},
+    MintReward { denom: String },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct MintRewardResponse {
+    pub denom: String,
+    pub reward: Option<MintReward>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct NextBurnResponse {
+    // None if no burn is left on the schedule
+    pub next: Option<Scheduled>,
+    pub amount: Option<Coin>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ScheduleResponse {
+    pub schedule: Option<BurnSchedule>,
+}
+
+/// BurnHookMsg should be de/serialized under `BurnHook()` variant in a ExecuteMsg.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnHookMsg {
+    pub receipt: BurnReceipt,
+}
+
+impl BurnHookMsg {
+    /// serializes the message
+    pub fn into_binary(self) -> StdResult<Binary> {
+        let msg = BurnExecuteMsg::BurnHook(self);
+        to_binary(&msg)
+    }
+
+    /// creates a cosmos_msg sending this struct to the named contract
+    pub fn into_cosmos_msg<T: Into<String>>(self, contract_addr: T) -> StdResult<CosmosMsg> {
+        let msg = self.into_binary()?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
 }
 
+// This is just a helper to properly serialize the above message
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum BurnExecuteMsg {
+    BurnHook(BurnHookMsg),
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ConfigResponse {
+    pub admin: Option<String>,
+    pub burnable_denoms: Vec<BurnableDenom>,
+    pub burnable_cw20s: Vec<BurnableCw20>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnableDenomsResponse {
+    pub denoms: Vec<BurnableDenom>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnableCw20sResponse {
+    pub tokens: Vec<BurnableCw20>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct TotalBurnedResponse {
+    pub denom: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnedByResponse {
+    pub address: Addr,
+    pub burned: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct TopBurnersResponse {
+    pub burners: Vec<Burner>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Burner {
+    pub address: Addr,
+    pub amount: Uint128,
+}
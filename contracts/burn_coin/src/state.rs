@@ -0,0 +1,109 @@
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use cw_storage_plus::{Item, Map, IndexedMap, MultiIndex, IndexList, Index};
+use cw_controllers::{Admin, Hooks};
+use cw_utils::{Duration, Expiration, Scheduled};
+use cw1155::TokenId;
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnableDenom {
+    pub denom: String,
+    pub min_amount: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnableCw20 {
+    // cw20 token contract
+    pub address: String,
+    pub min_amount: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnRecord {
+    pub burner: Addr,
+    pub denom: String,
+    pub amount: Uint128,
+}
+
+// proof of a single burn, ids are sequential starting from 1
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnReceipt {
+    pub id: u64,
+    pub burner: Addr,
+    pub coins: Vec<Coin>,
+    pub height: u64,
+    pub time: Timestamp,
+}
+
+// treasury deposit burned `amount` per `interval` until `until` or the deposit runs out
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BurnSchedule {
+    pub depositor: Addr,
+    pub amount: Coin,
+    pub interval: Duration,
+    pub next: Scheduled,
+    pub until: Expiration,
+    // deposit not burned yet
+    pub remaining: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardToken {
+    Cw20 { address: String },
+    // token id per campaign
+    Cw1155 { address: String, token_id: TokenId },
+}
+
+// burn_coin must be allowed to mint on the reward token contract
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct MintReward {
+    pub token: RewardToken,
+    // reward minted per burned unit, rounded down
+    pub ratio: Decimal,
+}
+
+pub const ADMIN : Admin = Admin::new("admin");
+// contracts notified with BurnHookMsg on every burn
+pub const HOOKS : Hooks = Hooks::new("burn_hooks");
+// denoms accepted for burning, keyed by denom
+pub const BURNABLE_DENOMS : Map<&str, BurnableDenom> = Map::new("burnable_denoms");
+// cw20 contracts accepted for burning, keyed by token contract address
+pub const BURNABLE_CW20S : Map<&Addr, BurnableCw20> = Map::new("burnable_cw20s");
+
+pub struct BurnIndexes<'a> {
+    // (denom, amount) lets the leaderboard iterate burners of a denom ordered by amount
+    pub amount: MultiIndex<'a, (String, u128), BurnRecord, (Addr, String)>,
+}
+
+impl<'a> IndexList<BurnRecord> for BurnIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<BurnRecord>> + '_> {
+        let v: Vec<&dyn Index<BurnRecord>> = vec![&self.amount];
+        Box::new(v.into_iter())
+    }
+}
+
+// cumulative burned amount per (burner, denom), cw20 tokens use their contract address as denom
+pub fn burns<'a>() -> IndexedMap<'a, (&'a Addr, &'a str), BurnRecord, BurnIndexes<'a>> {
+    let indexes = BurnIndexes {
+        amount: MultiIndex::new(
+            |record: &BurnRecord| (record.denom.clone(), record.amount.u128()),
+            "burns",
+            "burns__amount",
+        ),
+    };
+    IndexedMap::new("burns", indexes)
+}
+
+// cumulative burned amount per denom
+pub const TOTAL_BURNED : Map<&str, Uint128> = Map::new("total_burned");
+
+// last issued receipt id
+pub const RECEIPT_COUNT : Item<u64> = Item::new("receipt_count");
+pub const RECEIPTS : Map<u64, BurnReceipt> = Map::new("receipts");
+
+pub const SCHEDULE : Item<BurnSchedule> = Item::new("schedule");
+
+// reward minted to burners, keyed by burned denom (cw20 contract address for cw20 burns)
+pub const MINT_REWARDS : Map<&str, MintReward> = Map::new("mint_rewards");
@@ -1,18 +1,23 @@
 
 #[cfg(not(feature = "library"))]
-use cosmwasm_std::{to_binary, entry_point, Env, Deps, DepsMut, MessageInfo, Response, StdResult, Binary, Uint128, CosmosMsg, WasmMsg, Addr, SubMsg, ReplyOn, Reply, Decimal};
+use cosmwasm_std::{to_binary, entry_point, Env, Deps, DepsMut, MessageInfo, Response, StdResult, Binary, Uint128, CosmosMsg, WasmMsg, Addr, SubMsg, ReplyOn, Reply, Decimal, VoteOption, Storage, Order};
+use cw_storage_plus::Bound;
 use cw2::set_contract_version;
+use cw_utils::must_pay;
 use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20InstantiateMsg, MinterResponse};
-use crate::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, QueryStaked};
+use crate::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, QueryStaked, ProxyInfo, AllProxiesResponse};
 use crate::error::ContractError;
-use crate::state::{Config, CONFIG, PROXY, STAKEINFO, StakeInfo};
-use qstaking_proxy::msg::{InstantiateMsg as ProxyInstantiateMsg, ExecuteMsg as ProxyExecuteMsg};
+use crate::state::{proxies, Config, State, Unbonding, CONFIG, HEARTBEAT, STAKEINFO, STAKED, VOTES, STATE, UNBONDING};
+use qstaking_proxy::msg::{InstantiateMsg as ProxyInstantiateMsg, ExecuteMsg as ProxyExecuteMsg, HubStatusResponse, WeightedVoteOption};
 
 const CONTRACT_NAME: &str = "knstl_qstaking";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const TOKEN_INIT_ID : u64 = 1;
 const STAKE_INIT_ID : u64 = 2;
 
+const DEFAULT_LIMIT : u32 = 10;
+const MAX_LIMIT : u32 = 30;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -21,15 +26,24 @@ pub fn instantiate(
     msg: InstantiateMsg,    
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    if msg.commission_rate >= Decimal::one() {
+        return Err(ContractError::InvalidCommissionRate {});
+    }
 
     CONFIG.save(deps.storage, &Config{
+        owner: info.sender.clone(),
         native_denom: msg.denom,
         cw20contract : String::new(),
         stake_contract_id : msg.proxy_id,
         stake_contract_label: msg.proxy_label,
         commission_rate: msg.commission_rate,
         unbond_period: msg.unbond_period,
+        emergency: false,
+        emergency_timelock: msg.emergency_timelock,
+        default_vote: None,
     })?;
+    STATE.save(deps.storage, &State::default(), env.block.height)?;
+    HEARTBEAT.save(deps.storage, &env.block.time)?;
 
     let res = Response::new()
     .add_submessage(SubMsg { 
@@ -75,8 +89,15 @@ pub fn execute(
         ExecuteMsg::Collect {validator} => exec_handle_collect(deps, info, validator),
         ExecuteMsg::CollectAll {} => exec_handle_collect_all(deps, info),
         ExecuteMsg::Restake { from, to, amount } => exec_handle_redelegation(deps, info, from, to, amount),
-        ExecuteMsg::Withdraw {} => exec_handle_withdraw(deps, info),
+        ExecuteMsg::Withdraw {} => exec_handle_withdraw(deps, env, info),
         ExecuteMsg::Compound { validator, amount } => exec_handle_compound(deps, env, info, validator, amount),
+        ExecuteMsg::SetEmergency { enabled } => exec_set_emergency(deps, info, enabled),
+        ExecuteMsg::Vote { proposal_id, option } => exec_handle_vote(deps, info, proposal_id, vec![WeightedVoteOption { option, weight: Decimal::one() }]),
+        ExecuteMsg::VoteWeighted { proposal_id, options } => exec_handle_vote(deps, info, proposal_id, options),
+        ExecuteMsg::SetDefaultVote { option } => exec_set_default_vote(deps, info, option),
+        ExecuteMsg::CastDefaultVotes { proposal_id, start_after, limit } => exec_cast_default_votes(deps, info, proposal_id, start_after, limit),
+        ExecuteMsg::ProxyExited {} => exec_proxy_exited(deps, env, info),
+        ExecuteMsg::Heartbeat {} => exec_heartbeat(deps, env, info),
     }
 }
 
@@ -98,8 +119,10 @@ fn exec_register(
             msg: to_binary(&ProxyInstantiateMsg {
                 denom: config.native_denom,
                 owner: info.sender,
+                derivative: config.cw20contract,
                 commission_rate: config.commission_rate,
                 unbond_period: config.unbond_period,
+                emergency_timelock: config.emergency_timelock,
             })?, 
             funds: vec![], 
             label: config.stake_contract_label,
@@ -118,29 +141,20 @@ fn exec_handle_stake(
     validator: String,
 )-> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    let proxy = PROXY.load(deps.storage, &info.sender)?;
-    if info.funds.len() > 1 {
-        return Err(ContractError::InvalidMultipleTokens {})
-    }
-    let received = info.funds.first().unwrap();
-    if received.denom != config.native_denom {
-        return Err(ContractError::UnstakeableTokenSent { denom: received.denom.clone() });
-    }
-    
-    match STAKEINFO.may_load(deps.storage, (&info.sender, validator.clone()))? {
-        Some(w) => {
-            STAKEINFO.save(deps.storage, (&info.sender, validator.clone()), &StakeInfo {
-                compounded: w.compounded,
-                staked: w.staked + received.amount, // to checked_add / normal
-            })?;
-        },
-        None => {            
-            STAKEINFO.save(deps.storage, (&info.sender, validator.clone()), &StakeInfo {
-                compounded: Uint128::zero(),
-                staked: received.amount,
-            })?;
-        },
-    }
+    let proxy = load_proxy(deps.storage, &info.sender)?;
+    let amount = must_pay(&info, &config.native_denom)?;
+
+    let mut stake_info = STAKEINFO.may_load(deps.storage, (&info.sender, validator.clone()))?.unwrap_or_default();
+    stake_info.staked = stake_info.staked.checked_add(amount)?;
+    STAKEINFO.save(deps.storage, (&info.sender, validator.clone()), &stake_info)?;
+    STAKED.update(deps.storage, &info.sender, env.block.height, |x| -> StdResult<_> {
+        Ok(x.unwrap_or_default().checked_add(amount)?)
+    })?;
+    update_state(deps.storage, env.block.height, |state| {
+        state.total_staked = state.total_staked.checked_add(amount)?;
+        state.derivative_supply = state.derivative_supply.checked_add(amount)?;
+        Ok(())
+    })?;
     let res = Response::new()
     .add_message(CosmosMsg::Wasm(
         WasmMsg::Execute { 
@@ -152,7 +166,7 @@ fn exec_handle_stake(
         contract_addr: config.cw20contract, 
         msg: to_binary(&Cw20ExecuteMsg::Mint { 
             recipient: info.sender.to_string(),
-            amount: received.amount,
+            amount,
         })?, 
         funds: vec![],
     }))
@@ -172,34 +186,42 @@ fn exec_handle_unstake(
     amount: Uint128,
 )->Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    let stake_info = STAKEINFO.load(deps.storage, (&info.sender, validator.clone()))?; //
-    let proxy = PROXY.load(deps.storage, &info.sender)?;
+    let proxy = load_proxy(deps.storage, &info.sender)?;
+    let mut stake_info = STAKEINFO
+        .may_load(deps.storage, (&info.sender, validator.clone()))?
+        .ok_or(ContractError::InvalidUnstake {})?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
     if amount > stake_info.staked {
         return Err(ContractError::InvalidUnstakeAmount {});
     }
     let redeem_rate = Decimal::from_ratio(amount, stake_info.staked);
+    let decompound_amount = stake_info.compounded * redeem_rate;
+    stake_info.staked = stake_info.staked.checked_sub(amount)?;
+    stake_info.compounded = stake_info.compounded.checked_sub(decompound_amount)?;
+    STAKEINFO.save(deps.storage, (&info.sender, validator.clone()), &stake_info)?;
+    let unbonding = amount.checked_add(decompound_amount)?;
+    UNBONDING.update(deps.storage, &info.sender, |x| -> StdResult<_> {
+        let mut unbondings = x.unwrap_or_default();
+        unbondings.push(Unbonding { amount: unbonding, complete_date: env.block.time.plus_seconds(config.unbond_period) });
+        Ok(unbondings)
+    })?;
+    STAKED.update(deps.storage, &info.sender, env.block.height, |x| -> StdResult<_> {
+        Ok(x.unwrap_or_default().checked_sub(amount)?)
+    })?;
+    update_state(deps.storage, env.block.height, |state| {
+        state.total_staked = state.total_staked.checked_sub(amount)?;
+        state.total_compounded = state.total_compounded.checked_sub(decompound_amount)?;
+        state.total_unbonding = state.total_unbonding.checked_add(unbonding)?;
+        state.derivative_supply = state.derivative_supply.checked_sub(amount)?;
+        Ok(())
+    })?;
+
     let res = 
-    if stake_info.compounded == Uint128::zero() {
-        STAKEINFO.update(
-            deps.storage, (&info.sender, validator.clone()),
-            |x| -> StdResult<_> {
-                let mut ret = x.unwrap();
-                ret.staked = ret.staked.checked_sub(amount).unwrap();
-                Ok(ret)
-            }
-        )?;
+    if decompound_amount.is_zero() {
         Response::new()
     } else {
-        let decompound_amount = stake_info.compounded * redeem_rate;
-        STAKEINFO.update(
-            deps.storage, (&info.sender, validator.clone()),
-            |x| -> StdResult<_> {
-                let mut ret = x.unwrap();
-                ret.staked = ret.staked.checked_sub(amount).unwrap();
-                ret.compounded = ret.compounded.checked_sub(decompound_amount).unwrap();
-                Ok(ret)
-            }
-        )?;
         Response::new()                
         .add_message(WasmMsg::Execute { 
             contract_addr: proxy.clone(),
@@ -226,9 +248,22 @@ fn exec_handle_unstake(
 
 fn exec_handle_withdraw(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
 ) -> Result<Response, ContractError> {
-    let proxy = PROXY.load(deps.storage, &info.sender)?;
+    let proxy = load_proxy(deps.storage, &info.sender)?;
+    // the proxy pays out every unbonding completed by now
+    let (completed, pending): (Vec<_>, Vec<_>) = UNBONDING
+        .may_load(deps.storage, &info.sender)?
+        .unwrap_or_default()
+        .into_iter()
+        .partition(|x| env.block.time >= x.complete_date);
+    let withdrawn = completed.iter().map(|x| x.amount).sum::<Uint128>();
+    UNBONDING.save(deps.storage, &info.sender, &pending)?;
+    update_state(deps.storage, env.block.height, |state| {
+        state.total_unbonding = state.total_unbonding.saturating_sub(withdrawn);
+        Ok(())
+    })?;
 
     let res = Response::new()
     .add_message(CosmosMsg::Wasm({
@@ -251,37 +286,35 @@ fn exec_handle_redelegation(
     to: String,
     amount: Uint128,
 ) -> Result<Response, ContractError> { 
-    let from_stake_info = STAKEINFO.load(deps.storage, (&info.sender, from.clone()))?;
-    let proxy = PROXY.load(deps.storage, &info.sender)?;
+    let proxy = load_proxy(deps.storage, &info.sender)?;
+    if from == to {
+        return Err(ContractError::InvalidRequest {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let mut from_stake_info = STAKEINFO
+        .may_load(deps.storage, (&info.sender, from.clone()))?
+        .ok_or(ContractError::InvalidUnstake {})?;
     if from_stake_info.staked < amount {
             return Err(ContractError::NotEnoughTokens {});
     }
-    STAKEINFO.update(deps.storage, (&info.sender, from.clone()), |x| -> StdResult<_> {
-        let mut ret = x.unwrap();
-        ret.staked = ret.staked.checked_sub(amount).unwrap();
-        Ok(ret)
-    })?;
-    match STAKEINFO.has(deps.storage, (&info.sender, to.clone())) {
-        true => {
-            STAKEINFO.update(deps.storage, (&info.sender, to.clone()), |x| -> StdResult<_> {
-            let mut ret = x.unwrap();
-            ret.staked = ret.staked.checked_add(amount).unwrap();
-            Ok(ret)
-        })?;
-        },
-        false => {
-            STAKEINFO.save(deps.storage, (&info.sender, to.clone()), &StakeInfo { 
-                compounded: Uint128::zero(), 
-                staked: amount,
-            })?;
-        },
-    }
+    // compounded tokens are delegated to the same validator, so they move along proportionally
+    let compounded = from_stake_info.compounded * Decimal::from_ratio(amount, from_stake_info.staked);
+    from_stake_info.staked = from_stake_info.staked.checked_sub(amount)?;
+    from_stake_info.compounded = from_stake_info.compounded.checked_sub(compounded)?;
+    STAKEINFO.save(deps.storage, (&info.sender, from.clone()), &from_stake_info)?;
+
+    let mut to_stake_info = STAKEINFO.may_load(deps.storage, (&info.sender, to.clone()))?.unwrap_or_default();
+    to_stake_info.staked = to_stake_info.staked.checked_add(amount)?;
+    to_stake_info.compounded = to_stake_info.compounded.checked_add(compounded)?;
+    STAKEINFO.save(deps.storage, (&info.sender, to.clone()), &to_stake_info)?;
 
     let res = Response::new()
     .add_message(CosmosMsg::Wasm(
         WasmMsg::Execute { 
             contract_addr: proxy,
-            msg: to_binary(&ProxyExecuteMsg::Restake { from, to, amount })?,
+            msg: to_binary(&ProxyExecuteMsg::Restake { from, to, amount, compounded })?,
             funds: vec![],
     }))
     ;
@@ -293,7 +326,7 @@ fn exec_handle_collect(
     info: MessageInfo,
     validator: String,
 ) -> Result<Response, ContractError> {
-    let proxy = PROXY.load(deps.storage, &info.sender)?;
+    let proxy = load_proxy(deps.storage, &info.sender)?;
 
     let res = Response::new()
     .add_message(WasmMsg::Execute { 
@@ -313,18 +346,19 @@ fn exec_handle_collect_all(
     info: MessageInfo,
 ) -> Result<Response, ContractError> {
     
-    let proxy = PROXY.load(deps.storage, &info.sender)?;
+    let proxy = load_proxy(deps.storage, &info.sender)?;
     let mut withdraw_msgs: Vec<CosmosMsg> = vec![];
 
-    let keys = STAKEINFO
-        .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending);
+    let stake_infos = STAKEINFO
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
     
-    for key in keys {
-        let v = key.unwrap().1;
-        if !STAKEINFO.load(deps.storage, (&info.sender, v.clone()))?.staked.is_zero() {
+    for (validator, stake_info) in stake_infos {
+        if !stake_info.staked.is_zero() {
         withdraw_msgs.push(CosmosMsg::Wasm({WasmMsg::Execute { 
             contract_addr: proxy.clone(),
-            msg: to_binary(&ProxyExecuteMsg::Collect { validator: v.clone() })?, 
+            msg: to_binary(&ProxyExecuteMsg::Collect { validator })?, 
             funds: vec![],
         }}))}
     }
@@ -339,18 +373,22 @@ fn exec_handle_collect_all(
 
 fn exec_handle_compound(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     validator: String,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
-    let proxy = PROXY.load(deps.storage, &info.sender)?;
+    let proxy = load_proxy(deps.storage, &info.sender)?;
 
-    STAKEINFO.update(deps.storage, (&info.sender, validator.clone()), |x| -> StdResult<_> {
-        let mut ret = x.unwrap();
-        ret.compounded += amount;
+    STAKEINFO.update(deps.storage, (&info.sender, validator.clone()), |x| -> Result<_, ContractError> {
+        let mut ret = x.ok_or(ContractError::InvalidCompound {})?;
+        ret.compounded = ret.compounded.checked_add(amount)?;
         Ok(ret)
     })?;
+    update_state(deps.storage, env.block.height, |state| {
+        state.total_compounded = state.total_compounded.checked_add(amount)?;
+        Ok(())
+    })?;
 
     let res = Response::new()
     .add_message(CosmosMsg::Wasm(
@@ -365,16 +403,211 @@ fn exec_handle_compound(
     Ok(res)
 }
 
+// only the owner keeps the hub alive, otherwise anyone could hold off emergency exits forever
+fn exec_heartbeat(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    HEARTBEAT.save(deps.storage, &env.block.time)?;
+    let res = Response::new()
+    .add_attribute("action", "heartbeat")
+    .add_attribute("time", env.block.time.to_string())
+    ;
+    Ok(res)
+}
+
+fn exec_set_emergency(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        if info.sender != config.owner {
+            return Err(ContractError::Unauthorized {});
+        }
+        config.emergency = enabled;
+        Ok(config)
+    })?;
+
+    let res = Response::new()
+    .add_attribute("action", "set_emergency")
+    .add_attribute("enabled", enabled.to_string())
+    ;
+    Ok(res)
+}
+
+fn exec_handle_vote(
+    deps: DepsMut,
+    info: MessageInfo,
+    proposal_id: u64,
+    options: Vec<WeightedVoteOption>,
+) -> Result<Response, ContractError> {
+    let proxy = load_proxy(deps.storage, &info.sender)?;
+    VOTES.save(deps.storage, (proposal_id, &info.sender), &options)?;
+
+    // a single full-weight option is sent as a plain vote
+    let msg = match options.as_slice() {
+        [single] if single.weight == Decimal::one() => ProxyExecuteMsg::Vote { proposal_id, option: single.option.clone() },
+        _ => ProxyExecuteMsg::VoteWeighted { proposal_id, options },
+    };
+    let res = Response::new()
+    .add_message(WasmMsg::Execute {
+        contract_addr: proxy,
+        msg: to_binary(&msg)?,
+        funds: vec![],
+    })
+    .add_attribute("action", "vote")
+    .add_attribute("from", &info.sender)
+    .add_attribute("proposal_id", proposal_id.to_string())
+    ;
+    Ok(res)
+}
+
+fn exec_set_default_vote(
+    deps: DepsMut,
+    info: MessageInfo,
+    option: Option<VoteOption>,
+) -> Result<Response, ContractError> {
+    CONFIG.update(deps.storage, |mut config| -> Result<_, ContractError> {
+        if info.sender != config.owner {
+            return Err(ContractError::Unauthorized {});
+        }
+        config.default_vote = option;
+        Ok(config)
+    })?;
+
+    let res = Response::new()
+    .add_attribute("action", "set_default_vote")
+    ;
+    Ok(res)
+}
+
+fn exec_cast_default_votes(
+    deps: DepsMut,
+    info: MessageInfo,
+    proposal_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let option = config.default_vote.ok_or(ContractError::NoDefaultVote {})?;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+    let registered = proxies()
+        .range(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut vote_msgs: Vec<CosmosMsg> = vec![];
+    for (user, proxy) in registered {
+        if VOTES.has(deps.storage, (proposal_id, &user)) {
+            continue
+        }
+        vote_msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: proxy,
+            msg: to_binary(&ProxyExecuteMsg::Vote { proposal_id, option: option.clone() })?,
+            funds: vec![],
+        }));
+    }
+
+    let res = Response::new()
+    .add_attribute("action", "cast_default_votes")
+    .add_attribute("proposal_id", proposal_id.to_string())
+    .add_attribute("count", vote_msgs.len().to_string())
+    .add_messages(vote_msgs)
+    ;
+    Ok(res)
+}
+
+// the proxy has burned the owner's qdarc and undelegated everything on its own, so the owner's
+// stake is dropped and the proxy detached, the owner may register again for a new proxy
+fn exec_proxy_exited(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let owner = query_owner_of_proxy(deps.as_ref(), info.sender.to_string())?
+        .ok_or(ContractError::Unauthorized {})?;
+
+    let stake_infos = STAKEINFO
+        .prefix(&owner)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut staked = Uint128::zero();
+    let mut compounded = Uint128::zero();
+    for (validator, stake_info) in stake_infos {
+        staked = staked.checked_add(stake_info.staked)?;
+        compounded = compounded.checked_add(stake_info.compounded)?;
+        STAKEINFO.remove(deps.storage, (&owner, validator));
+    }
+    let unbonding = UNBONDING
+        .may_load(deps.storage, &owner)?
+        .unwrap_or_default()
+        .iter()
+        .map(|x| x.amount)
+        .sum::<Uint128>();
+    UNBONDING.remove(deps.storage, &owner);
+    STAKED.remove(deps.storage, &owner, env.block.height)?;
+    update_state(deps.storage, env.block.height, |state| {
+        state.total_staked = state.total_staked.checked_sub(staked)?;
+        state.total_compounded = state.total_compounded.checked_sub(compounded)?;
+        state.total_unbonding = state.total_unbonding.saturating_sub(unbonding);
+        state.derivative_supply = state.derivative_supply.checked_sub(staked)?;
+        state.registered_users = state.registered_users.saturating_sub(1);
+        Ok(())
+    })?;
+    proxies().remove(deps.storage, &owner)?;
+
+    let res = Response::new()
+    .add_attribute("action", "proxy_exited")
+    .add_attribute("owner", &owner)
+    .add_attribute("proxy", &info.sender)
+    .add_attribute("staked", staked)
+    ;
+    Ok(res)
+}
+
+// applies `action` to the current totals and snapshots them at `height`
+fn update_state<A>(
+    storage: &mut dyn Storage,
+    height: u64,
+    action: A,
+) -> Result<State, ContractError>
+where
+    A: FnOnce(&mut State) -> Result<(), ContractError>,
+{
+    STATE.update(storage, height, |state| -> Result<_, ContractError> {
+        let mut state = state.unwrap_or_default();
+        action(&mut state)?;
+        Ok(state)
+    })
+}
+
+fn load_proxy(
+    storage: &dyn Storage,
+    user: &Addr,
+) -> Result<String, ContractError> {
+    proxies().may_load(storage, user)?.ok_or(ContractError::UnregisteredUser {})
+}
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: Reply,
 ) -> Result<Response, ContractError> {
     match msg.id {
         TOKEN_INIT_ID => handle_token_init (deps, msg),
-        STAKE_INIT_ID => handle_stake_init (deps, msg),
+        STAKE_INIT_ID => handle_stake_init (deps, env, msg),
         _ => Err(ContractError::Unauthorized {}),
     }
 }
@@ -382,8 +615,11 @@ fn handle_token_init (
     deps: DepsMut,
     msg: Reply,
 ) -> Result<Response, ContractError> {
+    let cw20contract = cw_utils::parse_reply_instantiate_data(msg)
+        .map_err(|_| ContractError::InvalidSubmsg {})?
+        .contract_address;
     CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
-        config.cw20contract = cw_utils::parse_reply_instantiate_data(msg).unwrap().contract_address;
+        config.cw20contract = cw20contract;
         Ok(config)
     })?;
     Ok(Response::default())
@@ -391,9 +627,10 @@ fn handle_token_init (
 
 fn handle_stake_init(
     deps: DepsMut,
+    env: Env,
     msg: Reply,
 ) -> Result<Response, ContractError> {
-    let res = msg.clone().result.into_result().unwrap();
+    let res = msg.result.clone().into_result().map_err(|_| ContractError::InvalidSubmsg {})?;
     
     let mut owner = String::new();
     for event in res.events {
@@ -407,10 +644,17 @@ fn handle_stake_init(
         return Err(ContractError::InvalidSubmsg {});
     }
     
-    PROXY.save(deps.storage, 
-        &deps.api.addr_validate(&owner).unwrap(), 
-        &cw_utils::parse_reply_instantiate_data(msg).unwrap().contract_address
-    )?;
+    let proxy = cw_utils::parse_reply_instantiate_data(msg)
+        .map_err(|_| ContractError::InvalidSubmsg {})?
+        .contract_address;
+    let owner = deps.api.addr_validate(&owner)?;
+    if !proxies().has(deps.storage, &owner) {
+        update_state(deps.storage, env.block.height, |state| {
+            state.registered_users += 1;
+            Ok(())
+        })?;
+    }
+    proxies().save(deps.storage, &owner, &proxy)?;
     Ok(Response::default())
 }
 
@@ -427,13 +671,22 @@ pub fn query(
         QueryMsg::Staked { address } => to_binary(&query_stake_amount(deps, address)?),
         QueryMsg::TokenInfo { address } => to_binary(&query_reward_token_amount(deps, address)?),
         QueryMsg::ProxyAddress { address } => to_binary(&query_proxy_address(deps, address)?),
+        QueryMsg::AllProxies { start_after, limit } => to_binary(&query_all_proxies(deps, start_after, limit)?),
+        QueryMsg::OwnerOfProxy { proxy } => to_binary(&query_owner_of_proxy(deps, proxy)?),
+        QueryMsg::Emergency {} => to_binary(&query_emergency(deps)?),
+        QueryMsg::Status {} => to_binary(&query_status(deps)?),
+        QueryMsg::Vote { proposal_id, address } => to_binary(&query_vote(deps, proposal_id, address)?),
+        QueryMsg::State {} => to_binary(&STATE.may_load(deps.storage)?.unwrap_or_default()),
+        QueryMsg::StakedAtHeight { address, height } => to_binary(&STAKED.may_load_at_height(deps.storage, &address, height)?.unwrap_or_default()),
+        QueryMsg::TotalStakedAtHeight { height } => to_binary(&query_total_staked_at_height(deps, height)?),
+        QueryMsg::StateAtHeight { height } => to_binary(&STATE.may_load_at_height(deps.storage, height)?.unwrap_or_default()),
     }
 }
 fn query_stake_amount(deps: Deps, address: Addr)-> StdResult<Vec<QueryStaked>>{
-    let iter  = STAKEINFO.prefix(&address).range(deps.storage, None, None, cosmwasm_std::Order::Ascending).into_iter();
+    let iter  = STAKEINFO.prefix(&address).range(deps.storage, None, None, cosmwasm_std::Order::Ascending);
     let mut ret = vec![];
     for item in iter {
-        let x = item.unwrap();
+        let x = item?;
         ret.push(QueryStaked {  
             validator: x.0,
             staked: x.1.staked, 
@@ -444,22 +697,417 @@ fn query_stake_amount(deps: Deps, address: Addr)-> StdResult<Vec<QueryStaked>>{
 }
 fn query_reward_token_amount(deps: Deps, address: Addr) -> StdResult<String> {
     let config = CONFIG.load(deps.storage)?;
-    Ok(deps.querier.query_wasm_smart(
+    deps.querier.query_wasm_smart(
         config.cw20contract,
         &Cw20QueryMsg::Balance { address: address.into() },
-    )?)
+    )
 }
 fn query_config(deps: Deps) -> StdResult<Config> {
     let config = CONFIG.load(deps.storage)?;
     Ok(config)
 }
 fn query_account_info(deps: Deps, address: Addr) -> StdResult<bool> {
-    Ok(PROXY.has(deps.storage, &address))
+    Ok(proxies().has(deps.storage, &address))
 }
-fn query_proxy_address(deps: Deps, address: Addr) -> StdResult<String> {
-    if PROXY.has(deps.storage, &address) {
-        Ok(PROXY.load(deps.storage, &address)?)
-    } else {
-        Ok(String::new())
+fn query_proxy_address(deps: Deps, address: Addr) -> StdResult<Option<Addr>> {
+    Ok(proxies().may_load(deps.storage, &address)?.map(Addr::unchecked))
+}
+fn query_all_proxies(deps: Deps, start_after: Option<String>, limit: Option<u32>) -> StdResult<AllProxiesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+    let proxies = proxies()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(owner, proxy)| ProxyInfo { owner, proxy: Addr::unchecked(proxy) }))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(AllProxiesResponse { proxies })
+}
+fn query_owner_of_proxy(deps: Deps, proxy: String) -> StdResult<Option<Addr>> {
+    let owner = proxies()
+        .idx
+        .proxy
+        .item(deps.storage, proxy)?
+        .map(|(owner, _)| String::from_utf8(owner).map(Addr::unchecked))
+        .transpose()?;
+    Ok(owner)
+}
+fn query_total_staked_at_height(deps: Deps, height: u64) -> StdResult<Uint128> {
+    let state = STATE.may_load_at_height(deps.storage, height)?.unwrap_or_default();
+    Ok(state.total_staked)
+}
+fn query_emergency(deps: Deps) -> StdResult<bool> {
+    Ok(CONFIG.load(deps.storage)?.emergency)
+}
+fn query_status(deps: Deps) -> StdResult<HubStatusResponse> {
+    Ok(HubStatusResponse {
+        emergency: CONFIG.load(deps.storage)?.emergency,
+        last_heartbeat: HEARTBEAT.load(deps.storage)?,
+    })
+}
+fn query_vote(deps: Deps, proposal_id: u64, address: Addr) -> StdResult<Option<Vec<WeightedVoteOption>>> {
+    VOTES.may_load(deps.storage, (proposal_id, &address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{coin, coins, from_binary, OwnedDeps, SubMsgResponse, SubMsgResult};
+    use cw_utils::PaymentError;
+    use crate::msg::QueryStaked;
+
+    const OWNER: &str = "owner";
+    const USER: &str = "user";
+    const USER_PROXY: &str = "userproxy";
+
+    fn instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            denom: "udarc".to_string(),
+            cw20_id: 1,
+            cw20_label: "qdarc".to_string(),
+            token_name: "qdarc".to_string(),
+            token_symbol: "qdarc".to_string(),
+            proxy_id: 2,
+            proxy_label: "knstl_qstaking_proxy".to_string(),
+            commission_rate: Decimal::percent(15),
+            unbond_period: 120,
+            emergency_timelock: 86400,
+        }
+    }
+
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), instantiate_msg()).unwrap();
+        CONFIG.update(&mut deps.storage, |mut config| -> StdResult<_> {
+            config.cw20contract = "qdarc".to_string();
+            Ok(config)
+        }).unwrap();
+        register(&mut deps, USER, USER_PROXY);
+        deps
+    }
+
+    fn register(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str, proxy: &str) {
+        proxies().save(&mut deps.storage, &Addr::unchecked(user), &proxy.to_string()).unwrap();
+    }
+
+    fn proxy_msg(contract_addr: &str, msg: &ProxyExecuteMsg) -> CosmosMsg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(msg).unwrap(),
+            funds: vec![],
+        })
+    }
+
+    #[test]
+    fn vote_is_relayed_and_recorded() {
+        let mut deps = setup();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::Vote { proposal_id: 3, option: VoteOption::Abstain }).unwrap();
+        assert_eq!(res.messages[0].msg, proxy_msg(USER_PROXY, &ProxyExecuteMsg::Vote { proposal_id: 3, option: VoteOption::Abstain }));
+
+        let options = vec![
+            WeightedVoteOption { option: VoteOption::Yes, weight: Decimal::percent(60) },
+            WeightedVoteOption { option: VoteOption::No, weight: Decimal::percent(40) },
+        ];
+        let res = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::VoteWeighted { proposal_id: 4, options: options.clone() }).unwrap();
+        assert_eq!(res.messages[0].msg, proxy_msg(USER_PROXY, &ProxyExecuteMsg::VoteWeighted { proposal_id: 4, options: options.clone() }));
+
+        let vote: Option<Vec<WeightedVoteOption>> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Vote { proposal_id: 4, address: Addr::unchecked(USER) }).unwrap()).unwrap();
+        assert_eq!(vote, Some(options));
+        let vote: Option<Vec<WeightedVoteOption>> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Vote { proposal_id: 5, address: Addr::unchecked(USER) }).unwrap()).unwrap();
+        assert_eq!(vote, None);
+    }
+
+    #[test]
+    fn default_votes_skip_users_who_voted() {
+        let mut deps = setup();
+        register(&mut deps, "user2", "user2proxy");
+        register(&mut deps, "user3", "user3proxy");
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::CastDefaultVotes { proposal_id: 1, start_after: None, limit: None }).unwrap_err();
+        assert_eq!(err, ContractError::NoDefaultVote {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::SetDefaultVote { option: Some(VoteOption::Abstain) }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::SetDefaultVote { option: Some(VoteOption::Abstain) }).unwrap();
+
+        execute(deps.as_mut(), mock_env(), mock_info("user2", &[]), ExecuteMsg::Vote { proposal_id: 1, option: VoteOption::Yes }).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::CastDefaultVotes { proposal_id: 1, start_after: None, limit: None }).unwrap();
+        let msgs: Vec<_> = res.messages.into_iter().map(|m| m.msg).collect();
+        let default_vote = ProxyExecuteMsg::Vote { proposal_id: 1, option: VoteOption::Abstain };
+        assert_eq!(msgs, vec![proxy_msg(USER_PROXY, &default_vote), proxy_msg("user3proxy", &default_vote)]);
+
+        // paginated by user address
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::CastDefaultVotes { proposal_id: 1, start_after: Some("user2".to_string()), limit: Some(1) }).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, proxy_msg("user3proxy", &default_vote));
+    }
+
+    fn stake(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, validator: &str, amount: u128) {
+        execute(deps.as_mut(), mock_env(), mock_info(USER, &coins(amount, "udarc")), ExecuteMsg::Stake { validator: validator.to_string() }).unwrap();
+    }
+
+    #[test]
+    fn instantiate_rejects_full_commission() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg { commission_rate: Decimal::one(), ..instantiate_msg() };
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCommissionRate {});
+    }
+
+    #[test]
+    fn stake_rejects_invalid_funds() {
+        let mut deps = setup();
+        let msg = ExecuteMsg::Stake { validator: "validator".to_string() };
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("unregistered", &coins(100, "udarc")), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::UnregisteredUser {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &coins(100, "uother")), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MissingDenom("udarc".to_string())));
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[coin(100, "udarc"), coin(100, "uother")]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MultipleDenoms {}));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unstake_rejects_invalid_amounts() {
+        let mut deps = setup();
+        let unstake = |validator: &str, amount: u128| ExecuteMsg::Unstake { validator: validator.to_string(), amount: Uint128::new(amount) };
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), unstake("validator", 100)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidUnstake {});
+
+        stake(&mut deps, "validator", 100);
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), unstake("validator", 0)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidZeroAmount {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), unstake("validator", 101)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidUnstakeAmount {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info("unregistered", &[]), unstake("validator", 100)).unwrap_err();
+        assert_eq!(err, ContractError::UnregisteredUser {});
+
+        // unstaking everything leaves an empty stake, unstaking again must not panic
+        execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), unstake("validator", 100)).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), unstake("validator", 0)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidZeroAmount {});
+    }
+
+    #[test]
+    fn restake_rejects_invalid_amounts() {
+        let mut deps = setup();
+        let restake = |from: &str, to: &str, amount: u128| ExecuteMsg::Restake { from: from.to_string(), to: to.to_string(), amount: Uint128::new(amount) };
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), restake("validator", "validator2", 100)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidUnstake {});
+
+        stake(&mut deps, "validator", 100);
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), restake("validator", "validator2", 101)).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughTokens {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), restake("validator", "validator", 100)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidRequest {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), restake("validator", "validator2", 0)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidZeroAmount {});
+
+        execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), restake("validator", "validator2", 40)).unwrap();
+        let staked: Vec<QueryStaked> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Staked { address: Addr::unchecked(USER) }).unwrap()).unwrap();
+        assert_eq!(staked, vec![
+            QueryStaked { validator: "validator".to_string(), staked: Uint128::new(60), compounded: Uint128::zero() },
+            QueryStaked { validator: "validator2".to_string(), staked: Uint128::new(40), compounded: Uint128::zero() },
+        ]);
+    }
+
+    #[test]
+    fn compound_requires_delegation() {
+        let mut deps = setup();
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::Compound { validator: "validator".to_string(), amount: Uint128::new(10) }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCompound {});
+    }
+
+    #[test]
+    fn collect_all_only_collects_own_delegations() {
+        let mut deps = setup();
+        register(&mut deps, "user2", "user2proxy");
+        stake(&mut deps, "validator", 100);
+        execute(deps.as_mut(), mock_env(), mock_info("user2", &coins(100, "udarc")), ExecuteMsg::Stake { validator: "validator2".to_string() }).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::CollectAll {}).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, proxy_msg(USER_PROXY, &ProxyExecuteMsg::Collect { validator: "validator".to_string() }));
+    }
+
+    #[test]
+    fn reply_rejects_failed_or_malformed_submessage() {
+        let mut deps = setup();
+
+        let err = reply(deps.as_mut(), mock_env(), Reply { id: STAKE_INIT_ID, result: SubMsgResult::Err("failed".to_string()) }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSubmsg {});
+        let err = reply(deps.as_mut(), mock_env(), Reply { id: TOKEN_INIT_ID, result: SubMsgResult::Ok(SubMsgResponse { events: vec![], data: None }) }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSubmsg {});
+    }
+
+    #[test]
+    fn restake_carries_compounded_proportionally() {
+        let mut deps = setup();
+        stake(&mut deps, "validator", 100);
+        execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::Compound { validator: "validator".to_string(), amount: Uint128::new(30) }).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::Restake {
+            from: "validator".to_string(),
+            to: "validator2".to_string(),
+            amount: Uint128::new(40),
+        }).unwrap();
+        assert_eq!(res.messages[0].msg, proxy_msg(USER_PROXY, &ProxyExecuteMsg::Restake {
+            from: "validator".to_string(),
+            to: "validator2".to_string(),
+            amount: Uint128::new(40),
+            compounded: Uint128::new(12),
+        }));
+
+        let staked: Vec<QueryStaked> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Staked { address: Addr::unchecked(USER) }).unwrap()).unwrap();
+        assert_eq!(staked, vec![
+            QueryStaked { validator: "validator".to_string(), staked: Uint128::new(60), compounded: Uint128::new(18) },
+            QueryStaked { validator: "validator2".to_string(), staked: Uint128::new(40), compounded: Uint128::new(12) },
+        ]);
+    }
+
+    #[test]
+    fn proxies_are_paginated_and_reverse_looked_up() {
+        let mut deps = setup();
+        register(&mut deps, "user2", "user2proxy");
+        register(&mut deps, "user3", "user3proxy");
+
+        let proxy: Option<Addr> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ProxyAddress { address: Addr::unchecked(USER) }).unwrap()).unwrap();
+        assert_eq!(proxy, Some(Addr::unchecked(USER_PROXY)));
+        let proxy: Option<Addr> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ProxyAddress { address: Addr::unchecked("unknown") }).unwrap()).unwrap();
+        assert_eq!(proxy, None);
+
+        let res: AllProxiesResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::AllProxies { start_after: None, limit: Some(2) }).unwrap()).unwrap();
+        assert_eq!(res.proxies, vec![
+            ProxyInfo { owner: Addr::unchecked(USER), proxy: Addr::unchecked(USER_PROXY) },
+            ProxyInfo { owner: Addr::unchecked("user2"), proxy: Addr::unchecked("user2proxy") },
+        ]);
+        let res: AllProxiesResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::AllProxies { start_after: Some("user2".to_string()), limit: None }).unwrap()).unwrap();
+        assert_eq!(res.proxies, vec![ProxyInfo { owner: Addr::unchecked("user3"), proxy: Addr::unchecked("user3proxy") }]);
+
+        let owner: Option<Addr> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::OwnerOfProxy { proxy: "user2proxy".to_string() }).unwrap()).unwrap();
+        assert_eq!(owner, Some(Addr::unchecked("user2")));
+        let owner: Option<Addr> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::OwnerOfProxy { proxy: "unknown".to_string() }).unwrap()).unwrap();
+        assert_eq!(owner, None);
+    }
+
+    #[test]
+    fn state_tracks_totals_per_height() {
+        let mut deps = setup();
+        let at = |blocks: u64, seconds: u64| {
+            let mut env = mock_env();
+            env.block.height += blocks;
+            env.block.time = env.block.time.plus_seconds(seconds);
+            env
+        };
+        execute(deps.as_mut(), at(1, 0), mock_info(USER, &coins(100, "udarc")), ExecuteMsg::Stake { validator: "validator".to_string() }).unwrap();
+        execute(deps.as_mut(), at(1, 0), mock_info(USER, &[]), ExecuteMsg::Compound { validator: "validator".to_string(), amount: Uint128::new(20) }).unwrap();
+        execute(deps.as_mut(), at(2, 0), mock_info(USER, &[]), ExecuteMsg::Unstake { validator: "validator".to_string(), amount: Uint128::new(50) }).unwrap();
+
+        let state: State = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+        assert_eq!(state, State {
+            total_staked: Uint128::new(50),
+            total_compounded: Uint128::new(10),
+            total_unbonding: Uint128::new(60),
+            registered_users: 0,
+            derivative_supply: Uint128::new(50),
+        });
+        // snapshots hold the totals as of the start of a block
+        let state: State = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::StateAtHeight { height: mock_env().block.height + 2 }).unwrap()).unwrap();
+        assert_eq!((state.total_staked, state.total_compounded, state.total_unbonding), (Uint128::new(100), Uint128::new(20), Uint128::zero()));
+        let state: State = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::StateAtHeight { height: mock_env().block.height }).unwrap()).unwrap();
+        assert_eq!(state, State::default());
+
+        // unbonding is not complete yet
+        execute(deps.as_mut(), at(3, 119), mock_info(USER, &[]), ExecuteMsg::Withdraw {}).unwrap();
+        let state: State = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+        assert_eq!(state.total_unbonding, Uint128::new(60));
+        execute(deps.as_mut(), at(4, 120), mock_info(USER, &[]), ExecuteMsg::Withdraw {}).unwrap();
+        let state: State = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+        assert_eq!(state.total_unbonding, Uint128::zero());
+    }
+
+    #[test]
+    fn staked_is_checkpointed_per_height() {
+        let mut deps = setup();
+        register(&mut deps, "user2", "user2proxy");
+        let height = mock_env().block.height;
+        let at = |blocks: u64| {
+            let mut env = mock_env();
+            env.block.height += blocks;
+            env
+        };
+        execute(deps.as_mut(), at(1), mock_info(USER, &coins(100, "udarc")), ExecuteMsg::Stake { validator: "validator".to_string() }).unwrap();
+        execute(deps.as_mut(), at(1), mock_info(USER, &coins(50, "udarc")), ExecuteMsg::Stake { validator: "validator2".to_string() }).unwrap();
+        execute(deps.as_mut(), at(2), mock_info("user2", &coins(70, "udarc")), ExecuteMsg::Stake { validator: "validator".to_string() }).unwrap();
+        execute(deps.as_mut(), at(3), mock_info(USER, &[]), ExecuteMsg::Unstake { validator: "validator".to_string(), amount: Uint128::new(30) }).unwrap();
+
+        let staked_at = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, address: &str, height: u64| -> Uint128 {
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::StakedAtHeight { address: Addr::unchecked(address), height }).unwrap()).unwrap()
+        };
+        let total_at = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, height: u64| -> Uint128 {
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::TotalStakedAtHeight { height }).unwrap()).unwrap()
+        };
+        assert_eq!(staked_at(&deps, USER, height + 1), Uint128::zero());
+        assert_eq!(staked_at(&deps, USER, height + 2), Uint128::new(150));
+        assert_eq!(staked_at(&deps, USER, height + 4), Uint128::new(120));
+        assert_eq!(staked_at(&deps, "user2", height + 2), Uint128::zero());
+        assert_eq!(staked_at(&deps, "user2", height + 3), Uint128::new(70));
+        assert_eq!(total_at(&deps, height + 1), Uint128::zero());
+        assert_eq!(total_at(&deps, height + 3), Uint128::new(220));
+        assert_eq!(total_at(&deps, height + 4), Uint128::new(190));
+    }
+
+    #[test]
+    fn cannot_claim_again_after_emergency_exit() {
+        let mut deps = setup();
+        stake(&mut deps, "validator", 100);
+        stake(&mut deps, "validator2", 50);
+        execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::Compound { validator: "validator".to_string(), amount: Uint128::new(20) }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::Unstake { validator: "validator2".to_string(), amount: Uint128::new(10) }).unwrap();
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("notaproxy", &[]), ExecuteMsg::ProxyExited {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(USER_PROXY, &[]), ExecuteMsg::ProxyExited {}).unwrap();
+
+        // the exited stake is gone from the books
+        let state: State = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+        assert_eq!(state, State::default());
+        let staked: Vec<QueryStaked> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Staked { address: Addr::unchecked(USER) }).unwrap()).unwrap();
+        assert_eq!(staked, vec![]);
+
+        // and cannot be claimed a second time through the hub
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER, &[]), ExecuteMsg::Unstake { validator: "validator".to_string(), amount: Uint128::new(100) }).unwrap_err();
+        assert_eq!(err, ContractError::UnregisteredUser {});
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(120);
+        let err = execute(deps.as_mut(), env, mock_info(USER, &[]), ExecuteMsg::Withdraw {}).unwrap_err();
+        assert_eq!(err, ContractError::UnregisteredUser {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER_PROXY, &[]), ExecuteMsg::ProxyExited {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn status_reports_heartbeat() {
+        let mut deps = setup();
+        let status: HubStatusResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Status {}).unwrap()).unwrap();
+        assert_eq!(status, HubStatusResponse { emergency: false, last_heartbeat: mock_env().block.time });
+
+        // only the owner keeps the hub alive, other executions don't
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(1000);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::Heartbeat {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), ExecuteMsg::Heartbeat {}).unwrap();
+        let heartbeat = env.block.time;
+        env.block.time = env.block.time.plus_seconds(1000);
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), ExecuteMsg::SetEmergency { enabled: true }).unwrap();
+        let status: HubStatusResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Status {}).unwrap()).unwrap();
+        assert_eq!(status, HubStatusResponse { emergency: true, last_heartbeat: heartbeat });
+    }
+}
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{OverflowError, StdError};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +7,12 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -65,4 +72,10 @@ pub enum ContractError {
 
     #[error("Cannot compound to validator with no bonded tokens")]
     InvalidCompound {},
+
+    #[error("No default vote has been set")]
+    NoDefaultVote {},
+
+    #[error("Commission rate must be lower than 1")]
+    InvalidCommissionRate {},
 }
\ No newline at end of file
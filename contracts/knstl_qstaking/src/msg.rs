@@ -1,6 +1,7 @@
 use serde::{Serialize, Deserialize};
 use schemars::JsonSchema;
-use cosmwasm_std::{Addr, Uint128, Decimal};
+use cosmwasm_std::{Addr, Uint128, Decimal, VoteOption};
+use qstaking_proxy::msg::WeightedVoteOption;
 
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
@@ -14,6 +15,7 @@ pub struct InstantiateMsg {
     pub proxy_label: String,
     pub commission_rate: Decimal,
     pub unbond_period: u64,
+    pub emergency_timelock: u64,
 }
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -26,6 +28,15 @@ pub enum ExecuteMsg {
     CollectAll {},
     Withdraw {},
     Compound {validator: String, amount: Uint128},
+    SetEmergency {enabled: bool},
+    Vote {proposal_id: u64, option: VoteOption},
+    VoteWeighted {proposal_id: u64, options: Vec<WeightedVoteOption>},
+    SetDefaultVote {option: Option<VoteOption>},
+    CastDefaultVotes {proposal_id: u64, start_after: Option<String>, limit: Option<u32>},
+    // sent by a proxy whose owner took their stake out through an emergency exit
+    ProxyExited {},
+    // does nothing but keep proxies from enabling emergency exits while no one else executes
+    Heartbeat {},
     // Decompound {validator: String, amount: Uint128},
 }
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
@@ -36,9 +47,29 @@ pub enum QueryMsg {
     Staked {address: Addr},
     TokenInfo {address: Addr},
     ProxyAddress {address: Addr},
+    AllProxies {start_after: Option<String>, limit: Option<u32>},
+    OwnerOfProxy {proxy: String},
+    Emergency {},
+    Status {},
+    Vote {proposal_id: u64, address: Addr},
+    State {},
+    StateAtHeight {height: u64},
+    StakedAtHeight {address: Addr, height: u64},
+    TotalStakedAtHeight {height: u64},
 }
 
 
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct ProxyInfo {
+    pub owner: Addr,
+    pub proxy: Addr,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct AllProxiesResponse {
+    pub proxies: Vec<ProxyInfo>,
+}
+
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct QueryStaked {
     pub validator: String,
@@ -1,25 +1,78 @@
 
 use serde::{Serialize, Deserialize};
 use schemars::JsonSchema;
-use cw_storage_plus::{Map, Item};
-use cosmwasm_std::{Addr, Uint128, Decimal};
+use cw_storage_plus::{Map, Item, IndexedMap, UniqueIndex, IndexList, Index, SnapshotItem, SnapshotMap, Strategy};
+use cosmwasm_std::{Addr, Uint128, Decimal, VoteOption, Timestamp};
+use qstaking_proxy::msg::WeightedVoteOption;
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct Config {
+    pub owner: Addr,
     pub native_denom: String,
     pub cw20contract : String,
     pub stake_contract_id : u64,
     pub stake_contract_label: String,
     pub commission_rate: Decimal,
     pub unbond_period: u64,
+    pub emergency: bool,
+    pub emergency_timelock: u64,
+    // vote cast on behalf of users who did not vote themselves
+    pub default_vote: Option<VoteOption>,
 }
 
-#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Default)]
 pub struct StakeInfo {
     pub compounded: Uint128,
     pub staked : Uint128,
 }
+// protocol-wide totals, emergency exits are dropped when the proxy reports them
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Default)]
+pub struct State {
+    pub total_staked: Uint128,
+    pub total_compounded: Uint128,
+    // unstaked tokens not withdrawn yet
+    pub total_unbonding: Uint128,
+    pub registered_users: u64,
+    // qdarc minted by this contract and not burned yet
+    pub derivative_supply: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Unbonding {
+    pub amount: Uint128,
+    pub complete_date: Timestamp,
+}
+
 pub const CONFIG : Item<Config> = Item::new("delegateinfo");
+// last time anything was executed on this contract, proxies enable emergency exits when it gets too old
+pub const HEARTBEAT : Item<Timestamp> = Item::new("heartbeat");
+pub const STATE : SnapshotItem<State> = SnapshotItem::new("state", "state__checkpoints", "state__changelog", Strategy::EveryBlock);
+// user's unstaked tokens, mirrors the unbonding queue of the user's proxy
+pub const UNBONDING : Map<&Addr, Vec<Unbonding>> = Map::new("unbonding");
 
 pub const STAKEINFO : Map<(&Addr, String), StakeInfo> = Map::new("stakeinfo");
-pub const PROXY : Map<&Addr, String> = Map::new("proxyaddr");
\ No newline at end of file
+// user's staked amount over all validators, checkpointed per height for governance weight
+pub const STAKED : SnapshotMap<&Addr, Uint128> = SnapshotMap::new("staked", "staked__checkpoints", "staked__changelog", Strategy::EveryBlock);
+
+pub struct ProxyIndexes<'a> {
+    // reverse lookup from proxy contract address to its owner
+    pub proxy: UniqueIndex<'a, String, String, Addr>,
+}
+
+impl<'a> IndexList<String> for ProxyIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<String>> + '_> {
+        let v: Vec<&dyn Index<String>> = vec![&self.proxy];
+        Box::new(v.into_iter())
+    }
+}
+
+// proxy contract address per registered user
+pub fn proxies<'a>() -> IndexedMap<'a, &'a Addr, String, ProxyIndexes<'a>> {
+    let indexes = ProxyIndexes {
+        proxy: UniqueIndex::new(|proxy: &String| proxy.clone(), "proxyaddr__proxy"),
+    };
+    IndexedMap::new("proxyaddr", indexes)
+}
+
+// votes users cast themselves, keyed by (proposal_id, user)
+pub const VOTES : Map<(u64, &Addr), Vec<WeightedVoteOption>> = Map::new("votes");
@@ -1,12 +1,15 @@
 #[cfg(not(feature = "library"))]
-use cosmwasm_std::{to_binary, entry_point, Env, Deps, DepsMut, MessageInfo, Response, StdResult, Binary, Uint128, CosmosMsg, StakingMsg, Coin, BankMsg, DistributionMsg, Decimal, Storage };
+use cosmwasm_std::{to_binary, from_binary, entry_point, Env, Deps, DepsMut, MessageInfo, Response, StdResult, Binary, Uint128, CosmosMsg, StakingMsg, Coin, BankMsg, DistributionMsg, Decimal, Storage, GovMsg, VoteOption, Addr, WasmMsg, SubMsg, Reply, QueryRequest, WasmQuery, Empty, SystemResult, SystemError, ContractResult, StdError, to_vec, OverflowError, OverflowOperation };
 use cw2::set_contract_version;
-use crate::msg::{InstantiateMsg, ExecuteMsg, QueryMsg};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_utils::must_pay;
+use crate::msg::{InstantiateMsg, ExecuteMsg, ReceiveMsg, QueryMsg, HubQueryMsg, HubStatusResponse, HubExecuteMsg, WeightedVoteOption, DelegationsResponse, DelegationInfo};
 use crate::error::ContractError;
-use crate::state::{CONFIG, Config, UNBONDED, Unbonded, BONDED, COMPOUNDED};
+use crate::state::{CONFIG, Config, UNBONDED, Unbonded, BONDED, COMPOUNDED, VALIDATORS, ValidatorStake, REDELEGATIONS, Redelegation, EXITED};
 
 const CONTRACT_NAME: &str = "knstl_qstaking_proxy";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const HUB_NOTIFY_ID : u64 = 1;
 
 #[entry_point]
 pub fn instantiate(
@@ -16,16 +19,22 @@ pub fn instantiate(
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    if msg.commission_rate >= Decimal::one() {
+        return Err(ContractError::InvalidCommissionRate {});
+    }
     CONFIG.save(deps.storage, &Config{
         admin: info.sender,
+        derivative: deps.api.addr_validate(&msg.derivative)?,
         denom: msg.denom,
         owner: msg.owner.clone(),
         unbond_period: msg.unbond_period,
         commission_rate: msg.commission_rate,
+        emergency_timelock: msg.emergency_timelock,
     })?;
     BONDED.save(deps.storage, &Uint128::zero())?;
     UNBONDED.save(deps.storage, &vec![])?;
     COMPOUNDED.save(deps.storage, &Uint128::zero())?;
+    REDELEGATIONS.save(deps.storage, &vec![])?;
     Ok(Response::new().add_attribute("owner", msg.owner))
 }
 
@@ -40,10 +49,15 @@ pub fn execute(
         ExecuteMsg::Stake { validator } => exec_stake(deps, env, info, validator),
         ExecuteMsg::Unstake { validator, amount } => exec_unstake(deps, env, info, validator, amount),
         ExecuteMsg::Withdraw {} => exec_withdraw(deps, env, info),
-        ExecuteMsg::Restake { from, to, amount} => exec_restake(deps, env, info, from, to, amount),
+        ExecuteMsg::Restake { from, to, amount, compounded } => exec_restake(deps, env, info, from, to, amount, compounded),
         ExecuteMsg::Collect { validator} => exec_collect(deps, info, validator),
         ExecuteMsg::Compound { validator, amount } => exec_compound(deps, info, validator, amount),
         ExecuteMsg::Decompound { validator, amount } => exec_decompound(deps, env, info, validator, amount),
+        ExecuteMsg::EmergencyUnstakeAll {} => exec_emergency_unstake_all(deps, env, info.sender, Uint128::zero()),
+        ExecuteMsg::EmergencyWithdraw {} => exec_emergency_withdraw(deps, env, info),
+        ExecuteMsg::Receive(msg) => exec_receive(deps, env, info, msg),
+        ExecuteMsg::Vote { proposal_id, option } => exec_vote(deps, info, proposal_id, option),
+        ExecuteMsg::VoteWeighted { proposal_id, options } => exec_vote_weighted(deps, env, info, proposal_id, options),
     }
 }
 
@@ -58,20 +72,22 @@ fn exec_stake(
     if info.sender != config.admin {
         return Err(ContractError::UnknownUser {});
     }
-    let received = info.funds.first().unwrap();
-    if received.denom != config.denom {
-        return Err(ContractError::UnstakeableTokenSent { denom: received.denom.clone() });
-    }
-    BONDED.update(deps.storage, |x| -> StdResult<Uint128> {
-        Ok(x + received.amount)
+    let amount = must_pay(&info, &config.denom)?;
+    BONDED.update(deps.storage, |x| -> Result<_, ContractError> {
+        Ok(x.checked_add(amount)?)
+    })?;
+    VALIDATORS.update(deps.storage, &validator, |x| -> Result<_, ContractError> {
+        let mut stake = x.unwrap_or_default();
+        stake.bonded = stake.bonded.checked_add(amount)?;
+        Ok(stake)
     })?;
     let res = Response::new()
     .add_message(CosmosMsg::Staking(
         StakingMsg::Delegate { 
             validator: validator.clone(), 
             amount: Coin { 
-                denom: received.denom.clone(), 
-                amount: received.amount,
+                denom: config.denom,
+                amount,
             },
         }
     ))
@@ -93,17 +109,19 @@ fn exec_unstake(
     if info.sender != config.admin {
         return Err(ContractError::UnknownUser {})
     }
+    let bonded = BONDED.load(deps.storage)?.checked_sub(amount).map_err(|_| ContractError::InsufficientBalance {})?;
+    let mut stake = VALIDATORS.may_load(deps.storage, &validator)?.unwrap_or_default();
+    stake.bonded = stake.bonded.checked_sub(amount).map_err(|_| ContractError::InsufficientBalance {})?;
+    BONDED.save(deps.storage, &bonded)?;
+    VALIDATORS.save(deps.storage, &validator, &stake)?;
     UNBONDED.update(deps.storage, |mut x| -> StdResult<Vec<Unbonded>> {
-        x.push(Unbonded { amount: amount, complete_date: env.block.time.plus_seconds(config.unbond_period), validator: validator.clone() });
+        x.push(Unbonded { amount, complete_date: env.block.time.plus_seconds(config.unbond_period), validator: validator.clone() });
         Ok(x)
     })?;
-    BONDED.update(deps.storage, |x| -> StdResult<Uint128> {
-        Ok(x.saturating_sub(amount))
-    })?;
     let res = Response::new()
     .add_message(CosmosMsg::Staking(
         StakingMsg::Undelegate { 
-            validator: validator,
+            validator,
             amount : Coin {
                 amount,
                 denom: config.denom,
@@ -122,18 +140,54 @@ fn exec_restake(
     from: String,
     to: String,
     amount: Uint128,
+    compounded: Uint128,
 )->Result<Response, ContractError> {
 
     let config = CONFIG.load(deps.storage)?;
     if info.sender != config.admin {
         return Err(ContractError::UnknownUser {})
     }
+
+    // the chain rejects redelegating out of a validator that is itself the destination of
+    // an incomplete redelegation, so fail early with a clear error
+    let mut redelegations: Vec<Redelegation> = REDELEGATIONS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| env.block.time < r.complete_date)
+        .collect();
+    if let Some(pending) = redelegations.iter().find(|r| r.to == from) {
+        return Err(ContractError::OnRedelegationPeriod { validator: from, complete_date: pending.complete_date })
+    }
+
+    VALIDATORS.update(deps.storage, &from, |x| -> Result<_, ContractError> {
+        let mut stake = x.unwrap_or_default();
+        stake.bonded = stake.bonded.checked_sub(amount).map_err(|_| ContractError::InsufficientBalance {})?;
+        stake.compounded = stake.compounded.checked_sub(compounded).map_err(|_| ContractError::InsufficientBalance {})?;
+        Ok(stake)
+    })?;
+    VALIDATORS.update(deps.storage, &to, |x| -> Result<_, ContractError> {
+        let mut stake = x.unwrap_or_default();
+        stake.bonded = stake.bonded.checked_add(amount)?;
+        stake.compounded = stake.compounded.checked_add(compounded)?;
+        Ok(stake)
+    })?;
+
+    let total = amount.checked_add(compounded)?;
+    redelegations.push(Redelegation {
+        from: from.clone(),
+        to: to.clone(),
+        amount: total,
+        complete_date: env.block.time.plus_seconds(config.unbond_period),
+    });
+    REDELEGATIONS.save(deps.storage, &redelegations)?;
+
     let res = Response::new()
     .add_message(CosmosMsg::Staking(
         StakingMsg::Redelegate { 
          src_validator: from.clone(),
          dst_validator: to.clone(),
-         amount: Coin { denom: config.denom, amount: amount },
+         amount: Coin { denom: config.denom, amount: total },
      }))
     .add_attribute("action", "redelegate")
     .add_attribute("from", &from)
@@ -158,15 +212,16 @@ fn exec_withdraw(
         return Err(ContractError::InvalidZeroAmount {});
     }
     
-    let reward_ratio: Decimal = Decimal::from_ratio(withdrawals, bonded + unbondings);  
-    let reward = balance.amount - withdrawals;
+    let reward_ratio: Decimal = Decimal::from_ratio(withdrawals, bonded.checked_add(unbondings)?);
+    let reward = balance.amount.checked_sub(withdrawals).map_err(|_| ContractError::InsufficientBalance {})?;
+    let withdrawn_reward = mul_decimal(reward, reward_ratio)?;
     let total_unbond = Coin {
-        amount: withdrawals + (reward * reward_ratio * (Decimal::one() - config.commission_rate)),
+        amount: withdrawals.checked_add(mul_decimal(withdrawn_reward, owner_share(&config)?)?)?,
         denom: config.denom.clone(),
     };
 
     let commission = Coin {
-        amount : reward * reward_ratio * config.commission_rate,
+        amount : mul_decimal(withdrawn_reward, config.commission_rate)?,
         denom: config.denom,
     };
     let res = 
@@ -225,11 +280,19 @@ fn exec_compound (
         return Err(ContractError::CompoundWithUnbondeds {  })
     }
     
-    COMPOUNDED.update(deps.storage, |x| -> StdResult<Uint128> {
-        Ok(x + amount)
+    COMPOUNDED.update(deps.storage, |x| -> Result<_, ContractError> {
+        Ok(x.checked_add(amount)?)
+    })?;
+    VALIDATORS.update(deps.storage, &validator, |x| -> Result<_, ContractError> {
+        let mut stake = x.unwrap_or_default();
+        stake.compounded = stake.compounded.checked_add(amount)?;
+        Ok(stake)
     })?;
 
-    
+    // the commission is taken on top of the compounded amount, which is the owner's share
+    let commission = amount
+        .checked_multiply_ratio(config.commission_rate.atomics(), owner_share(&config)?.atomics())
+        .map_err(|_| OverflowError::new(OverflowOperation::Mul, amount, config.commission_rate))?;
     let res = Response::new()
     .add_message(CosmosMsg::Staking(
         StakingMsg::Delegate { 
@@ -241,7 +304,7 @@ fn exec_compound (
     .add_message(CosmosMsg::Bank(
         BankMsg::Burn { 
             amount: vec![Coin{
-                amount: amount * (config.commission_rate / (Decimal::one() - config.commission_rate)),
+                amount: commission,
                 denom: config.denom 
         }]
     }))
@@ -264,11 +327,16 @@ fn exec_decompound (
         return Err(ContractError::UnknownUser {})
     }
 
-    COMPOUNDED.update(deps.storage, |x| -> StdResult<Uint128> {
-        Ok(x - amount)
+    COMPOUNDED.update(deps.storage, |x| -> Result<_, ContractError> {
+        x.checked_sub(amount).map_err(|_| ContractError::InvalidDecompoundAmount {})
+    })?;
+    VALIDATORS.update(deps.storage, &validator, |x| -> Result<_, ContractError> {
+        let mut stake = x.unwrap_or_default();
+        stake.compounded = stake.compounded.checked_sub(amount).map_err(|_| ContractError::InvalidDecompoundAmount {})?;
+        Ok(stake)
     })?;
     UNBONDED.update(deps.storage, |mut x| -> StdResult<Vec<Unbonded>> {
-        x.push(Unbonded { amount: amount, complete_date: env.block.time.plus_seconds(config.unbond_period), validator: validator.clone() });
+        x.push(Unbonded { amount, complete_date: env.block.time.plus_seconds(config.unbond_period), validator: validator.clone() });
         Ok(x)
     })?;
     let res = Response::new()
@@ -285,6 +353,256 @@ fn exec_decompound (
     Ok(res)
 }
 
+fn exec_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.derivative {
+        return Err(ContractError::Unauthorized {})
+    }
+    let sender = deps.api.addr_validate(&msg.sender)?;
+    match from_binary(&msg.msg)? {
+        ReceiveMsg::EmergencyUnstakeAll {} => exec_emergency_unstake_all(deps, env, sender, msg.amount),
+    }
+}
+
+// the owner has to give back the derivative minted for the bonded amount, which is burned,
+// so it cannot be redeemed through the hub again once the delegations are taken out here.
+// all of it has to come back at once, as the hub forgets the whole stake of the owner.
+fn exec_emergency_unstake_all(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    returned: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if sender != config.owner {
+        return Err(ContractError::Unauthorized {})
+    }
+    if !is_emergency(deps.as_ref(), &env, &config)? {
+        return Err(ContractError::EmergencyNotEnabled {})
+    }
+    let bonded = BONDED.load(deps.storage)?;
+    if returned != bonded {
+        return Err(ContractError::DerivativeNotReturned { expected: bonded })
+    }
+
+    let delegations = deps.querier.query_all_delegations(env.contract.address.clone())?;
+    let mut undelegate_msgs: Vec<CosmosMsg> = vec![];
+    let mut unbondeds = UNBONDED.load(deps.storage)?;
+    for delegation in delegations.into_iter().filter(|d| !d.amount.amount.is_zero()) {
+        unbondeds.push(Unbonded {
+            amount: delegation.amount.amount,
+            complete_date: env.block.time.plus_seconds(config.unbond_period),
+            validator: delegation.validator.clone(),
+        });
+        undelegate_msgs.push(CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: delegation.validator,
+            amount: delegation.amount,
+        }));
+    }
+    if undelegate_msgs.is_empty() {
+        return Err(ContractError::InvalidZeroAmount {})
+    }
+    UNBONDED.save(deps.storage, &unbondeds)?;
+    BONDED.save(deps.storage, &Uint128::zero())?;
+    COMPOUNDED.save(deps.storage, &Uint128::zero())?;
+    let validators = VALIDATORS
+        .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+    for validator in validators {
+        VALIDATORS.remove(deps.storage, &validator);
+    }
+    REDELEGATIONS.remove(deps.storage);
+    EXITED.save(deps.storage, &true)?;
+
+    let res = 
+    if returned.is_zero() {
+        Response::new()
+    } else {
+        Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: config.derivative.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount: returned })?,
+            funds: vec![],
+        })
+    }
+    .add_messages(undelegate_msgs)
+    // the hub drops the owner's stake from its books, a hub that fails must not block the exit
+    .add_submessage(SubMsg::reply_on_error(WasmMsg::Execute {
+        contract_addr: config.admin.to_string(),
+        msg: to_binary(&HubExecuteMsg::ProxyExited {})?,
+        funds: vec![],
+    }, HUB_NOTIFY_ID))
+    .add_attribute("action", "emergency_unstake_all")
+    .add_attribute("from", &env.contract.address)
+    .add_attribute("by", &sender)
+    .add_attribute("burned", returned)
+    ;
+    Ok(res)
+}
+
+fn exec_emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {})
+    }
+    // after the exit the hub no longer knows this proxy, the unbonding tokens are only reachable here
+    let exited = EXITED.may_load(deps.storage)?.unwrap_or(false);
+    if !exited && !is_emergency(deps.as_ref(), &env, &config)? {
+        return Err(ContractError::EmergencyNotEnabled {})
+    }
+
+    // everything liquid belongs to the owner, no commission is taken on emergency exit
+    resolve_unbondings(deps.storage, env.clone())?;
+    let balance = deps.querier.query_balance(env.contract.address, config.denom)?;
+    if balance.amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {})
+    }
+
+    let res = Response::new()
+    .add_message(BankMsg::Send {
+        amount: vec![balance],
+        to_address: config.owner.to_string(),
+    })
+    .add_attribute("action", "emergency_withdraw")
+    .add_attribute("to", &config.owner)
+    ;
+    Ok(res)
+}
+
+fn exec_vote(
+    deps: DepsMut,
+    info: MessageInfo,
+    proposal_id: u64,
+    option: VoteOption,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::UnknownUser {})
+    }
+
+    let res = Response::new()
+    .add_message(CosmosMsg::Gov(GovMsg::Vote { proposal_id, vote: option }))
+    .add_attribute("action", "vote")
+    .add_attribute("proposal_id", proposal_id.to_string())
+    .add_attribute("owner", &config.owner)
+    ;
+    Ok(res)
+}
+
+fn exec_vote_weighted(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: u64,
+    options: Vec<WeightedVoteOption>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::UnknownUser {})
+    }
+
+    let mut total = Decimal::zero();
+    for (i, weighted) in options.iter().enumerate() {
+        if weighted.weight.is_zero() || options[..i].iter().any(|o| o.option == weighted.option) {
+            return Err(ContractError::InvalidVoteWeights {})
+        }
+        total += weighted.weight;
+    }
+    if total != Decimal::one() {
+        return Err(ContractError::InvalidVoteWeights {})
+    }
+
+    // cosmwasm-std has no GovMsg for weighted votes, so send it as a raw sdk message
+    let res = Response::new()
+    .add_message(CosmosMsg::Stargate {
+        type_url: "/cosmos.gov.v1beta1.MsgVoteWeighted".to_string(),
+        value: encode_msg_vote_weighted(proposal_id, env.contract.address.as_str(), &options),
+    })
+    .add_attribute("action", "vote_weighted")
+    .add_attribute("proposal_id", proposal_id.to_string())
+    .add_attribute("owner", &config.owner)
+    ;
+    Ok(res)
+}
+
+// Protobuf encoding of cosmos.gov.v1beta1.MsgVoteWeighted
+// (https://github.com/cosmos/cosmos-sdk/blob/v0.45.4/proto/cosmos/gov/v1beta1/tx.proto#L62-L73)
+fn encode_msg_vote_weighted(
+    proposal_id: u64,
+    voter: &str,
+    options: &[WeightedVoteOption],
+) -> Binary {
+    let mut buf = vec![];
+    encode_varint_field(&mut buf, 1, proposal_id);
+    encode_length_delimited_field(&mut buf, 2, voter.as_bytes());
+    for weighted in options {
+        let option = match weighted.option {
+            VoteOption::Yes => 1,
+            VoteOption::Abstain => 2,
+            VoteOption::No => 3,
+            VoteOption::NoWithVeto => 4,
+        };
+        let mut option_buf = vec![];
+        encode_varint_field(&mut option_buf, 1, option);
+        // sdk.Dec is marshalled as its 18 decimal places integer representation
+        encode_length_delimited_field(&mut option_buf, 2, weighted.weight.atomics().to_string().as_bytes());
+        encode_length_delimited_field(&mut buf, 3, &option_buf);
+    }
+    Binary(buf)
+}
+
+fn encode_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn encode_varint_field(buf: &mut Vec<u8>, field_number: u8, value: u64) {
+    buf.push(field_number << 3);
+    encode_varint(buf, value);
+}
+
+fn encode_length_delimited_field(buf: &mut Vec<u8>, field_number: u8, data: &[u8]) {
+    buf.push(field_number << 3 | 2);
+    encode_varint(buf, data.len() as u64);
+    buf.extend_from_slice(data);
+}
+
+// emergency is on when the hub says so, or when the hub owner has not sent a heartbeat for
+// emergency_timelock. the hub counts as broken, and so enables emergency as well, when the
+// contract is gone or when its code fails to answer the Status query, eg. after a migration to
+// code without it. other querier failures are returned as errors, they say nothing about the hub.
+fn is_emergency(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+) -> StdResult<bool> {
+    let request: QueryRequest<Empty> = WasmQuery::Smart {
+        contract_addr: config.admin.to_string(),
+        msg: to_binary(&HubQueryMsg::Status {})?,
+    }.into();
+    match deps.querier.raw_query(&to_vec(&request)?) {
+        SystemResult::Err(SystemError::NoSuchContract { .. }) => Ok(true),
+        SystemResult::Ok(ContractResult::Err(_)) => Ok(true),
+        SystemResult::Ok(ContractResult::Ok(data)) => {
+            let status: HubStatusResponse = from_binary(&data)?;
+            Ok(status.emergency || env.block.time >= status.last_heartbeat.plus_seconds(config.emergency_timelock))
+        }
+        SystemResult::Err(err) => Err(StdError::generic_err(format!("Querier system error: {}", err))),
+    }
+}
+
 fn resolve_unbondings(
     storage: &mut dyn Storage,
     env: Env,
@@ -294,7 +612,7 @@ fn resolve_unbondings(
     let mut new_unbonded : Vec<Unbonded> = vec![];
     for unbonded in unbondeds.iter() {
         if env.block.time.seconds() >= unbonded.complete_date.seconds() {
-            ret += unbonded.amount;
+            ret = ret.checked_add(unbonded.amount)?;
         } 
         else { new_unbonded.push(Unbonded { amount: unbonded.amount, complete_date: unbonded.complete_date, validator: unbonded.validator.clone() }) }
     }
@@ -303,17 +621,39 @@ fn resolve_unbondings(
     })?;
     Ok(ret)
 }
+// amount * ratio, as an error instead of a panic when the result does not fit
+fn mul_decimal(amount: Uint128, ratio: Decimal) -> StdResult<Uint128> {
+    amount
+        .checked_multiply_ratio(ratio.atomics(), Decimal::one().atomics())
+        .map_err(|_| StdError::overflow(OverflowError::new(OverflowOperation::Mul, amount, ratio)))
+}
+// part of the rewards left to the owner once the commission is taken
+fn owner_share(config: &Config) -> StdResult<Decimal> {
+    Ok(Decimal::new(Decimal::one().atomics().checked_sub(config.commission_rate.atomics())?))
+}
 fn get_unbonded_amount(
     storage: &mut dyn Storage
 )-> StdResult<Uint128> {
     let unbondeds = UNBONDED.load(storage)?;
     let mut ret = Uint128::zero();
     for unbonded in unbondeds.iter() {
-        ret += unbonded.amount
+        ret = ret.checked_add(unbonded.amount)?;
     }
     Ok(ret)
 }
 
+#[entry_point]
+pub fn reply(
+    _deps: DepsMut,
+    _env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    match msg.id {
+        HUB_NOTIFY_ID => Ok(Response::new().add_attribute("hub_notified", "false")),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
 #[entry_point]
 pub fn query(
     deps: Deps,
@@ -324,14 +664,16 @@ pub fn query(
         QueryMsg::ConfigInfo {} => to_binary(&query_config(deps)?),
         QueryMsg::Unbondings {} => to_binary(&query_unbondings(deps)?),
         QueryMsg::Rewards {} => to_binary(&query_rewards(deps, env)?),
+        QueryMsg::Delegations {} => to_binary(&query_delegations(deps, env)?),
+        QueryMsg::Redelegations {} => to_binary(&query_redelegations(deps, env)?),
     }
 }
 
 fn query_config(deps: Deps)-> StdResult<Config>{
-    Ok(CONFIG.load(deps.storage)?)
+    CONFIG.load(deps.storage)
 }
 fn query_unbondings(deps: Deps)-> StdResult<Vec<Unbonded>>{
-    Ok(UNBONDED.load(deps.storage)?)
+    UNBONDED.load(deps.storage)
 }
 fn query_rewards(deps: Deps, env: Env) -> StdResult<Uint128> {
     let config = CONFIG.load(deps.storage)?;
@@ -341,18 +683,538 @@ fn query_rewards(deps: Deps, env: Env) -> StdResult<Uint128> {
     let unbondeds = UNBONDED.load(deps.storage)?;
     for unbonded in unbondeds.iter() {
         if env.block.time.seconds() >= unbonded.complete_date.seconds() {
-            unbondings += unbonded.amount;
+            unbondings = unbondings.checked_add(unbonded.amount)?;
         } 
     }
     let mut unbonded = Uint128::zero();
     for unbond in unbondeds.iter() {
-        unbonded += unbond.amount
+        unbonded = unbonded.checked_add(unbond.amount)?;
     }
 
     if bonded.is_zero() && unbonded.is_zero() {
         return Ok(Uint128::zero())
     }
-    let reward_ratio: Decimal = Decimal::from_ratio(unbondings, bonded + unbonded);  
-    
-    Ok(unbondings + ((balance.amount - unbondings ) * reward_ratio * (Decimal::one() - config.commission_rate)))
-}
\ No newline at end of file
+    let reward_ratio: Decimal = Decimal::from_ratio(unbondings, bonded.checked_add(unbonded)?);  
+    let reward = mul_decimal(balance.amount.checked_sub(unbondings)?, reward_ratio)?;
+    Ok(unbondings.checked_add(mul_decimal(reward, owner_share(&config)?)?)?)
+}
+fn query_delegations(deps: Deps, env: Env) -> StdResult<DelegationsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut tracked = VALIDATORS
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<Vec<(String, ValidatorStake)>>>()?;
+    // validators delegated to without being tracked are reported as well
+    for delegation in deps.querier.query_all_delegations(env.contract.address.clone())? {
+        if !tracked.iter().any(|(validator, _)| validator == &delegation.validator) {
+            tracked.push((delegation.validator, ValidatorStake::default()));
+        }
+    }
+
+    let mut delegations = vec![];
+    for (validator, stake) in tracked {
+        let (delegated, rewards) = match deps.querier.query_delegation(env.contract.address.clone(), validator.clone())? {
+            Some(delegation) => {
+                let rewards = delegation.accumulated_rewards
+                    .iter()
+                    .filter(|coin| coin.denom == config.denom)
+                    .map(|coin| coin.amount)
+                    .sum();
+                (delegation.amount.amount, rewards)
+            },
+            None => (Uint128::zero(), Uint128::zero()),
+        };
+        let tracked_amount = stake.bonded.checked_add(stake.compounded)?;
+        delegations.push(DelegationInfo {
+            validator,
+            delegated,
+            rewards,
+            bonded: stake.bonded,
+            compounded: stake.compounded,
+            shortfall: tracked_amount.saturating_sub(delegated),
+            surplus: delegated.saturating_sub(tracked_amount),
+        });
+    }
+    Ok(DelegationsResponse { delegations })
+}
+
+fn query_redelegations(deps: Deps, env: Env) -> StdResult<Vec<Redelegation>> {
+    Ok(REDELEGATIONS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|r| env.block.time < r.complete_date)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{coin, coins, from_binary, Addr, ContractResult, FullDelegation, OwnedDeps, SubMsgResult, SystemResult, Validator};
+    use cw_utils::PaymentError;
+
+    const HUB: &str = "hub";
+    const OWNER: &str = "owner";
+    const DERIVATIVE: &str = "qdarc";
+    const VALIDATOR: &str = "validator";
+    const DENOM: &str = "udarc";
+    const TIMELOCK: u64 = 86400;
+
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info(HUB, &[]), InstantiateMsg {
+            denom: DENOM.to_string(),
+            owner: Addr::unchecked(OWNER),
+            derivative: DERIVATIVE.to_string(),
+            unbond_period: 120,
+            commission_rate: Decimal::percent(15),
+            emergency_timelock: TIMELOCK,
+        }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &coins(1000, DENOM)), ExecuteMsg::Stake {
+            validator: VALIDATOR.to_string(),
+        }).unwrap();
+        deps.querier.update_staking(DENOM, &[Validator {
+            address: VALIDATOR.to_string(),
+            commission: Decimal::percent(5),
+            max_commission: Decimal::percent(10),
+            max_change_rate: Decimal::percent(1),
+        }], &[FullDelegation {
+            delegator: mock_env().contract.address,
+            validator: VALIDATOR.to_string(),
+            amount: Coin::new(1000, DENOM),
+            can_redelegate: Coin::new(1000, DENOM),
+            accumulated_rewards: vec![],
+        }]);
+        hub_status(&mut deps, false);
+        deps
+    }
+
+    // hub answering the status query, it was last executed at mock_env() time
+    fn hub_status(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, emergency: bool) {
+        let status = HubStatusResponse { emergency, last_heartbeat: mock_env().block.time };
+        deps.querier.update_wasm(move |_| SystemResult::Ok(ContractResult::Ok(to_binary(&status).unwrap())));
+    }
+
+    fn flag_emergency(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) {
+        hub_status(deps, true);
+    }
+
+    #[test]
+    fn emergency_exit_only_by_owner() {
+        let mut deps = setup();
+        flag_emergency(&mut deps);
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::EmergencyUnstakeAll {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn emergency_exit_disabled_while_hub_is_active() {
+        let mut deps = setup();
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::EmergencyUnstakeAll {}).unwrap_err();
+        assert_eq!(err, ContractError::EmergencyNotEnabled {});
+
+        // the timelock runs from the last hub heartbeat, not from the last call to this proxy,
+        // so an owner who has not acted for a long time cannot exit while the hub is working
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(TIMELOCK - 1);
+        let err = execute(deps.as_mut(), env, mock_info(OWNER, &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap_err();
+        assert_eq!(err, ContractError::EmergencyNotEnabled {});
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(TIMELOCK * 10);
+        let heartbeat = env.block.time;
+        deps.querier.update_wasm(move |_| SystemResult::Ok(ContractResult::Ok(to_binary(&HubStatusResponse { emergency: false, last_heartbeat: heartbeat }).unwrap())));
+        let err = execute(deps.as_mut(), env, mock_info(OWNER, &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap_err();
+        assert_eq!(err, ContractError::EmergencyNotEnabled {});
+    }
+
+    #[test]
+    fn emergency_exit_when_hub_cannot_answer() {
+        let mut deps = setup();
+        deps.querier.update_balance(mock_env().contract.address, coins(1234, DENOM));
+        deps.querier.update_wasm(|_| SystemResult::Ok(ContractResult::Err("hub is broken".to_string())));
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap();
+
+        // a hub that is gone
+        deps.querier.update_wasm(|_| SystemResult::Err(SystemError::NoSuchContract { addr: HUB.to_string() }));
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap();
+
+        // other querier failures say nothing about the hub
+        deps.querier.update_wasm(|_| SystemResult::Err(SystemError::Unknown {}));
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::GenericErr { .. })), "{:?}", err);
+    }
+
+    fn return_derivative(amount: u128) -> ExecuteMsg {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: OWNER.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&ReceiveMsg::EmergencyUnstakeAll {}).unwrap(),
+        })
+    }
+
+    #[test]
+    fn emergency_unstake_all_when_hub_flags_emergency() {
+        let mut deps = setup();
+        flag_emergency(&mut deps);
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(DERIVATIVE, &[]), return_derivative(1000)).unwrap();
+        assert_eq!(res.messages.len(), 3);
+        assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: DERIVATIVE.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount: Uint128::new(1000) }).unwrap(),
+            funds: vec![],
+        }));
+        assert_eq!(res.messages[1].msg, CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: VALIDATOR.to_string(),
+            amount: Coin::new(1000, DENOM),
+        }));
+        assert_eq!(res.messages[2], SubMsg::reply_on_error(WasmMsg::Execute {
+            contract_addr: HUB.to_string(),
+            msg: to_binary(&HubExecuteMsg::ProxyExited {}).unwrap(),
+            funds: vec![],
+        }, HUB_NOTIFY_ID));
+        assert_eq!(BONDED.load(&deps.storage).unwrap(), Uint128::zero());
+
+        let unbondings: Vec<Unbonded> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Unbondings {}).unwrap()).unwrap();
+        assert_eq!(unbondings, vec![Unbonded {
+            amount: Uint128::new(1000),
+            complete_date: mock_env().block.time.plus_seconds(120),
+            validator: VALIDATOR.to_string(),
+        }]);
+
+        // a hub failing to handle the exit does not revert it
+        let res = reply(deps.as_mut(), mock_env(), Reply { id: HUB_NOTIFY_ID, result: SubMsgResult::Err("hub is broken".to_string()) }).unwrap();
+        assert_eq!(res.attributes[0].value, "false");
+    }
+
+    #[test]
+    fn emergency_unstake_all_requires_derivative() {
+        let mut deps = setup();
+        flag_emergency(&mut deps);
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::EmergencyUnstakeAll {}).unwrap_err();
+        assert_eq!(err, ContractError::DerivativeNotReturned { expected: Uint128::new(1000) });
+        let err = execute(deps.as_mut(), mock_env(), mock_info(DERIVATIVE, &[]), return_derivative(999)).unwrap_err();
+        assert_eq!(err, ContractError::DerivativeNotReturned { expected: Uint128::new(1000) });
+        // only the derivative token is accepted
+        let err = execute(deps.as_mut(), mock_env(), mock_info("othertoken", &[]), return_derivative(1000)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        // and only from the owner
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "anyone".to_string(),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::EmergencyUnstakeAll {}).unwrap(),
+        });
+        let err = execute(deps.as_mut(), mock_env(), mock_info(DERIVATIVE, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // without bonded tokens there is nothing to return
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Unstake { validator: VALIDATOR.to_string(), amount: Uint128::new(1000) }).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::EmergencyUnstakeAll {}).unwrap();
+        assert_eq!(res.messages.len(), 2);
+    }
+
+    #[test]
+    fn withdraw_after_exit_does_not_need_emergency() {
+        let mut deps = setup();
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Restake {
+            from: VALIDATOR.to_string(),
+            to: "validator2".to_string(),
+            amount: Uint128::new(400),
+            compounded: Uint128::zero(),
+        }).unwrap();
+        flag_emergency(&mut deps);
+        execute(deps.as_mut(), mock_env(), mock_info(DERIVATIVE, &[]), return_derivative(1000)).unwrap();
+        let redelegations: Vec<Redelegation> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Redelegations {}).unwrap()).unwrap();
+        assert_eq!(redelegations, vec![]);
+
+        // the hub recovers before the unbonding is done, the owner can still take the tokens out
+        hub_status(&mut deps, false);
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(120);
+        deps.querier.update_balance(env.contract.address.clone(), coins(1000, DENOM));
+        let res = execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: OWNER.to_string(),
+            amount: coins(1000, DENOM),
+        }));
+        let err = execute(deps.as_mut(), env, mock_info(HUB, &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    #[test]
+    fn emergency_withdraw_after_timelock() {
+        let mut deps = setup();
+        deps.querier.update_balance(mock_env().contract.address, coins(1234, DENOM));
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(TIMELOCK);
+        let res = execute(deps.as_mut(), env, mock_info(OWNER, &[]), ExecuteMsg::EmergencyWithdraw {}).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send {
+            to_address: OWNER.to_string(),
+            amount: coins(1234, DENOM),
+        }));
+    }
+
+    #[test]
+    fn vote_only_through_hub() {
+        let mut deps = setup();
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::Vote { proposal_id: 1, option: VoteOption::Yes }).unwrap_err();
+        assert_eq!(err, ContractError::UnknownUser {});
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Vote { proposal_id: 1, option: VoteOption::No }).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Gov(GovMsg::Vote { proposal_id: 1, vote: VoteOption::No }));
+    }
+
+    #[test]
+    fn vote_weighted_encodes_sdk_message() {
+        let mut deps = setup();
+
+        let options = vec![
+            WeightedVoteOption { option: VoteOption::Yes, weight: Decimal::percent(70) },
+            WeightedVoteOption { option: VoteOption::NoWithVeto, weight: Decimal::percent(30) },
+        ];
+        let res = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::VoteWeighted { proposal_id: 300, options }).unwrap();
+
+        let mut expected = vec![0x08, 0xac, 0x02, 0x12, 15];
+        expected.extend_from_slice(b"cosmos2contract");
+        expected.extend_from_slice(&[0x1a, 22, 0x08, 1, 0x12, 18]);
+        expected.extend_from_slice(b"700000000000000000");
+        expected.extend_from_slice(&[0x1a, 22, 0x08, 4, 0x12, 18]);
+        expected.extend_from_slice(b"300000000000000000");
+        assert_eq!(res.messages[0].msg, CosmosMsg::Stargate {
+            type_url: "/cosmos.gov.v1beta1.MsgVoteWeighted".to_string(),
+            value: Binary(expected),
+        });
+    }
+
+    #[test]
+    fn vote_weighted_rejects_invalid_weights() {
+        let mut deps = setup();
+
+        let invalid = vec![
+            vec![],
+            vec![WeightedVoteOption { option: VoteOption::Yes, weight: Decimal::percent(50) }],
+            vec![
+                WeightedVoteOption { option: VoteOption::Yes, weight: Decimal::percent(50) },
+                WeightedVoteOption { option: VoteOption::Yes, weight: Decimal::percent(50) },
+            ],
+            vec![
+                WeightedVoteOption { option: VoteOption::Yes, weight: Decimal::one() },
+                WeightedVoteOption { option: VoteOption::No, weight: Decimal::zero() },
+            ],
+        ];
+        for options in invalid {
+            let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::VoteWeighted { proposal_id: 1, options }).unwrap_err();
+            assert_eq!(err, ContractError::InvalidVoteWeights {});
+        }
+    }
+
+    #[test]
+    fn delegations_report_discrepancies() {
+        let mut deps = setup();
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Compound { validator: VALIDATOR.to_string(), amount: Uint128::new(100) }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &coins(500, DENOM)), ExecuteMsg::Stake { validator: "validator2".to_string() }).unwrap();
+
+        // validator got slashed, validator2 delegation is pending, validator3 was delegated outside of this contract
+        let validators: Vec<Validator> = [VALIDATOR, "validator2", "validator3"].iter().map(|address| Validator {
+            address: address.to_string(),
+            commission: Decimal::percent(5),
+            max_commission: Decimal::percent(10),
+            max_change_rate: Decimal::percent(1),
+        }).collect();
+        deps.querier.update_staking(DENOM, &validators, &[
+            FullDelegation {
+                delegator: mock_env().contract.address,
+                validator: VALIDATOR.to_string(),
+                amount: Coin::new(1050, DENOM),
+                can_redelegate: Coin::new(1050, DENOM),
+                accumulated_rewards: vec![Coin::new(7, DENOM), Coin::new(3, "uother")],
+            },
+            FullDelegation {
+                delegator: mock_env().contract.address,
+                validator: "validator3".to_string(),
+                amount: Coin::new(20, DENOM),
+                can_redelegate: Coin::new(20, DENOM),
+                accumulated_rewards: vec![],
+            },
+        ]);
+
+        let res: DelegationsResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Delegations {}).unwrap()).unwrap();
+        assert_eq!(res.delegations, vec![
+            DelegationInfo {
+                validator: VALIDATOR.to_string(),
+                delegated: Uint128::new(1050),
+                rewards: Uint128::new(7),
+                bonded: Uint128::new(1000),
+                compounded: Uint128::new(100),
+                shortfall: Uint128::new(50),
+                surplus: Uint128::zero(),
+            },
+            DelegationInfo {
+                validator: "validator2".to_string(),
+                delegated: Uint128::zero(),
+                rewards: Uint128::zero(),
+                bonded: Uint128::new(500),
+                compounded: Uint128::zero(),
+                shortfall: Uint128::new(500),
+                surplus: Uint128::zero(),
+            },
+            DelegationInfo {
+                validator: "validator3".to_string(),
+                delegated: Uint128::new(20),
+                rewards: Uint128::zero(),
+                bonded: Uint128::zero(),
+                compounded: Uint128::zero(),
+                shortfall: Uint128::zero(),
+                surplus: Uint128::new(20),
+            },
+        ]);
+    }
+
+    #[test]
+    fn instantiate_rejects_full_commission() {
+        let mut deps = mock_dependencies();
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info(HUB, &[]), InstantiateMsg {
+            denom: DENOM.to_string(),
+            owner: Addr::unchecked(OWNER),
+            derivative: DERIVATIVE.to_string(),
+            unbond_period: 120,
+            commission_rate: Decimal::one(),
+            emergency_timelock: TIMELOCK,
+        }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCommissionRate {});
+    }
+
+    #[test]
+    fn stake_rejects_invalid_funds() {
+        let mut deps = setup();
+        let stake = ExecuteMsg::Stake { validator: VALIDATOR.to_string() };
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), stake.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &coins(100, "uother")), stake.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MissingDenom(DENOM.to_string())));
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[coin(100, DENOM), coin(100, "uother")]), stake).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MultipleDenoms {}));
+    }
+
+    #[test]
+    fn decompound_cannot_exceed_compounded() {
+        let mut deps = setup();
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Compound { validator: VALIDATOR.to_string(), amount: Uint128::new(100) }).unwrap();
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Decompound { validator: VALIDATOR.to_string(), amount: Uint128::new(101) }).unwrap_err();
+        assert_eq!(err, ContractError::InvalidDecompoundAmount {});
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Decompound { validator: VALIDATOR.to_string(), amount: Uint128::new(100) }).unwrap();
+    }
+
+    #[test]
+    fn mul_decimal_overflow_is_an_error() {
+        assert_eq!(mul_decimal(Uint128::new(1000), Decimal::percent(15)).unwrap(), Uint128::new(150));
+        let err = mul_decimal(Uint128::MAX, Decimal::percent(101)).unwrap_err();
+        assert!(matches!(err, StdError::Overflow { .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn withdraw_rejects_balance_below_unbondings() {
+        let mut deps = setup();
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Unstake { validator: VALIDATOR.to_string(), amount: Uint128::new(500) }).unwrap();
+        deps.querier.update_balance(mock_env().contract.address, coins(499, DENOM));
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(120);
+        let err = execute(deps.as_mut(), env, mock_info(HUB, &[]), ExecuteMsg::Withdraw {}).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientBalance {});
+    }
+
+    #[test]
+    fn restake_moves_tracking_and_records_redelegation() {
+        let mut deps = setup();
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Compound { validator: VALIDATOR.to_string(), amount: Uint128::new(100) }).unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Restake {
+            from: VALIDATOR.to_string(),
+            to: "validator2".to_string(),
+            amount: Uint128::new(400),
+            compounded: Uint128::new(40),
+        }).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator: VALIDATOR.to_string(),
+            dst_validator: "validator2".to_string(),
+            amount: Coin::new(440, DENOM),
+        }));
+        assert_eq!(VALIDATORS.load(&deps.storage, VALIDATOR).unwrap(), ValidatorStake { bonded: Uint128::new(600), compounded: Uint128::new(60) });
+        assert_eq!(VALIDATORS.load(&deps.storage, "validator2").unwrap(), ValidatorStake { bonded: Uint128::new(400), compounded: Uint128::new(40) });
+
+        let redelegations: Vec<Redelegation> = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Redelegations {}).unwrap()).unwrap();
+        assert_eq!(redelegations, vec![Redelegation {
+            from: VALIDATOR.to_string(),
+            to: "validator2".to_string(),
+            amount: Uint128::new(440),
+            complete_date: mock_env().block.time.plus_seconds(120),
+        }]);
+    }
+
+    #[test]
+    fn restake_from_redelegation_destination_waits_for_completion() {
+        let mut deps = setup();
+        let restake = |from: &str, to: &str| ExecuteMsg::Restake {
+            from: from.to_string(),
+            to: to.to_string(),
+            amount: Uint128::new(100),
+            compounded: Uint128::zero(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), restake(VALIDATOR, "validator2")).unwrap();
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), restake("validator2", "validator3")).unwrap_err();
+        assert_eq!(err, ContractError::OnRedelegationPeriod {
+            validator: "validator2".to_string(),
+            complete_date: mock_env().block.time.plus_seconds(120),
+        });
+        // redelegating out of the source again is fine
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), restake(VALIDATOR, "validator3")).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(120);
+        execute(deps.as_mut(), env.clone(), mock_info(HUB, &[]), restake("validator2", "validator3")).unwrap();
+        let redelegations: Vec<Redelegation> = from_binary(&query(deps.as_ref(), env, QueryMsg::Redelegations {}).unwrap()).unwrap();
+        assert_eq!(redelegations.len(), 1);
+    }
+
+    #[test]
+    fn cannot_unstake_or_restake_more_than_tracked() {
+        let mut deps = setup();
+        execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), ExecuteMsg::Compound { validator: VALIDATOR.to_string(), amount: Uint128::new(100) }).unwrap();
+        let unstake = |validator: &str, amount: u128| ExecuteMsg::Unstake { validator: validator.to_string(), amount: Uint128::new(amount) };
+        let restake = |amount: u128, compounded: u128| ExecuteMsg::Restake {
+            from: VALIDATOR.to_string(),
+            to: "validator2".to_string(),
+            amount: Uint128::new(amount),
+            compounded: Uint128::new(compounded),
+        };
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), unstake(VALIDATOR, 1001)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientBalance {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), unstake("validator2", 1)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientBalance {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), restake(1001, 0)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientBalance {});
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), restake(1000, 101)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientBalance {});
+        assert_eq!(VALIDATORS.load(&deps.storage, VALIDATOR).unwrap(), ValidatorStake { bonded: Uint128::new(1000), compounded: Uint128::new(100) });
+        assert_eq!(UNBONDED.load(&deps.storage).unwrap(), vec![]);
+
+        // stake taken out through an emergency exit cannot be unstaked again through the hub
+        flag_emergency(&mut deps);
+        execute(deps.as_mut(), mock_env(), mock_info(DERIVATIVE, &[]), return_derivative(1000)).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info(HUB, &[]), unstake(VALIDATOR, 1000)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientBalance {});
+    }
+}
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{OverflowError, StdError, Timestamp, Uint128};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +7,12 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -56,4 +63,25 @@ pub enum ContractError {
 
     #[error("Cannot compound when unbonded tokens exist, unbond first")]
     CompoundWithUnbondeds {},
+
+    #[error("Emergency exit is not enabled")]
+    EmergencyNotEnabled {},
+
+    #[error("Emergency exit requires returning exactly the {} derivative tokens minted for the bonded amount, partial returns are not accepted", expected)]
+    DerivativeNotReturned { expected: Uint128 },
+
+    #[error("Vote weights must be positive, unique per option and sum up to 1")]
+    InvalidVoteWeights {},
+
+    #[error("Commission rate must be lower than 1")]
+    InvalidCommissionRate {},
+
+    #[error("Decompound amount exceeds compounded amount")]
+    InvalidDecompoundAmount {},
+
+    #[error("Amount exceeds the balance held or tracked by this contract")]
+    InsufficientBalance {},
+
+    #[error("Validator {} is still receiving a redelegation, cannot redelegate from it until {}", validator, complete_date)]
+    OnRedelegationPeriod { validator: String, complete_date: Timestamp },
 }
\ No newline at end of file
@@ -1,12 +1,16 @@
 use serde::{Serialize, Deserialize};
 use schemars::JsonSchema;
-use cosmwasm_std::{Uint128, Addr, Decimal};
+use cosmwasm_std::{Uint128, Addr, Decimal, VoteOption, Timestamp};
+use cw20::Cw20ReceiveMsg;
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct InstantiateMsg {
     pub denom : String,
     pub owner : Addr,
+    // cw20 token the hub mints to the owner for the bonded amount
+    pub derivative : String,
     pub unbond_period: u64,
     pub commission_rate: Decimal,
+    pub emergency_timelock: u64,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
@@ -15,10 +19,31 @@ pub enum ExecuteMsg {
     Stake { validator: String },
     Unstake { validator: String, amount: Uint128 },
     Collect {validator: String },
-    Restake { from: String, to: String, amount: Uint128 },
+    // amount is bonded principal, compounded is moved along with it
+    Restake { from: String, to: String, amount: Uint128, compounded: Uint128 },
     Withdraw { },
     Compound { validator: String, amount: Uint128},
     Decompound { validator: String, amount: Uint128},
+    // owner only, available when hub is in emergency mode or timelock has passed.
+    // with bonded tokens, unstaking all is done by sending back the derivative through Receive
+    EmergencyUnstakeAll {},
+    EmergencyWithdraw {},
+    Receive(Cw20ReceiveMsg),
+    Vote { proposal_id: u64, option: VoteOption },
+    VoteWeighted { proposal_id: u64, options: Vec<WeightedVoteOption> },
+}
+
+// sent by the derivative token along with the returned derivative
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    EmergencyUnstakeAll {},
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct WeightedVoteOption {
+    pub option: VoteOption,
+    pub weight: Decimal,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
@@ -27,5 +52,49 @@ pub enum QueryMsg {
     ConfigInfo {},
     Unbondings {},
     Rewards {},
+    Delegations {},
+    Redelegations {},
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct DelegationsResponse {
+    pub delegations: Vec<DelegationInfo>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct DelegationInfo {
+    pub validator: String,
+    // on-chain delegation and its pending rewards, in config denom
+    pub delegated: Uint128,
+    pub rewards: Uint128,
+    // amounts tracked by this contract
+    pub bonded: Uint128,
+    pub compounded: Uint128,
+    // tracked amount exceeding the on-chain delegation (e.g. slashed)
+    pub shortfall: Uint128,
+    // on-chain delegation exceeding the tracked amount
+    pub surplus: Uint128,
 }
 
+
+
+// subset of hub's QueryMsg, used to check whether hub has flagged emergency mode or stopped working
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HubQueryMsg {
+    Status {},
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct HubStatusResponse {
+    pub emergency: bool,
+    // last time the hub executed anything
+    pub last_heartbeat: Timestamp,
+}
+
+// subset of hub's ExecuteMsg, tells the hub the owner has left through an emergency exit
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HubExecuteMsg {
+    ProxyExited {},
+}
@@ -1,7 +1,7 @@
 
 use serde::{Serialize, Deserialize};
 use schemars::JsonSchema;
-use cw_storage_plus::Item;
+use cw_storage_plus::{Item, Map};
 use cosmwasm_std::{Addr, Uint128, Timestamp, Decimal};
 
 
@@ -9,9 +9,11 @@ use cosmwasm_std::{Addr, Uint128, Timestamp, Decimal};
 pub struct Config {
     pub owner : Addr,
     pub admin : Addr,
+    pub derivative : Addr,
     pub denom : String,
     pub unbond_period: u64,
     pub commission_rate: Decimal,
+    pub emergency_timelock: u64,
 }
 #[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct Unbonded {
@@ -20,7 +22,27 @@ pub struct Unbonded {
     pub validator: String,
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Redelegation {
+    pub from: String,
+    pub to: String,
+    pub amount: Uint128,
+    pub complete_date: Timestamp,
+}
+
+// amounts tracked by this contract for a single validator
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Default)]
+pub struct ValidatorStake {
+    pub bonded: Uint128,
+    pub compounded: Uint128,
+}
+
 pub const CONFIG : Item<Config> = Item::new("config");
 pub const BONDED : Item<Uint128> = Item::new("bonded");
 pub const UNBONDED : Item<Vec<Unbonded>> = Item::new("unbonded");
-pub const COMPOUNDED : Item<Uint128> = Item::new("compounded");
\ No newline at end of file
+pub const COMPOUNDED : Item<Uint128> = Item::new("compounded");
+pub const REDELEGATIONS : Item<Vec<Redelegation>> = Item::new("redelegations");
+pub const VALIDATORS : Map<&str, ValidatorStake> = Map::new("validators");
+// set once the owner took the delegations out with EmergencyUnstakeAll, the hub has dropped
+// this proxy by then, so withdrawing no longer depends on the hub's emergency state
+pub const EXITED : Item<bool> = Item::new("exited");
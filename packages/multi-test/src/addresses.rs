@@ -0,0 +1,482 @@
+//! Address generation for new contracts, and an `Api` validating bech32 addresses
+//! like a real chain does.
+
+use anyhow::{bail, Result as AnyResult};
+use sha2::{Digest, Sha256};
+
+use cosmwasm_std::testing::MockApi;
+use cosmwasm_std::{
+    Addr, Api, CanonicalAddr, RecoverPubkeyError, StdError, StdResult, VerificationError,
+};
+
+/// Picks the addresses of newly instantiated contracts
+pub trait AddressGenerator {
+    /// Address of the `instance_id`th contract, counting from 1 over all codes.
+    fn contract_address(&self, code_id: u64, instance_id: u64) -> AnyResult<Addr>;
+
+    /// Address derived from the code checksum, the creator and a salt only, so it is known
+    /// before instantiating, like instantiate2 in wasmd assigns them.
+    fn predictable_contract_address(
+        &self,
+        checksum: &[u8],
+        creator: &CanonicalAddr,
+        salt: &[u8],
+    ) -> AnyResult<Addr>;
+}
+
+/// Generates `contract0`, `contract1`, ... which `MockApi` accepts. This is the default.
+#[derive(Default, Clone, Debug)]
+pub struct SimpleAddressGenerator {}
+
+impl AddressGenerator for SimpleAddressGenerator {
+    fn contract_address(&self, _code_id: u64, instance_id: u64) -> AnyResult<Addr> {
+        match instance_id.checked_sub(1) {
+            Some(index) => Ok(Addr::unchecked(format!("contract{}", index))),
+            None => bail!("Contract instance ids start at 1"),
+        }
+    }
+
+    fn predictable_contract_address(
+        &self,
+        checksum: &[u8],
+        creator: &CanonicalAddr,
+        salt: &[u8],
+    ) -> AnyResult<Addr> {
+        let hash = instantiate2_hash(checksum, creator, salt)?;
+        Ok(Addr::unchecked(format!(
+            "contract{}",
+            hex::encode(&hash[..8])
+        )))
+    }
+}
+
+/// Generates the bech32 addresses wasmd assigns, with the given prefix
+#[derive(Clone, Debug)]
+pub struct Bech32AddressGenerator {
+    prefix: String,
+}
+
+impl Bech32AddressGenerator {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Bech32AddressGenerator {
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl AddressGenerator for Bech32AddressGenerator {
+    fn contract_address(&self, code_id: u64, instance_id: u64) -> AnyResult<Addr> {
+        // see https://github.com/CosmWasm/wasmd/blob/v0.27.0/x/wasm/keeper/addresses.go#L12-L18
+        let mut key = b"wasm\0".to_vec();
+        key.extend_from_slice(&code_id.to_be_bytes());
+        key.extend_from_slice(&instance_id.to_be_bytes());
+        let hash = module_hash(&key);
+        Ok(Addr::unchecked(bech32_encode(&self.prefix, &hash)?))
+    }
+
+    fn predictable_contract_address(
+        &self,
+        checksum: &[u8],
+        creator: &CanonicalAddr,
+        salt: &[u8],
+    ) -> AnyResult<Addr> {
+        let hash = instantiate2_hash(checksum, creator, salt)?;
+        Ok(Addr::unchecked(bech32_encode(&self.prefix, &hash)?))
+    }
+}
+
+/// `address.Module` of the cosmos sdk
+fn module_hash(key: &[u8]) -> Vec<u8> {
+    let typ = Sha256::digest(b"module");
+    Sha256::new().chain(typ).chain(key).finalize().to_vec()
+}
+
+fn instantiate2_hash(checksum: &[u8], creator: &CanonicalAddr, salt: &[u8]) -> AnyResult<Vec<u8>> {
+    if salt.is_empty() || salt.len() > 64 {
+        bail!("Salt must be between 1 and 64 bytes long");
+    }
+    // see https://github.com/CosmWasm/wasmd/blob/v0.29.0/x/wasm/keeper/addresses.go#L34-L61
+    // the init message is not part of the address
+    let mut key = b"wasm\0".to_vec();
+    for part in [checksum, creator.as_slice(), salt, &[]] {
+        key.extend_from_slice(&(part.len() as u64).to_be_bytes());
+        key.extend_from_slice(part);
+    }
+    Ok(module_hash(&key))
+}
+
+/// Validates addresses as bech32 strings with a fixed prefix, so tests fail on addresses
+/// a chain would reject. Crypto functions are the ones of `MockApi`.
+#[derive(Clone)]
+pub struct Bech32Api {
+    prefix: String,
+    mock: MockApi,
+}
+
+impl Bech32Api {
+    /// Fails if the prefix cannot be used in bech32 addresses
+    pub fn new(prefix: impl Into<String>) -> AnyResult<Self> {
+        let prefix = prefix.into();
+        check_hrp(&prefix)?;
+        Ok(Bech32Api {
+            prefix,
+            mock: MockApi::default(),
+        })
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Valid address derived from `name`, to give users of tests readable names
+    pub fn addr_make(&self, name: &str) -> Addr {
+        let hash = Sha256::digest(name.as_bytes());
+        // encoding only fails for invalid prefixes, which `new` rejects
+        Addr::unchecked(bech32_encode(&self.prefix, &hash).unwrap())
+    }
+}
+
+impl Api for Bech32Api {
+    fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+        let canonical = self.addr_canonicalize(human)?;
+        let normalized = self.addr_humanize(&canonical)?;
+        if normalized != human {
+            return Err(StdError::generic_err(format!(
+                "Address {} is not normalized, expected {}",
+                human, normalized
+            )));
+        }
+        Ok(normalized)
+    }
+
+    fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+        let (prefix, data) = bech32_decode(human).map_err(|err| {
+            StdError::generic_err(format!("Invalid bech32 address {}: {}", human, err))
+        })?;
+        if prefix != self.prefix {
+            return Err(StdError::generic_err(format!(
+                "Wrong prefix of address {}, expected {}",
+                human, self.prefix
+            )));
+        }
+        Ok(data.into())
+    }
+
+    fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        bech32_encode(&self.prefix, canonical.as_slice())
+            .map(Addr::unchecked)
+            .map_err(|err| StdError::generic_err(err.to_string()))
+    }
+
+    fn secp256k1_verify(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.mock
+            .secp256k1_verify(message_hash, signature, public_key)
+    }
+
+    fn secp256k1_recover_pubkey(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        self.mock
+            .secp256k1_recover_pubkey(message_hash, signature, recovery_param)
+    }
+
+    fn ed25519_verify(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.mock.ed25519_verify(message, signature, public_key)
+    }
+
+    fn ed25519_batch_verify(
+        &self,
+        messages: &[&[u8]],
+        signatures: &[&[u8]],
+        public_keys: &[&[u8]],
+    ) -> Result<bool, VerificationError> {
+        self.mock
+            .ed25519_batch_verify(messages, signatures, public_keys)
+    }
+
+    fn debug(&self, message: &str) {
+        self.mock.debug(message)
+    }
+}
+
+// bech32 as specified in https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+const MAX_LENGTH: usize = 90;
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk = 1u32;
+    for value in values {
+        let top = chk >> 25;
+        chk = (chk & 0x01ff_ffff) << 5 ^ u32::from(*value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut expanded: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    expanded.push(0);
+    expanded.extend(hrp.bytes().map(|b| b & 31));
+    expanded
+}
+
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> AnyResult<Vec<u8>> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let max = (1 << to) - 1;
+    let mut converted = vec![];
+    for value in data {
+        acc = (acc << from) | u32::from(*value);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        bail!("invalid padding");
+    }
+    Ok(converted)
+}
+
+fn check_hrp(hrp: &str) -> AnyResult<()> {
+    if hrp.is_empty() || hrp.bytes().any(|b| !(33..=126).contains(&b)) {
+        bail!("invalid prefix {:?}", hrp);
+    }
+    if hrp.bytes().any(|b| b.is_ascii_uppercase()) {
+        bail!("prefix {} is not lowercase", hrp);
+    }
+    Ok(())
+}
+
+fn bech32_encode(hrp: &str, data: &[u8]) -> AnyResult<String> {
+    check_hrp(hrp)?;
+    let mut values = convert_bits(data, 8, 5, true)?;
+    let mut checksum_input = hrp_expand(hrp);
+    checksum_input.extend_from_slice(&values);
+    checksum_input.extend_from_slice(&[0; 6]);
+    let checksum = polymod(&checksum_input) ^ 1;
+    values.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8));
+
+    let mut encoded = format!("{}1", hrp);
+    encoded.extend(values.iter().map(|v| CHARSET[*v as usize] as char));
+    Ok(encoded)
+}
+
+fn bech32_decode(encoded: &str) -> AnyResult<(String, Vec<u8>)> {
+    if encoded.len() > MAX_LENGTH {
+        bail!("longer than {} characters", MAX_LENGTH);
+    }
+    if encoded.bytes().any(|b| b.is_ascii_uppercase()) {
+        // mixed case is invalid, uppercase is not normalized
+        bail!("not lowercase");
+    }
+    let separator = match encoded.rfind('1') {
+        Some(separator) if separator > 0 && separator + 7 <= encoded.len() => separator,
+        _ => bail!("no valid separator"),
+    };
+    let (hrp, data) = (&encoded[..separator], &encoded[separator + 1..]);
+    check_hrp(hrp)?;
+
+    let values = data
+        .bytes()
+        .map(|c| match CHARSET.iter().position(|v| *v == c) {
+            Some(value) => Ok(value as u8),
+            None => bail!("invalid character {:?}", c as char),
+        })
+        .collect::<AnyResult<Vec<u8>>>()?;
+    let mut checksum_input = hrp_expand(hrp);
+    checksum_input.extend_from_slice(&values);
+    if polymod(&checksum_input) != 1 {
+        bail!("invalid checksum");
+    }
+    let data = convert_bits(&values[..values.len() - 6], 5, 8, false)?;
+    Ok((hrp.to_string(), data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::module::FailingModule;
+    use crate::test_helpers::contracts::payout;
+    use crate::{AppBuilder, Executor, WasmKeeper};
+    use cosmwasm_std::{coin, Empty};
+
+    #[test]
+    fn bech32_round_trip() {
+        // test vector of bip-0173
+        let (hrp, data) = bech32_decode("a12uel5l").unwrap();
+        assert_eq!(hrp, "a");
+        assert!(data.is_empty());
+        bech32_decode("a12uel5m").unwrap_err();
+
+        let api = Bech32Api::new("darc").unwrap();
+        let addr = api.addr_make("owner");
+        assert!(addr.as_str().starts_with("darc1"));
+        assert_eq!(api.addr_validate(addr.as_str()).unwrap(), addr);
+        let canonical = api.addr_canonicalize(addr.as_str()).unwrap();
+        assert_eq!(canonical.len(), 32);
+        assert_eq!(api.addr_humanize(&canonical).unwrap(), addr);
+    }
+
+    #[test]
+    fn bech32_api_rejects_invalid_addresses() {
+        let api = Bech32Api::new("darc").unwrap();
+        let addr = api.addr_make("owner");
+
+        // plain names as used with MockApi
+        api.addr_validate("owner").unwrap_err();
+        // other chains
+        let juno = Bech32Api::new("juno").unwrap().addr_make("owner");
+        let err = api.addr_validate(juno.as_str()).unwrap_err();
+        assert!(err.to_string().contains("Wrong prefix"), "{}", err);
+        // not normalized
+        api.addr_validate(&addr.as_str().to_uppercase())
+            .unwrap_err();
+        // typos
+        let mut typo = addr.to_string();
+        typo.replace_range(10..11, if &typo[10..11] == "q" { "p" } else { "q" });
+        api.addr_validate(&typo).unwrap_err();
+
+        // prefixes that cannot be encoded
+        assert!(Bech32Api::new("").is_err());
+        assert!(Bech32Api::new("Darc").is_err());
+        assert!(Bech32Api::new("da rc").is_err());
+    }
+
+    #[test]
+    fn simple_addresses() {
+        let generator = SimpleAddressGenerator::default();
+        assert_eq!(generator.contract_address(1, 1).unwrap(), "contract0");
+        assert_eq!(generator.contract_address(2, 3).unwrap(), "contract2");
+        generator.contract_address(1, 0).unwrap_err();
+    }
+
+    #[test]
+    fn wasmd_addresses() {
+        let generator = Bech32AddressGenerator::new("cosmos");
+        // first contract of the first code, as derived by wasmd
+        assert_eq!(
+            generator.contract_address(1, 1).unwrap(),
+            "cosmos14hj2tavq8fpesdwxxcu44rty3hh90vhujrvcmstl4zr3txmfvw9s4hmalr"
+        );
+        assert_ne!(
+            generator.contract_address(1, 2).unwrap(),
+            generator.contract_address(2, 1).unwrap()
+        );
+
+        let creator = CanonicalAddr::from(vec![0x9e; 20]);
+        let predictable = generator
+            .predictable_contract_address(&[0x13; 32], &creator, b"salt")
+            .unwrap();
+        assert_eq!(
+            predictable,
+            generator
+                .predictable_contract_address(&[0x13; 32], &creator, b"salt")
+                .unwrap()
+        );
+        assert_ne!(
+            predictable,
+            generator
+                .predictable_contract_address(&[0x13; 32], &creator, b"pepper")
+                .unwrap()
+        );
+        generator
+            .predictable_contract_address(&[0x13; 32], &creator, b"")
+            .unwrap_err();
+    }
+
+    #[test]
+    fn app_with_bech32_addresses() {
+        let api = Bech32Api::new("darc").unwrap();
+        let owner = api.addr_make("owner");
+        let wasm = WasmKeeper::<Empty, Empty>::new()
+            .with_address_generator(Bech32AddressGenerator::new("darc"));
+        let mut app = AppBuilder::new()
+            .with_api(api.clone())
+            .with_wasm::<FailingModule<Empty, Empty, Empty>, _>(wasm)
+            .build(|_, _, _| {});
+        let code_id = app.store_code(payout::contract());
+        let init_msg = payout::InstantiateMessage {
+            payout: coin(5, "eth"),
+        };
+
+        let contract = app
+            .instantiate_contract(code_id, owner.clone(), &init_msg, &[], "payout", None)
+            .unwrap();
+        assert_eq!(
+            contract,
+            Bech32AddressGenerator::new("darc")
+                .contract_address(code_id, 1)
+                .unwrap()
+        );
+        api.addr_validate(contract.as_str()).unwrap();
+
+        // addresses of MockApi are rejected now
+        app.execute_contract(owner.clone(), Addr::unchecked("contract0"), &Empty {}, &[])
+            .unwrap_err();
+
+        // instantiate2 puts the contract where it was predicted
+        let predicted = app
+            .predictable_contract_address(code_id, &owner, b"salt")
+            .unwrap();
+        let contract = app
+            .instantiate2_contract(
+                code_id,
+                owner.clone(),
+                &init_msg,
+                &[],
+                "payout",
+                None,
+                b"salt".to_vec(),
+            )
+            .unwrap();
+        assert_eq!(contract, predicted);
+        api.addr_validate(contract.as_str()).unwrap();
+        // but only once
+        let err = app
+            .instantiate2_contract(
+                code_id,
+                owner,
+                &init_msg,
+                &[],
+                "payout",
+                None,
+                b"salt".to_vec(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Contract address {} is already taken", contract)
+        );
+    }
+}
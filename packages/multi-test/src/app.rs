@@ -5,22 +5,58 @@ use anyhow::bail;
 use anyhow::Result as AnyResult;
 use cosmwasm_std::testing::{mock_env, MockApi, MockStorage};
 use cosmwasm_std::{
-    from_slice, to_binary, Addr, Api, Binary, BlockInfo, ContractResult, CosmosMsg, CustomQuery,
-    Empty, Querier, QuerierResult, QuerierWrapper, QueryRequest, Record, Storage, SystemError,
-    SystemResult,
+    from_slice, to_binary, Addr, Api, Binary, BlockInfo, Coin, ContractResult, CosmosMsg,
+    CustomQuery, Empty, Querier, QuerierResult, QuerierWrapper, QueryRequest, Record, StdResult,
+    Storage, SystemError, SystemResult, WasmMsg,
 };
+use cw_utils::parse_instantiate_response_data;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::bank::{Bank, BankKeeper, BankSudo};
 use crate::contracts::Contract;
+use crate::error::Error;
 use crate::executor::{AppResponse, Executor};
+use crate::gas::{GasConfig, GasMeter};
+#[cfg(feature = "stargate")]
+use crate::gov::{Gov, GovKeeper, GovSudo};
+#[cfg(feature = "stargate")]
+use crate::ibc::{Ibc, IbcKeeper, IbcSudo};
 use crate::module::{FailingModule, Module};
-use crate::staking::{Distribution, FailingDistribution, FailingStaking, Staking, StakingSudo};
+use crate::staking::{
+    Distribution, DistributionKeeper, DistributionQuery, StakeKeeper, Staking, StakingSudo,
+};
+#[cfg(feature = "stargate")]
+use crate::stargate::{Stargate, StargateKeeper, StargateMsg, StargateQuery};
 use crate::transactions::transactional;
+#[cfg(feature = "stargate")]
+use crate::wasm::WasmIbcSudo;
 use crate::wasm::{ContractData, Wasm, WasmKeeper, WasmSudo};
 
+// Without the `stargate` feature the router keeps its gov, ibc and stargate slots,
+// but no message can reach them.
+#[cfg(not(feature = "stargate"))]
+pub trait Gov {}
+#[cfg(not(feature = "stargate"))]
+pub trait Ibc {}
+#[cfg(not(feature = "stargate"))]
+pub trait Stargate {}
+
+#[cfg(not(feature = "stargate"))]
+impl<ExecT, QueryT, SudoT> Gov for FailingModule<ExecT, QueryT, SudoT> {}
+#[cfg(not(feature = "stargate"))]
+impl<ExecT, QueryT, SudoT> Ibc for FailingModule<ExecT, QueryT, SudoT> {}
+#[cfg(not(feature = "stargate"))]
+impl<ExecT, QueryT, SudoT> Stargate for FailingModule<ExecT, QueryT, SudoT> {}
+
+#[cfg(not(feature = "stargate"))]
+pub type GovKeeper = FailingModule<Empty, Empty, Empty>;
+#[cfg(not(feature = "stargate"))]
+pub type IbcKeeper = FailingModule<Empty, Empty, Empty>;
+#[cfg(not(feature = "stargate"))]
+pub type StargateKeeper = FailingModule<Empty, Empty, Empty>;
+
 pub fn next_block(block: &mut BlockInfo) {
     block.time = block.time.plus_seconds(5);
     block.height += 1;
@@ -44,17 +80,20 @@ pub struct App<
     Storage = MockStorage,
     Custom = FailingModule<Empty, Empty, Empty>,
     Wasm = WasmKeeper<Empty, Empty>,
-    Staking = FailingStaking,
-    Distr = FailingDistribution,
+    Staking = StakeKeeper,
+    Distr = DistributionKeeper,
+    Gov = GovKeeper,
+    Ibc = IbcKeeper,
+    Stargate = StargateKeeper,
 > {
-    router: Router<Bank, Custom, Wasm, Staking, Distr>,
+    router: Router<Bank, Custom, Wasm, Staking, Distr, Gov, Ibc, Stargate>,
     api: Api,
     storage: Storage,
     block: BlockInfo,
 }
 
-fn no_init<BankT, CustomT, WasmT, StakingT, DistrT>(
-    _: &mut Router<BankT, CustomT, WasmT, StakingT, DistrT>,
+fn no_init<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>(
+    _: &mut Router<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>,
     _: &dyn Api,
     _: &mut dyn Storage,
 ) {
@@ -75,8 +114,11 @@ impl BasicApp {
                 BankKeeper,
                 FailingModule<Empty, Empty, Empty>,
                 WasmKeeper<Empty, Empty>,
-                FailingStaking,
-                FailingDistribution,
+                StakeKeeper,
+                DistributionKeeper,
+                GovKeeper,
+                IbcKeeper,
+                StargateKeeper,
             >,
             &dyn Api,
             &mut dyn Storage,
@@ -97,8 +139,11 @@ where
             BankKeeper,
             FailingModule<ExecC, QueryC, Empty>,
             WasmKeeper<ExecC, QueryC>,
-            FailingStaking,
-            FailingDistribution,
+            StakeKeeper,
+            DistributionKeeper,
+            GovKeeper,
+            IbcKeeper,
+            StargateKeeper,
         >,
         &dyn Api,
         &mut dyn Storage,
@@ -107,11 +152,12 @@ where
     AppBuilder::new_custom().build(init_fn)
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT> Querier
-    for App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT> Querier
+    for App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
 where
     CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
+    CustomT::SudoT: DeserializeOwned,
     WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
     BankT: Bank,
     ApiT: Api,
@@ -119,6 +165,9 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    GovT: Gov,
+    IbcT: Ibc,
+    StargateT: Stargate,
 {
     fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
         self.router
@@ -127,11 +176,13 @@ where
     }
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT> Executor<CustomT::ExecT>
-    for App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    Executor<CustomT::ExecT>
+    for App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
 where
     CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
+    CustomT::SudoT: DeserializeOwned,
     WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
     BankT: Bank,
     ApiT: Api,
@@ -139,6 +190,9 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    GovT: Gov,
+    IbcT: Ibc,
+    StargateT: Stargate,
 {
     fn execute(
         &mut self,
@@ -159,12 +213,15 @@ pub type BasicAppBuilder<ExecC, QueryC> = AppBuilder<
     MockStorage,
     FailingModule<ExecC, QueryC, Empty>,
     WasmKeeper<ExecC, QueryC>,
-    FailingStaking,
-    FailingDistribution,
+    StakeKeeper,
+    DistributionKeeper,
+    GovKeeper,
+    IbcKeeper,
+    StargateKeeper,
 >;
 
 /// Utility to build App in stages. If particular items wont be set, defaults would be used
-pub struct AppBuilder<Bank, Api, Storage, Custom, Wasm, Staking, Distr> {
+pub struct AppBuilder<Bank, Api, Storage, Custom, Wasm, Staking, Distr, Gov, Ibc, Stargate> {
     api: Api,
     block: BlockInfo,
     storage: Storage,
@@ -173,6 +230,10 @@ pub struct AppBuilder<Bank, Api, Storage, Custom, Wasm, Staking, Distr> {
     custom: Custom,
     staking: Staking,
     distribution: Distr,
+    gov: Gov,
+    ibc: Ibc,
+    stargate: Stargate,
+    gas: Option<GasConfig>,
 }
 
 impl Default
@@ -182,8 +243,11 @@ impl Default
         MockStorage,
         FailingModule<Empty, Empty, Empty>,
         WasmKeeper<Empty, Empty>,
-        FailingStaking,
-        FailingDistribution,
+        StakeKeeper,
+        DistributionKeeper,
+        GovKeeper,
+        IbcKeeper,
+        StargateKeeper,
     >
 {
     fn default() -> Self {
@@ -198,8 +262,11 @@ impl
         MockStorage,
         FailingModule<Empty, Empty, Empty>,
         WasmKeeper<Empty, Empty>,
-        FailingStaking,
-        FailingDistribution,
+        StakeKeeper,
+        DistributionKeeper,
+        GovKeeper,
+        IbcKeeper,
+        StargateKeeper,
     >
 {
     /// Creates builder with default components working with empty exec and query messages.
@@ -211,8 +278,12 @@ impl
             bank: BankKeeper::new(),
             wasm: WasmKeeper::new(),
             custom: FailingModule::new(),
-            staking: FailingStaking::new(),
-            distribution: FailingDistribution::new(),
+            staking: StakeKeeper::new(),
+            distribution: DistributionKeeper::new(),
+            gov: GovKeeper::new(),
+            ibc: IbcKeeper::new(),
+            stargate: StargateKeeper::new(),
+            gas: None,
         }
     }
 }
@@ -224,8 +295,11 @@ impl<ExecC, QueryC>
         MockStorage,
         FailingModule<ExecC, QueryC, Empty>,
         WasmKeeper<ExecC, QueryC>,
-        FailingStaking,
-        FailingDistribution,
+        StakeKeeper,
+        DistributionKeeper,
+        GovKeeper,
+        IbcKeeper,
+        StargateKeeper,
     >
 where
     ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
@@ -241,14 +315,18 @@ where
             bank: BankKeeper::new(),
             wasm: WasmKeeper::new(),
             custom: FailingModule::new(),
-            staking: FailingStaking::new(),
-            distribution: FailingDistribution::new(),
+            staking: StakeKeeper::new(),
+            distribution: DistributionKeeper::new(),
+            gov: GovKeeper::new(),
+            ibc: IbcKeeper::new(),
+            stargate: StargateKeeper::new(),
+            gas: None,
         }
     }
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
-    AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
 {
     /// Overwrites default wasm executor.
     ///
@@ -262,7 +340,8 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_wasm<C: Module, NewWasm: Wasm<C::ExecT, C::QueryT>>(
         self,
         wasm: NewWasm,
-    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, NewWasm, StakingT, DistrT> {
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, NewWasm, StakingT, DistrT, GovT, IbcT, StargateT>
+    {
         let AppBuilder {
             bank,
             api,
@@ -271,6 +350,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
             ..
         } = self;
 
@@ -283,6 +366,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
         }
     }
 
@@ -290,7 +377,8 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_bank<NewBank: Bank>(
         self,
         bank: NewBank,
-    ) -> AppBuilder<NewBank, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT> {
+    ) -> AppBuilder<NewBank, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    {
         let AppBuilder {
             wasm,
             api,
@@ -299,6 +387,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
             ..
         } = self;
 
@@ -311,6 +403,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
         }
     }
 
@@ -318,7 +414,8 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_api<NewApi: Api>(
         self,
         api: NewApi,
-    ) -> AppBuilder<BankT, NewApi, StorageT, CustomT, WasmT, StakingT, DistrT> {
+    ) -> AppBuilder<BankT, NewApi, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    {
         let AppBuilder {
             wasm,
             bank,
@@ -327,6 +424,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
             ..
         } = self;
 
@@ -339,6 +440,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
         }
     }
 
@@ -346,7 +451,8 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_storage<NewStorage: Storage>(
         self,
         storage: NewStorage,
-    ) -> AppBuilder<BankT, ApiT, NewStorage, CustomT, WasmT, StakingT, DistrT> {
+    ) -> AppBuilder<BankT, ApiT, NewStorage, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    {
         let AppBuilder {
             wasm,
             api,
@@ -355,6 +461,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
             ..
         } = self;
 
@@ -367,6 +477,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
         }
     }
 
@@ -382,7 +496,8 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_custom<NewCustom: Module>(
         self,
         custom: NewCustom,
-    ) -> AppBuilder<BankT, ApiT, StorageT, NewCustom, WasmT, StakingT, DistrT> {
+    ) -> AppBuilder<BankT, ApiT, StorageT, NewCustom, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    {
         let AppBuilder {
             wasm,
             bank,
@@ -391,6 +506,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
             ..
         } = self;
 
@@ -403,6 +522,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
         }
     }
 
@@ -410,7 +533,8 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_staking<NewStaking: Staking>(
         self,
         staking: NewStaking,
-    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, NewStaking, DistrT> {
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, NewStaking, DistrT, GovT, IbcT, StargateT>
+    {
         let AppBuilder {
             wasm,
             api,
@@ -419,6 +543,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             block,
             bank,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
             ..
         } = self;
 
@@ -431,6 +559,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
         }
     }
 
@@ -438,7 +570,56 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     pub fn with_distribution<NewDistribution: Distribution>(
         self,
         distribution: NewDistribution,
-    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, NewDistribution> {
+    ) -> AppBuilder<
+        BankT,
+        ApiT,
+        StorageT,
+        CustomT,
+        WasmT,
+        StakingT,
+        NewDistribution,
+        GovT,
+        IbcT,
+        StargateT,
+    > {
+        let AppBuilder {
+            wasm,
+            api,
+            storage,
+            custom,
+            block,
+            staking,
+            bank,
+            gov,
+            ibc,
+            stargate,
+            gas,
+            ..
+        } = self;
+
+        AppBuilder {
+            api,
+            block,
+            storage,
+            bank,
+            wasm,
+            custom,
+            staking,
+            distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
+        }
+    }
+
+    /// Overwrites default gov interface
+    #[cfg(feature = "stargate")]
+    pub fn with_gov<NewGov: Gov>(
+        self,
+        gov: NewGov,
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, NewGov, IbcT, StargateT>
+    {
         let AppBuilder {
             wasm,
             api,
@@ -446,7 +627,11 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             block,
             staking,
+            distribution,
             bank,
+            ibc,
+            stargate,
+            gas,
             ..
         } = self;
 
@@ -459,6 +644,86 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom,
             staking,
             distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
+        }
+    }
+
+    /// Overwrites default ibc interface
+    #[cfg(feature = "stargate")]
+    pub fn with_ibc<NewIbc: Ibc>(
+        self,
+        ibc: NewIbc,
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, NewIbc, StargateT>
+    {
+        let AppBuilder {
+            wasm,
+            api,
+            storage,
+            custom,
+            block,
+            staking,
+            distribution,
+            bank,
+            gov,
+            stargate,
+            gas,
+            ..
+        } = self;
+
+        AppBuilder {
+            api,
+            block,
+            storage,
+            bank,
+            wasm,
+            custom,
+            staking,
+            distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
+        }
+    }
+
+    /// Overwrites default stargate interface
+    #[cfg(feature = "stargate")]
+    pub fn with_stargate<NewStargate: Stargate>(
+        self,
+        stargate: NewStargate,
+    ) -> AppBuilder<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, NewStargate>
+    {
+        let AppBuilder {
+            wasm,
+            api,
+            storage,
+            custom,
+            block,
+            staking,
+            distribution,
+            bank,
+            gov,
+            ibc,
+            gas,
+            ..
+        } = self;
+
+        AppBuilder {
+            api,
+            block,
+            storage,
+            bank,
+            wasm,
+            custom,
+            staking,
+            distribution,
+            gov,
+            ibc,
+            stargate,
+            gas,
         }
     }
 
@@ -468,13 +733,23 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
         self
     }
 
+    /// Meters the gas used by contracts with the given costs, so `SubMsg::gas_limit`
+    /// is enforced and [`App::gas_used`] reports the gas used by the last transaction.
+    ///
+    /// `AppResponse` has no gas field, adding one would break code building or matching it,
+    /// so the gas has to be read from the `App` after each execution.
+    pub fn with_gas_meter(mut self, config: GasConfig) -> Self {
+        self.gas = Some(config);
+        self
+    }
+
     /// Builds final `App`. At this point all components type have to be properly related to each
     /// other. If there are some generics related compilation error make sure, that all components
     /// are properly relating to each other.
     pub fn build<F>(
         self,
         init_fn: F,
-    ) -> App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+    ) -> App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
     where
         BankT: Bank,
         ApiT: Api,
@@ -483,7 +758,14 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
         WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
         StakingT: Staking,
         DistrT: Distribution,
-        F: FnOnce(&mut Router<BankT, CustomT, WasmT, StakingT, DistrT>, &dyn Api, &mut dyn Storage),
+        GovT: Gov,
+        IbcT: Ibc,
+        StargateT: Stargate,
+        F: FnOnce(
+            &mut Router<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>,
+            &dyn Api,
+            &mut dyn Storage,
+        ),
     {
         let router = Router {
             wasm: self.wasm,
@@ -491,6 +773,10 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
             custom: self.custom,
             staking: self.staking,
             distribution: self.distribution,
+            gov: self.gov,
+            ibc: self.ibc,
+            stargate: self.stargate,
+            gas: self.gas.map(GasMeter::new),
         };
 
         let mut app = App {
@@ -504,8 +790,8 @@ impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
     }
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
-    App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
 where
     WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
     BankT: Bank,
@@ -514,11 +800,14 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    GovT: Gov,
+    IbcT: Ibc,
+    StargateT: Stargate,
 {
     pub fn init_modules<F, T>(&mut self, init_fn: F) -> T
     where
         F: FnOnce(
-            &mut Router<BankT, CustomT, WasmT, StakingT, DistrT>,
+            &mut Router<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>,
             &dyn Api,
             &mut dyn Storage,
         ) -> T,
@@ -528,7 +817,11 @@ where
 
     pub fn read_module<F, T>(&self, query_fn: F) -> T
     where
-        F: FnOnce(&Router<BankT, CustomT, WasmT, StakingT, DistrT>, &dyn Api, &dyn Storage) -> T,
+        F: FnOnce(
+            &Router<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>,
+            &dyn Api,
+            &dyn Storage,
+        ) -> T,
     {
         query_fn(&self.router, &self.api, &self.storage)
     }
@@ -536,7 +829,7 @@ where
 
 // Helper functions to call some custom WasmKeeper logic.
 // They show how we can easily add such calls to other custom keepers (CustomT, StakingT, etc)
-impl<BankT, ApiT, StorageT, CustomT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, StakingT, DistrT, GovT, IbcT, StargateT>
     App<
         BankT,
         ApiT,
@@ -545,6 +838,9 @@ impl<BankT, ApiT, StorageT, CustomT, StakingT, DistrT>
         WasmKeeper<CustomT::ExecT, CustomT::QueryT>,
         StakingT,
         DistrT,
+        GovT,
+        IbcT,
+        StargateT,
     >
 where
     BankT: Bank,
@@ -553,8 +849,12 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    GovT: Gov,
+    IbcT: Ibc,
+    StargateT: Stargate,
     CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
+    CustomT::SudoT: DeserializeOwned,
 {
     /// This registers contract code (like uploading wasm bytecode on a chain),
     /// so it can later be used to instantiate a contract.
@@ -571,13 +871,64 @@ where
     pub fn dump_wasm_raw(&self, address: &Addr) -> Vec<Record> {
         self.read_module(|router, _, storage| router.wasm.dump_wasm_raw(storage, address))
     }
+
+    /// Address a contract of the code gets when `creator` instantiates it with `salt`
+    pub fn predictable_contract_address(
+        &self,
+        code_id: u64,
+        creator: &Addr,
+        salt: &[u8],
+    ) -> AnyResult<Addr> {
+        self.read_module(|router, api, _| {
+            router
+                .wasm
+                .predictable_address(api, code_id as usize, creator, salt)
+        })
+    }
+
+    /// Instantiates a contract like instantiate2 of wasmd does, at the address
+    /// `predictable_contract_address` returns for the same code, sender and salt
+    #[allow(clippy::too_many_arguments)]
+    pub fn instantiate2_contract<T: Serialize, U: Into<String>>(
+        &mut self,
+        code_id: u64,
+        sender: Addr,
+        init_msg: &T,
+        send_funds: &[Coin],
+        label: U,
+        admin: Option<String>,
+        salt: impl Into<Binary>,
+    ) -> AnyResult<Addr> {
+        let msg = WasmMsg::Instantiate {
+            admin,
+            code_id,
+            msg: to_binary(init_msg)?,
+            funds: send_funds.to_vec(),
+            label: label.into(),
+        };
+        let salt = salt.into();
+        let Self {
+            block,
+            router,
+            api,
+            storage,
+        } = self;
+        let res = transactional(&mut *storage, |write_cache, _| {
+            router
+                .wasm
+                .instantiate2(&*api, write_cache, router, block, sender, msg, salt)
+        })?;
+        let data = parse_instantiate_response_data(res.data.unwrap_or_default().as_slice())?;
+        Ok(Addr::unchecked(data.contract_address))
+    }
 }
 
-impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
-    App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
 where
     CustomT::ExecT: std::fmt::Debug + PartialEq + Clone + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
+    CustomT::SudoT: DeserializeOwned,
     WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
     BankT: Bank,
     ApiT: Api,
@@ -585,6 +936,9 @@ where
     CustomT: Module,
     StakingT: Staking,
     DistrT: Distribution,
+    GovT: Gov,
+    IbcT: Ibc,
+    StargateT: Stargate,
 {
     pub fn set_block(&mut self, block: BlockInfo) {
         self.block = block;
@@ -600,12 +954,29 @@ where
         self.block.clone()
     }
 
+    /// Gas used by the last executed transaction, 0 unless the `App` meters gas
+    pub fn gas_used(&self) -> u64 {
+        self.router.gas_used()
+    }
+
     /// Simple helper so we get access to all the QuerierWrapper helpers,
     /// eg. wrap().query_wasm_smart, query_all_balances, ...
     pub fn wrap(&self) -> QuerierWrapper<CustomT::QueryT> {
         QuerierWrapper::new(self)
     }
 
+    /// Queries the distribution module, which contracts cannot do as there is no
+    /// `QueryRequest` variant for it
+    pub fn query_distribution<T: DeserializeOwned>(
+        &self,
+        request: DistributionQuery,
+    ) -> AnyResult<T> {
+        let res = self
+            .router
+            .query_distribution(&self.api, &self.storage, &self.block, request)?;
+        Ok(from_slice(&res)?)
+    }
+
     /// Runs multiple CosmosMsg in one atomic operation.
     /// This will create a cache before the execution, so no state changes are persisted if any of them
     /// return an error. But all writes are persisted on success.
@@ -625,6 +996,7 @@ where
             storage,
         } = self;
 
+        router.reset_gas();
         transactional(&mut *storage, |write_cache, _| {
             msgs.into_iter()
                 .map(|msg| router.execute(&*api, write_cache, block, sender.clone(), msg))
@@ -649,6 +1021,7 @@ where
             storage,
         } = self;
 
+        router.reset_gas();
         transactional(&mut *storage, |write_cache, _| {
             router
                 .wasm
@@ -670,13 +1043,14 @@ where
             storage,
         } = self;
 
+        router.reset_gas();
         transactional(&mut *storage, |write_cache, _| {
             router.sudo(&*api, write_cache, block, msg)
         })
     }
 }
 
-pub struct Router<Bank, Custom, Wasm, Staking, Distr> {
+pub struct Router<Bank, Custom, Wasm, Staking, Distr, Gov, Ibc, Stargate> {
     // this can remain crate-only as all special functions are wired up to app currently
     // we need to figure out another format for wasm, as some like sudo need to be called after init
     pub(crate) wasm: Wasm,
@@ -685,17 +1059,26 @@ pub struct Router<Bank, Custom, Wasm, Staking, Distr> {
     pub custom: Custom,
     pub staking: Staking,
     pub distribution: Distr,
+    pub gov: Gov,
+    pub ibc: Ibc,
+    pub stargate: Stargate,
+    pub(crate) gas: Option<GasMeter>,
 }
 
-impl<BankT, CustomT, WasmT, StakingT, DistrT> Router<BankT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
+    Router<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
 where
     CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
+    CustomT::SudoT: DeserializeOwned,
     CustomT: Module,
     WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
     BankT: Bank,
     StakingT: Staking,
     DistrT: Distribution,
+    GovT: Gov,
+    IbcT: Ibc,
+    StargateT: Stargate,
 {
     pub fn querier<'a>(
         &'a self,
@@ -710,15 +1093,53 @@ where
             block_info,
         }
     }
+
+    pub fn query_distribution(
+        &self,
+        api: &dyn Api,
+        storage: &dyn Storage,
+        block: &BlockInfo,
+        request: DistributionQuery,
+    ) -> AnyResult<Binary> {
+        let querier = self.querier(api, storage, block);
+        self.distribution
+            .query(api, storage, &querier, block, request)
+    }
+
+    /// Gas used by the current transaction, 0 if gas is not metered
+    pub fn gas_used(&self) -> u64 {
+        self.gas.as_ref().map(GasMeter::used).unwrap_or_default()
+    }
+
+    fn reset_gas(&self) {
+        if let Some(gas) = &self.gas {
+            gas.reset();
+        }
+    }
 }
 
 /// We use it to allow calling into modules from another module in sudo mode.
 /// Things like gov proposals belong here.
 pub enum SudoMsg {
     Bank(BankSudo),
-    Custom(Empty),
+    /// Json encoded sudo message of the custom module, see `SudoMsg::custom`
+    Custom(Binary),
     Staking(StakingSudo),
+    #[cfg(feature = "stargate")]
+    Gov(GovSudo),
+    #[cfg(feature = "stargate")]
+    Ibc(IbcSudo),
     Wasm(WasmSudo),
+    /// Calls the IBC entry points of a contract
+    #[cfg(feature = "stargate")]
+    WasmIbc(WasmIbcSudo),
+}
+
+impl SudoMsg {
+    /// Sudo message for the custom module
+    pub fn custom(msg: &impl Serialize) -> StdResult<Self> {
+        Ok(SudoMsg::Custom(to_binary(msg)?))
+    }
 }
 
 impl From<WasmSudo> for SudoMsg {
@@ -739,6 +1160,27 @@ impl From<StakingSudo> for SudoMsg {
     }
 }
 
+#[cfg(feature = "stargate")]
+impl From<GovSudo> for SudoMsg {
+    fn from(gov: GovSudo) -> Self {
+        SudoMsg::Gov(gov)
+    }
+}
+
+#[cfg(feature = "stargate")]
+impl From<IbcSudo> for SudoMsg {
+    fn from(ibc: IbcSudo) -> Self {
+        SudoMsg::Ibc(ibc)
+    }
+}
+
+#[cfg(feature = "stargate")]
+impl From<WasmIbcSudo> for SudoMsg {
+    fn from(wasm_ibc: WasmIbcSudo) -> Self {
+        SudoMsg::WasmIbc(wasm_ibc)
+    }
+}
+
 pub trait CosmosRouter {
     type ExecC;
     type QueryC: CustomQuery;
@@ -767,18 +1209,27 @@ pub trait CosmosRouter {
         block: &BlockInfo,
         msg: SudoMsg,
     ) -> AnyResult<AppResponse>;
+
+    /// Meter charging the gas used by contracts, if gas is metered
+    fn gas_meter(&self) -> Option<&GasMeter> {
+        None
+    }
 }
 
-impl<BankT, CustomT, WasmT, StakingT, DistrT> CosmosRouter
-    for Router<BankT, CustomT, WasmT, StakingT, DistrT>
+impl<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT> CosmosRouter
+    for Router<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>
 where
     CustomT::ExecT: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
     CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
+    CustomT::SudoT: DeserializeOwned,
     CustomT: Module,
     WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
     BankT: Bank,
     StakingT: Staking,
     DistrT: Distribution,
+    GovT: Gov,
+    IbcT: Ibc,
+    StargateT: Stargate,
 {
     type ExecC = CustomT::ExecT;
     type QueryC = CustomT::QueryT;
@@ -799,6 +1250,16 @@ where
             CosmosMsg::Distribution(msg) => self
                 .distribution
                 .execute(api, storage, self, block, sender, msg),
+            #[cfg(feature = "stargate")]
+            CosmosMsg::Gov(msg) => self.gov.execute(api, storage, self, block, sender, msg),
+            #[cfg(feature = "stargate")]
+            CosmosMsg::Ibc(msg) => self.ibc.execute(api, storage, self, block, sender, msg),
+            #[cfg(feature = "stargate")]
+            CosmosMsg::Stargate { type_url, value } => {
+                let msg = StargateMsg { type_url, value };
+                self.stargate
+                    .execute(api, storage, self, block, sender, msg)
+            }
             _ => bail!("Cannot execute {:?}", msg),
         }
     }
@@ -819,7 +1280,14 @@ where
             QueryRequest::Bank(req) => self.bank.query(api, storage, &querier, block, req),
             QueryRequest::Custom(req) => self.custom.query(api, storage, &querier, block, req),
             QueryRequest::Staking(req) => self.staking.query(api, storage, &querier, block, req),
-            _ => unimplemented!(),
+            #[cfg(feature = "stargate")]
+            QueryRequest::Ibc(req) => self.ibc.query(api, storage, &querier, block, req),
+            #[cfg(feature = "stargate")]
+            QueryRequest::Stargate { path, data } => {
+                let req = StargateQuery { path, data };
+                self.stargate.query(api, storage, &querier, block, req)
+            }
+            request => bail!(Error::unsupported_query(&request)),
         }
     }
 
@@ -837,9 +1305,25 @@ where
             }
             SudoMsg::Bank(msg) => self.bank.sudo(api, storage, self, block, msg),
             SudoMsg::Staking(msg) => self.staking.sudo(api, storage, self, block, msg),
-            SudoMsg::Custom(_) => unimplemented!(),
+            #[cfg(feature = "stargate")]
+            SudoMsg::Gov(msg) => self.gov.sudo(api, storage, self, block, msg),
+            #[cfg(feature = "stargate")]
+            SudoMsg::Ibc(msg) => self.ibc.sudo(api, storage, self, block, msg),
+            #[cfg(feature = "stargate")]
+            SudoMsg::WasmIbc(msg) => {
+                self.wasm
+                    .ibc(api, msg.contract_addr, storage, self, block, msg.msg)
+            }
+            SudoMsg::Custom(msg) => {
+                let msg = from_slice(&msg).map_err(Error::invalid_custom_sudo)?;
+                self.custom.sudo(api, storage, self, block, msg)
+            }
         }
     }
+
+    fn gas_meter(&self) -> Option<&GasMeter> {
+        self.gas.as_ref()
+    }
 }
 
 pub struct MockRouter<ExecC, QueryC>(PhantomData<(ExecC, QueryC)>);
@@ -965,6 +1449,7 @@ mod test {
     where
         CustomT::ExecT: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned + 'static,
         CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
+        CustomT::SudoT: DeserializeOwned,
         WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
         BankT: Bank,
         ApiT: Api,
@@ -1032,6 +1517,34 @@ mod test {
         assert_eq!(vec![coin(15, "btc"), coin(70, "eth")], rich);
     }
 
+    #[test]
+    fn burn_tokens_reduces_supply() {
+        let owner = Addr::unchecked("owner");
+        let mut app = App::new(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(100, "eth"))
+                .unwrap();
+        });
+        let supply =
+            |app: &App| app.read_module(|router, _, storage| router.bank.supply(storage, "eth"));
+        assert_eq!(supply(&app).unwrap(), coin(100, "eth"));
+
+        let msg = BankMsg::Burn {
+            amount: coins(30, "eth"),
+        };
+        app.execute(owner.clone(), msg.into()).unwrap();
+        assert_eq!(supply(&app).unwrap(), coin(70, "eth"));
+
+        let msg = BankSudo::Burn {
+            from_address: owner.to_string(),
+            amount: coins(20, "eth"),
+        };
+        app.sudo(msg.into()).unwrap();
+        assert_eq!(supply(&app).unwrap(), coin(50, "eth"));
+        assert_eq!(get_balance(&app, &owner), coins(50, "eth"));
+    }
+
     #[test]
     fn simple_contract() {
         // set personal balance
@@ -1507,6 +2020,25 @@ mod test {
             let little_win = app.wrap().query_balance(&second, denom).unwrap();
             assert_eq!(little_win, bonus);
         }
+
+        #[test]
+        fn routes_sudo_messages() {
+            let mut app = BasicAppBuilder::<CustomMsg, Empty>::new_custom()
+                .with_custom(CustomHandler {})
+                .build(|_, _, _| {});
+
+            let err = app.sudo(SudoMsg::custom(&Empty {}).unwrap()).unwrap_err();
+            assert_eq!(err.to_string(), "sudo not implemented for CustomHandler");
+
+            // the message has to deserialize into the sudo type of the module
+            let err = app
+                .sudo(SudoMsg::Custom(Binary::from(b"[]".to_vec())))
+                .unwrap_err();
+            assert!(matches!(
+                err.downcast().unwrap(),
+                Error::InvalidCustomSudo(_)
+            ));
+        }
     }
 
     #[test]
@@ -1599,8 +2131,8 @@ mod test {
         // TODO: check error?
     }
 
-    fn query_router<BankT, CustomT, WasmT, StakingT, DistrT>(
-        router: &Router<BankT, CustomT, WasmT, StakingT, DistrT>,
+    fn query_router<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>(
+        router: &Router<BankT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>,
         api: &dyn Api,
         storage: &dyn Storage,
         rcpt: &Addr,
@@ -1613,6 +2145,9 @@ mod test {
         CustomT: Module,
         StakingT: Staking,
         DistrT: Distribution,
+        GovT: Gov,
+        IbcT: Ibc,
+        StargateT: Stargate,
     {
         let query = BankQuery::AllBalances {
             address: rcpt.into(),
@@ -1627,8 +2162,8 @@ mod test {
         val.amount
     }
 
-    fn query_app<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>(
-        app: &App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT>,
+    fn query_app<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>(
+        app: &App<BankT, ApiT, StorageT, CustomT, WasmT, StakingT, DistrT, GovT, IbcT, StargateT>,
         rcpt: &Addr,
     ) -> Vec<Coin>
     where
@@ -1636,12 +2171,16 @@ mod test {
             std::fmt::Debug + PartialEq + Clone + JsonSchema + DeserializeOwned + 'static,
         CustomT::QueryT: CustomQuery + DeserializeOwned + 'static,
         WasmT: Wasm<CustomT::ExecT, CustomT::QueryT>,
+        CustomT::SudoT: DeserializeOwned,
         BankT: Bank,
         ApiT: Api,
         StorageT: Storage,
         CustomT: Module,
         StakingT: Staking,
         DistrT: Distribution,
+        GovT: Gov,
+        IbcT: Ibc,
+        StargateT: Stargate,
     {
         let query = BankQuery::AllBalances {
             address: rcpt.into(),
@@ -1828,6 +2367,143 @@ mod test {
         assert_eq!(state.beneficiary, random);
     }
 
+    mod gas {
+        use super::*;
+
+        use cosmwasm_std::SubMsgResult;
+
+        fn setup(config: GasConfig) -> (BasicApp<CustomMsg>, Addr, Addr) {
+            let mut app = BasicAppBuilder::<CustomMsg, Empty>::new_custom()
+                .with_gas_meter(config)
+                .build(|_, _, _| {});
+            let code_id = app.store_code(reflect::contract());
+            let owner = Addr::unchecked("owner");
+            let caller = app
+                .instantiate_contract(code_id, owner.clone(), &EmptyMsg {}, &[], "Caller", None)
+                .unwrap();
+            let callee = app
+                .instantiate_contract(code_id, owner, &EmptyMsg {}, &[], "Callee", None)
+                .unwrap();
+            (app, caller, callee)
+        }
+
+        // makes the caller execute the callee and reply with id 1
+        fn call(callee: &Addr, gas_limit: Option<u64>) -> reflect::Message {
+            let msg = WasmMsg::Execute {
+                contract_addr: callee.to_string(),
+                msg: to_binary(&reflect::Message::default()).unwrap(),
+                funds: vec![],
+            };
+            let mut msg = SubMsg::reply_always(msg, 1);
+            msg.gas_limit = gas_limit;
+            reflect::Message {
+                messages: vec![msg],
+            }
+        }
+
+        fn count(app: &BasicApp<CustomMsg>, contract: &Addr) -> u32 {
+            let res: payout::CountResponse = app
+                .wrap()
+                .query_wasm_smart(contract, &reflect::QueryMsg::Count {})
+                .unwrap();
+            res.count
+        }
+
+        fn reply(app: &BasicApp<CustomMsg>, contract: &Addr) -> Reply {
+            app.wrap()
+                .query_wasm_smart(contract, &reflect::QueryMsg::Reply { id: 1 })
+                .unwrap()
+        }
+
+        #[test]
+        fn reports_gas_used() {
+            let (mut app, caller, callee) = setup(GasConfig::default());
+            let owner = Addr::unchecked("owner");
+
+            app.execute_contract(
+                owner.clone(),
+                callee.clone(),
+                &reflect::Message::default(),
+                &[],
+            )
+            .unwrap();
+            let gas_used = app.gas_used();
+            assert!(gas_used > 0);
+            // dispatching a submessage costs extra
+            app.execute_contract(owner.clone(), caller, &call(&callee, None), &[])
+                .unwrap();
+            assert!(app.gas_used() > gas_used + GasConfig::default().submsg_cost);
+
+            // without a meter no gas is used
+            let mut app = custom_app::<CustomMsg, Empty, _>(|_, _, _| {});
+            let code_id = app.store_code(reflect::contract());
+            let contract = app
+                .instantiate_contract(code_id, owner.clone(), &EmptyMsg {}, &[], "Reflect", None)
+                .unwrap();
+            app.execute_contract(owner, contract, &reflect::Message::default(), &[])
+                .unwrap();
+            assert_eq!(app.gas_used(), 0);
+        }
+
+        #[test]
+        fn submessage_out_of_gas_replies_error() {
+            let (mut app, caller, callee) = setup(GasConfig::default());
+            let owner = Addr::unchecked("owner");
+
+            app.execute_contract(
+                owner.clone(),
+                caller.clone(),
+                &call(&callee, Some(100)),
+                &[],
+            )
+            .unwrap();
+            // the callee was reverted and the caller got the error
+            assert_eq!(count(&app, &callee), 0);
+            assert_eq!(count(&app, &caller), 1);
+            match reply(&app, &caller).result {
+                SubMsgResult::Err(err) => assert!(err.starts_with("Out of gas"), "{}", err),
+                res => panic!("Unexpected reply result {:?}", res),
+            }
+            // the whole limit was charged
+            assert!(app.gas_used() > 100 + GasConfig::default().submsg_cost);
+
+            // with enough gas the callee runs
+            app.execute_contract(owner, caller.clone(), &call(&callee, Some(100_000)), &[])
+                .unwrap();
+            assert_eq!(count(&app, &callee), 1);
+            assert!(reply(&app, &caller).result.is_ok());
+        }
+
+        #[test]
+        fn transaction_out_of_gas_fails() {
+            let config = GasConfig {
+                tx_gas_limit: Some(1500),
+                ..GasConfig::default()
+            };
+            let (mut app, caller, callee) = setup(config);
+
+            // the callee fits in its limit, but the reply does not fit in the transaction
+            let err = app
+                .execute_contract(
+                    Addr::unchecked("owner"),
+                    caller.clone(),
+                    &call(&callee, Some(100_000)),
+                    &[],
+                )
+                .unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<Error>(),
+                    Some(Error::OutOfGas { limit: 1500, .. })
+                ),
+                "{}",
+                err
+            );
+            assert_eq!(count(&app, &callee), 0);
+            assert_eq!(count(&app, &caller), 0);
+        }
+    }
+
     mod reply_data_overwrite {
         use super::*;
 
@@ -1,10 +1,11 @@
 use anyhow::{bail, Result as AnyResult};
 use itertools::Itertools;
 use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{
     coin, to_binary, Addr, AllBalanceResponse, Api, BalanceResponse, BankMsg, BankQuery, Binary,
-    BlockInfo, Coin, Event, Querier, Storage,
+    BlockInfo, Coin, Event, Querier, StdResult, Storage, Uint128,
 };
 use cosmwasm_storage::{prefixed, prefixed_read};
 use cw_storage_plus::Map;
@@ -16,15 +17,89 @@ use crate::module::Module;
 
 const BALANCES: Map<&Addr, NativeBalance> = Map::new("balances");
 
+/// Wire format of `SupplyResponse`, which is `#[non_exhaustive]` and has no
+/// constructor before cosmwasm-std 1.2
+#[cfg(feature = "cosmwasm_1_1")]
+#[derive(Serialize)]
+struct SupplyQueryResponse {
+    amount: Coin,
+}
+const SUPPLY: Map<&str, Uint128> = Map::new("supply");
+const DENOM_METADATA: Map<&str, DenomMetadata> = Map::new("denom_metadata");
+
 pub const NAMESPACE_BANK: &[u8] = b"bank";
 
-// WIP
 #[derive(Clone, std::fmt::Debug, PartialEq, JsonSchema)]
 pub enum BankSudo {
+    /// Creates new tokens, increasing their supply
     Mint {
         to_address: String,
         amount: Vec<Coin>,
     },
+    /// Destroys tokens of any account, decreasing their supply
+    Burn {
+        from_address: String,
+        amount: Vec<Coin>,
+    },
+    /// Replaces all balances of the account, the supply follows the difference
+    SetBalance { address: String, amount: Vec<Coin> },
+    /// Registers the metadata of a denom, replacing what was registered before
+    SetDenomMetadata { metadata: DenomMetadata },
+}
+
+/// Metadata of a native token, as `x/bank` of the cosmos-sdk keeps it
+#[derive(Serialize, Deserialize, Clone, std::fmt::Debug, Default, PartialEq, JsonSchema)]
+pub struct DenomMetadata {
+    pub description: String,
+    /// Units the token can be displayed in, the first one being `base` with exponent 0
+    pub denom_units: Vec<DenomUnit>,
+    pub base: String,
+    /// One of `denom_units`, used to display the token to users
+    pub display: String,
+    pub name: String,
+    pub symbol: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, std::fmt::Debug, Default, PartialEq, JsonSchema)]
+pub struct DenomUnit {
+    pub denom: String,
+    /// Power of 10 one unit is worth of the base denom
+    pub exponent: u32,
+    pub aliases: Vec<String>,
+}
+
+impl DenomMetadata {
+    // follows Metadata.Validate of the cosmos-sdk, without checking the denom format
+    fn validate(&self) -> AnyResult<()> {
+        if self.base.is_empty() {
+            bail!("Denom metadata must have a base denom");
+        }
+        match self.denom_units.first() {
+            Some(unit) if unit.denom == self.base && unit.exponent == 0 => {}
+            _ => bail!(
+                "The first denom unit must be the base denom {} with exponent 0",
+                self.base
+            ),
+        }
+        if !self
+            .denom_units
+            .windows(2)
+            .all(|units| units[0].exponent < units[1].exponent)
+        {
+            bail!("Denom units must be sorted by increasing exponent");
+        }
+        if !self
+            .denom_units
+            .iter()
+            .any(|unit| unit.denom == self.display)
+        {
+            bail!(
+                "Display denom {} is not one of the denom units",
+                self.display
+            );
+        }
+        Ok(())
+    }
 }
 
 pub trait Bank: Module<ExecT = BankMsg, QueryT = BankQuery, SudoT = BankSudo> {}
@@ -48,6 +123,23 @@ impl BankKeeper {
         self.set_balance(&mut bank_storage, account, amount)
     }
 
+    /// Total amount of the denom held by all accounts
+    pub fn supply(&self, storage: &dyn Storage, denom: &str) -> AnyResult<Coin> {
+        let bank_storage = prefixed_read(storage, NAMESPACE_BANK);
+        let amount = SUPPLY.may_load(&bank_storage, denom)?.unwrap_or_default();
+        Ok(coin(amount.u128(), denom))
+    }
+
+    pub fn denom_metadata(
+        &self,
+        storage: &dyn Storage,
+        denom: &str,
+    ) -> AnyResult<Option<DenomMetadata>> {
+        let bank_storage = prefixed_read(storage, NAMESPACE_BANK);
+        Ok(DENOM_METADATA.may_load(&bank_storage, denom)?)
+    }
+
+    // every balance change goes through here, so this keeps the supply in line
     fn set_balance(
         &self,
         bank_storage: &mut dyn Storage,
@@ -56,6 +148,18 @@ impl BankKeeper {
     ) -> AnyResult<()> {
         let mut balance = NativeBalance(amount);
         balance.normalize();
+
+        for old in self.get_balance(bank_storage, account)? {
+            SUPPLY.update(bank_storage, &old.denom, |supply| -> StdResult<_> {
+                Ok(supply.unwrap_or_default().checked_sub(old.amount)?)
+            })?;
+        }
+        for new in &balance.0 {
+            SUPPLY.update(bank_storage, &new.denom, |supply| -> StdResult<_> {
+                Ok(supply.unwrap_or_default().checked_add(new.amount)?)
+            })?;
+        }
+
         BALANCES
             .save(bank_storage, account, &balance)
             .map_err(Into::into)
@@ -176,6 +280,24 @@ impl Module for BankKeeper {
                 self.mint(&mut bank_storage, to_address, amount)?;
                 Ok(AppResponse::default())
             }
+            BankSudo::Burn {
+                from_address,
+                amount,
+            } => {
+                let from_address = api.addr_validate(&from_address)?;
+                self.burn(&mut bank_storage, from_address, amount)?;
+                Ok(AppResponse::default())
+            }
+            BankSudo::SetBalance { address, amount } => {
+                let address = api.addr_validate(&address)?;
+                self.set_balance(&mut bank_storage, &address, amount)?;
+                Ok(AppResponse::default())
+            }
+            BankSudo::SetDenomMetadata { metadata } => {
+                metadata.validate()?;
+                DENOM_METADATA.save(&mut bank_storage, &metadata.base, &metadata)?;
+                Ok(AppResponse::default())
+            }
         }
     }
 
@@ -205,6 +327,13 @@ impl Module for BankKeeper {
                 let res = BalanceResponse { amount };
                 Ok(to_binary(&res)?)
             }
+            #[cfg(feature = "cosmwasm_1_1")]
+            BankQuery::Supply { denom } => {
+                let res = SupplyQueryResponse {
+                    amount: self.supply(storage, &denom)?,
+                };
+                Ok(to_binary(&res)?)
+            }
             q => bail!("Unsupported bank query: {:?}", q),
         }
     }
@@ -470,4 +599,171 @@ mod test {
         bank.sudo(&api, &mut store, &router, &block, msg)
             .unwrap_err();
     }
+
+    #[test]
+    #[cfg(feature = "cosmwasm_1_1")]
+    fn query_supply() {
+        use cosmwasm_std::SupplyResponse;
+
+        let api = MockApi::default();
+        let mut store = MockStorage::new();
+        let block = mock_env().block;
+        let querier: MockQuerier<Empty> = MockQuerier::new(&[]);
+
+        let bank = BankKeeper::new();
+        bank.init_balance(&mut store, &Addr::unchecked("owner"), coins(100, "eth"))
+            .unwrap();
+        bank.init_balance(&mut store, &Addr::unchecked("rcpt"), coins(50, "eth"))
+            .unwrap();
+
+        let req = BankQuery::Supply {
+            denom: "eth".to_string(),
+        };
+        let raw = bank.query(&api, &store, &querier, &block, req).unwrap();
+        let res: SupplyResponse = from_slice(&raw).unwrap();
+        assert_eq!(res.amount, coin(150, "eth"));
+
+        let req = BankQuery::Supply {
+            denom: "atom".to_string(),
+        };
+        let raw = bank.query(&api, &store, &querier, &block, req).unwrap();
+        let res: SupplyResponse = from_slice(&raw).unwrap();
+        assert_eq!(res.amount, coin(0, "atom"));
+    }
+
+    #[test]
+    fn tracks_supply() {
+        let api = MockApi::default();
+        let mut store = MockStorage::new();
+        let block = mock_env().block;
+        let router = MockRouter::default();
+
+        let owner = Addr::unchecked("owner");
+        let rcpt = Addr::unchecked("recipient");
+        let bank = BankKeeper::new();
+        let supply = |store: &MockStorage, denom| bank.supply(store, denom).unwrap();
+
+        bank.init_balance(&mut store, &owner, vec![coin(100, "eth"), coin(20, "btc")])
+            .unwrap();
+        bank.init_balance(&mut store, &rcpt, coins(50, "eth"))
+            .unwrap();
+        assert_eq!(supply(&store, "eth"), coin(150, "eth"));
+        assert_eq!(supply(&store, "btc"), coin(20, "btc"));
+        assert_eq!(supply(&store, "atom"), coin(0, "atom"));
+
+        // sending keeps the supply
+        let msg = BankMsg::Send {
+            to_address: rcpt.to_string(),
+            amount: coins(30, "eth"),
+        };
+        bank.execute(&api, &mut store, &router, &block, owner.clone(), msg)
+            .unwrap();
+        assert_eq!(supply(&store, "eth"), coin(150, "eth"));
+
+        let msg = BankMsg::Burn {
+            amount: coins(40, "eth"),
+        };
+        bank.execute(&api, &mut store, &router, &block, rcpt.clone(), msg)
+            .unwrap();
+        assert_eq!(supply(&store, "eth"), coin(110, "eth"));
+
+        let msg = BankSudo::Mint {
+            to_address: rcpt.to_string(),
+            amount: coins(5, "atom"),
+        };
+        bank.sudo(&api, &mut store, &router, &block, msg).unwrap();
+        assert_eq!(supply(&store, "atom"), coin(5, "atom"));
+
+        let msg = BankSudo::Burn {
+            from_address: owner.to_string(),
+            amount: coins(15, "btc"),
+        };
+        bank.sudo(&api, &mut store, &router, &block, msg).unwrap();
+        assert_eq!(supply(&store, "btc"), coin(5, "btc"));
+        assert_eq!(
+            query_balance(&bank, &api, &store, &owner),
+            vec![coin(5, "btc"), coin(70, "eth")]
+        );
+
+        // cannot burn more than the account holds
+        let msg = BankSudo::Burn {
+            from_address: rcpt.to_string(),
+            amount: coins(6, "atom"),
+        };
+        bank.sudo(&api, &mut store, &router, &block, msg)
+            .unwrap_err();
+        assert_eq!(supply(&store, "atom"), coin(5, "atom"));
+
+        // setting the balance replaces all denoms
+        let msg = BankSudo::SetBalance {
+            address: owner.to_string(),
+            amount: vec![coin(10, "eth"), coin(0, "btc"), coin(7, "atom")],
+        };
+        bank.sudo(&api, &mut store, &router, &block, msg).unwrap();
+        assert_eq!(
+            query_balance(&bank, &api, &store, &owner),
+            vec![coin(7, "atom"), coin(10, "eth")]
+        );
+        assert_eq!(supply(&store, "eth"), coin(50, "eth"));
+        assert_eq!(supply(&store, "btc"), coin(0, "btc"));
+        assert_eq!(supply(&store, "atom"), coin(12, "atom"));
+    }
+
+    #[test]
+    fn denom_metadata() {
+        let api = MockApi::default();
+        let mut store = MockStorage::new();
+        let block = mock_env().block;
+        let router = MockRouter::default();
+        let bank = BankKeeper::new();
+
+        let unit = |denom: &str, exponent| DenomUnit {
+            denom: denom.to_owned(),
+            exponent,
+            aliases: vec![],
+        };
+        let metadata = DenomMetadata {
+            description: "The native staking token".to_owned(),
+            denom_units: vec![unit("udarc", 0), unit("mdarc", 3), unit("darc", 6)],
+            base: "udarc".to_owned(),
+            display: "darc".to_owned(),
+            name: "Darc".to_owned(),
+            symbol: "DARC".to_owned(),
+        };
+        assert_eq!(bank.denom_metadata(&store, "udarc").unwrap(), None);
+
+        let msg = BankSudo::SetDenomMetadata {
+            metadata: metadata.clone(),
+        };
+        bank.sudo(&api, &mut store, &router, &block, msg).unwrap();
+        assert_eq!(
+            bank.denom_metadata(&store, "udarc").unwrap(),
+            Some(metadata.clone())
+        );
+
+        // invalid metadata is rejected
+        let invalid = [
+            DenomMetadata {
+                base: "".to_owned(),
+                ..metadata.clone()
+            },
+            DenomMetadata {
+                denom_units: vec![unit("darc", 6), unit("udarc", 0)],
+                ..metadata.clone()
+            },
+            DenomMetadata {
+                denom_units: vec![unit("udarc", 0), unit("darc", 6), unit("mdarc", 3)],
+                ..metadata.clone()
+            },
+            DenomMetadata {
+                display: "kdarc".to_owned(),
+                ..metadata
+            },
+        ];
+        for metadata in invalid {
+            let msg = BankSudo::SetDenomMetadata { metadata };
+            bank.sudo(&api, &mut store, &router, &block, msg)
+                .unwrap_err();
+        }
+    }
 }
@@ -8,6 +8,11 @@ use cosmwasm_std::{
     from_slice, Binary, CosmosMsg, CustomQuery, Deps, DepsMut, Empty, Env, MessageInfo,
     QuerierWrapper, Reply, Response, SubMsg,
 };
+#[cfg(feature = "stargate")]
+use cosmwasm_std::{
+    IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse,
+};
 
 use anyhow::{anyhow, bail, Result as AnyResult};
 
@@ -40,6 +45,66 @@ where
     fn reply(&self, deps: DepsMut<Q>, env: Env, msg: Reply) -> AnyResult<Response<T>>;
 
     fn migrate(&self, deps: DepsMut<Q>, env: Env, msg: Vec<u8>) -> AnyResult<Response<T>>;
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_open(
+        &self,
+        _deps: DepsMut<Q>,
+        _env: Env,
+        _msg: IbcChannelOpenMsg,
+    ) -> AnyResult<()> {
+        bail!("ibc not implemented for contract")
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_connect(
+        &self,
+        _deps: DepsMut<Q>,
+        _env: Env,
+        _msg: IbcChannelConnectMsg,
+    ) -> AnyResult<IbcBasicResponse<T>> {
+        bail!("ibc not implemented for contract")
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_close(
+        &self,
+        _deps: DepsMut<Q>,
+        _env: Env,
+        _msg: IbcChannelCloseMsg,
+    ) -> AnyResult<IbcBasicResponse<T>> {
+        bail!("ibc not implemented for contract")
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_receive(
+        &self,
+        _deps: DepsMut<Q>,
+        _env: Env,
+        _msg: IbcPacketReceiveMsg,
+    ) -> AnyResult<IbcReceiveResponse<T>> {
+        bail!("ibc not implemented for contract")
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_ack(
+        &self,
+        _deps: DepsMut<Q>,
+        _env: Env,
+        _msg: IbcPacketAckMsg,
+    ) -> AnyResult<IbcBasicResponse<T>> {
+        bail!("ibc not implemented for contract")
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_timeout(
+        &self,
+        _deps: DepsMut<Q>,
+        _env: Env,
+        _msg: IbcPacketTimeoutMsg,
+    ) -> AnyResult<IbcBasicResponse<T>> {
+        bail!("ibc not implemented for contract")
+    }
 }
 
 type ContractFn<T, C, E, Q> =
@@ -54,6 +119,37 @@ type PermissionedClosure<T, C, E, Q> = Box<dyn Fn(DepsMut<Q>, Env, T) -> Result<
 type ReplyClosure<C, E, Q> = Box<dyn Fn(DepsMut<Q>, Env, Reply) -> Result<Response<C>, E>>;
 type QueryClosure<T, E, Q> = Box<dyn Fn(Deps<Q>, Env, T) -> Result<Binary, E>>;
 
+#[cfg(feature = "stargate")]
+type IbcFn<T, R, E, Q> = fn(deps: DepsMut<Q>, env: Env, msg: T) -> Result<R, E>;
+#[cfg(feature = "stargate")]
+type IbcClosure<T, R, Q> = Box<dyn Fn(DepsMut<Q>, Env, T) -> AnyResult<R>>;
+
+#[cfg(feature = "stargate")]
+/// The IBC entry points of a contract, errors are already converted as they are all optional
+struct IbcClosures<C, Q>
+where
+    C: Clone + fmt::Debug + PartialEq + JsonSchema,
+    Q: CustomQuery,
+{
+    channel_open: IbcClosure<IbcChannelOpenMsg, (), Q>,
+    channel_connect: IbcClosure<IbcChannelConnectMsg, IbcBasicResponse<C>, Q>,
+    channel_close: IbcClosure<IbcChannelCloseMsg, IbcBasicResponse<C>, Q>,
+    packet_receive: IbcClosure<IbcPacketReceiveMsg, IbcReceiveResponse<C>, Q>,
+    packet_ack: IbcClosure<IbcPacketAckMsg, IbcBasicResponse<C>, Q>,
+    packet_timeout: IbcClosure<IbcPacketTimeoutMsg, IbcBasicResponse<C>, Q>,
+}
+
+#[cfg(feature = "stargate")]
+fn ibc_closure<T, R, E, Q>(raw_fn: IbcFn<T, R, E, Q>) -> IbcClosure<T, R, Q>
+where
+    T: 'static,
+    R: 'static,
+    E: Display + Debug + Send + Sync + 'static,
+    Q: CustomQuery + 'static,
+{
+    Box::new(move |deps, env, msg| raw_fn(deps, env, msg).map_err(|err| anyhow!(err)))
+}
+
 /// Wraps the exported functions from a contract and provides the normalized format
 /// Place T4 and E4 at the end, as we just want default placeholders for most contracts that don't have sudo
 pub struct ContractWrapper<
@@ -91,6 +187,8 @@ pub struct ContractWrapper<
     sudo_fn: Option<PermissionedClosure<T4, C, E4, Q>>,
     reply_fn: Option<ReplyClosure<C, E5, Q>>,
     migrate_fn: Option<PermissionedClosure<T6, C, E6, Q>>,
+    #[cfg(feature = "stargate")]
+    ibc_fns: Option<IbcClosures<C, Q>>,
 }
 
 impl<T1, T2, T3, E1, E2, E3, C, Q> ContractWrapper<T1, T2, T3, E1, E2, E3, C, Q>
@@ -116,6 +214,8 @@ where
             sudo_fn: None,
             reply_fn: None,
             migrate_fn: None,
+            #[cfg(feature = "stargate")]
+            ibc_fns: None,
         }
     }
 
@@ -133,6 +233,8 @@ where
             sudo_fn: None,
             reply_fn: None,
             migrate_fn: None,
+            #[cfg(feature = "stargate")]
+            ibc_fns: None,
         }
     }
 }
@@ -169,6 +271,8 @@ where
             sudo_fn: Some(Box::new(sudo_fn)),
             reply_fn: self.reply_fn,
             migrate_fn: self.migrate_fn,
+            #[cfg(feature = "stargate")]
+            ibc_fns: self.ibc_fns,
         }
     }
 
@@ -187,6 +291,8 @@ where
             sudo_fn: Some(customize_permissioned_fn(sudo_fn)),
             reply_fn: self.reply_fn,
             migrate_fn: self.migrate_fn,
+            #[cfg(feature = "stargate")]
+            ibc_fns: self.ibc_fns,
         }
     }
 
@@ -204,6 +310,8 @@ where
             sudo_fn: self.sudo_fn,
             reply_fn: Some(Box::new(reply_fn)),
             migrate_fn: self.migrate_fn,
+            #[cfg(feature = "stargate")]
+            ibc_fns: self.ibc_fns,
         }
     }
 
@@ -222,6 +330,8 @@ where
             sudo_fn: self.sudo_fn,
             reply_fn: Some(customize_permissioned_fn(reply_fn)),
             migrate_fn: self.migrate_fn,
+            #[cfg(feature = "stargate")]
+            ibc_fns: self.ibc_fns,
         }
     }
 
@@ -240,6 +350,8 @@ where
             sudo_fn: self.sudo_fn,
             reply_fn: self.reply_fn,
             migrate_fn: Some(Box::new(migrate_fn)),
+            #[cfg(feature = "stargate")]
+            ibc_fns: self.ibc_fns,
         }
     }
 
@@ -258,6 +370,37 @@ where
             sudo_fn: self.sudo_fn,
             reply_fn: self.reply_fn,
             migrate_fn: Some(customize_permissioned_fn(migrate_fn)),
+            #[cfg(feature = "stargate")]
+            ibc_fns: self.ibc_fns,
+        }
+    }
+
+    #[cfg(feature = "stargate")]
+    /// Registers all six IBC entry points, a contract taking part in IBC has to export them all
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_ibc<E7>(
+        self,
+        channel_open: IbcFn<IbcChannelOpenMsg, (), E7, Q>,
+        channel_connect: IbcFn<IbcChannelConnectMsg, IbcBasicResponse<C>, E7, Q>,
+        channel_close: IbcFn<IbcChannelCloseMsg, IbcBasicResponse<C>, E7, Q>,
+        packet_receive: IbcFn<IbcPacketReceiveMsg, IbcReceiveResponse<C>, E7, Q>,
+        packet_ack: IbcFn<IbcPacketAckMsg, IbcBasicResponse<C>, E7, Q>,
+        packet_timeout: IbcFn<IbcPacketTimeoutMsg, IbcBasicResponse<C>, E7, Q>,
+    ) -> Self
+    where
+        E7: Display + Debug + Send + Sync + 'static,
+    {
+        ContractWrapper {
+            #[cfg(feature = "stargate")]
+            ibc_fns: Some(IbcClosures {
+                channel_open: ibc_closure(channel_open),
+                channel_connect: ibc_closure(channel_connect),
+                channel_close: ibc_closure(channel_close),
+                packet_receive: ibc_closure(packet_receive),
+                packet_ack: ibc_closure(packet_ack),
+                packet_timeout: ibc_closure(packet_timeout),
+            }),
+            ..self
         }
     }
 }
@@ -433,4 +576,91 @@ where
             None => bail!("migrate not implemented for contract"),
         }
     }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_open(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcChannelOpenMsg,
+    ) -> AnyResult<()> {
+        (self.ibc()?.channel_open)(deps, env, msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_connect(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcChannelConnectMsg,
+    ) -> AnyResult<IbcBasicResponse<C>> {
+        (self.ibc()?.channel_connect)(deps, env, msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_close(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcChannelCloseMsg,
+    ) -> AnyResult<IbcBasicResponse<C>> {
+        (self.ibc()?.channel_close)(deps, env, msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_receive(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcPacketReceiveMsg,
+    ) -> AnyResult<IbcReceiveResponse<C>> {
+        (self.ibc()?.packet_receive)(deps, env, msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_ack(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcPacketAckMsg,
+    ) -> AnyResult<IbcBasicResponse<C>> {
+        (self.ibc()?.packet_ack)(deps, env, msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_timeout(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcPacketTimeoutMsg,
+    ) -> AnyResult<IbcBasicResponse<C>> {
+        (self.ibc()?.packet_timeout)(deps, env, msg)
+    }
+}
+
+#[cfg(feature = "stargate")]
+impl<T1, T2, T3, E1, E2, E3, C, Q, T4, E4, E5, T6, E6>
+    ContractWrapper<T1, T2, T3, E1, E2, E3, C, Q, T4, E4, E5, T6, E6>
+where
+    T1: DeserializeOwned + Debug,
+    T2: DeserializeOwned,
+    T3: DeserializeOwned,
+    T4: DeserializeOwned,
+    T6: DeserializeOwned,
+    E1: Display + Debug + Send + Sync + 'static,
+    E2: Display + Debug + Send + Sync + 'static,
+    E3: Display + Debug + Send + Sync + 'static,
+    E4: Display + Debug + Send + Sync + 'static,
+    E5: Display + Debug + Send + Sync + 'static,
+    E6: Display + Debug + Send + Sync + 'static,
+    C: Clone + fmt::Debug + PartialEq + JsonSchema,
+    Q: CustomQuery + DeserializeOwned + 'static,
+{
+    // this returns an error if the contract doesn't implement ibc
+    fn ibc(&self) -> AnyResult<&IbcClosures<C, Q>> {
+        match &self.ibc_fns {
+            Some(ibc_fns) => Ok(ibc_fns),
+            None => bail!("ibc not implemented for contract"),
+        }
+    }
 }
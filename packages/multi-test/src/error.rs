@@ -1,4 +1,5 @@
-use cosmwasm_std::{WasmMsg, WasmQuery};
+use cosmwasm_std::{to_vec, WasmMsg, WasmQuery};
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq)]
@@ -23,6 +24,15 @@ pub enum Error {
 
     #[error("Unregistered code id")]
     UnregisteredCodeId(usize),
+
+    #[error("Unsupported query: {0}")]
+    UnsupportedQuery(String),
+
+    #[error("Invalid custom sudo message: {0}")]
+    InvalidCustomSudo(String),
+
+    #[error("Out of gas: used {used}, limit {limit}")]
+    OutOfGas { used: u64, limit: u64 },
 }
 
 impl Error {
@@ -43,4 +53,16 @@ impl Error {
     pub fn event_type_too_short(ty: impl Into<String>) -> Self {
         Self::EventTypeTooShort(ty.into())
     }
+
+    /// Keeps the query as json, custom queries are not required to implement `Debug`
+    pub fn unsupported_query(query: &impl Serialize) -> Self {
+        let query = to_vec(query)
+            .map(|query| String::from_utf8_lossy(&query).into_owned())
+            .unwrap_or_default();
+        Self::UnsupportedQuery(query)
+    }
+
+    pub fn invalid_custom_sudo(err: impl ToString) -> Self {
+        Self::InvalidCustomSudo(err.to_string())
+    }
 }
@@ -0,0 +1,239 @@
+use std::cell::Cell;
+
+use anyhow::{bail, Result as AnyResult};
+use cosmwasm_std::{Order, Record, Storage};
+
+use crate::error::Error;
+
+/// Costs charged by the `GasMeter`. Defaults to the storage costs of the cosmos-sdk KV store.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GasConfig {
+    /// Charged per byte of key and value read from contract storage
+    pub read_cost_per_byte: u64,
+    /// Charged per byte of key and value written to contract storage, removals charge the key
+    pub write_cost_per_byte: u64,
+    /// Charged per record an iterator over contract storage returns, on top of the read cost
+    pub iter_step_cost: u64,
+    /// Charged for every submessage dispatched by a contract
+    pub submsg_cost: u64,
+    /// Gas available to every transaction, `None` for no limit
+    pub tx_gas_limit: Option<u64>,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        GasConfig {
+            read_cost_per_byte: 3,
+            write_cost_per_byte: 30,
+            iter_step_cost: 30,
+            submsg_cost: 1000,
+            tx_gas_limit: None,
+        }
+    }
+}
+
+/// Counts the gas used by the current transaction.
+///
+/// Only contract storage access and submessages are charged, queries and the work of the other
+/// modules are free. As storage access cannot fail, running out of gas is detected when the
+/// contract call returns, which then fails with `Error::OutOfGas`.
+pub struct GasMeter {
+    config: GasConfig,
+    used: Cell<u64>,
+    limit: Cell<Option<u64>>,
+}
+
+impl GasMeter {
+    pub fn new(config: GasConfig) -> Self {
+        let limit = Cell::new(config.tx_gas_limit);
+        GasMeter {
+            config,
+            used: Cell::new(0),
+            limit,
+        }
+    }
+
+    pub fn config(&self) -> &GasConfig {
+        &self.config
+    }
+
+    /// Gas used since the transaction started
+    pub fn used(&self) -> u64 {
+        self.used.get()
+    }
+
+    /// Total gas the current call may use, including what was used before it
+    pub fn limit(&self) -> Option<u64> {
+        self.limit.get()
+    }
+
+    pub fn consume(&self, amount: u64) {
+        self.used.set(self.used.get().saturating_add(amount));
+    }
+
+    /// Fails if more gas was used than the limit of the current call allows
+    pub fn check(&self) -> AnyResult<()> {
+        match self.limit.get() {
+            Some(limit) if self.used.get() > limit => bail!(Error::OutOfGas {
+                used: self.used.get(),
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs `action` allowed to use at most `limit` gas (but never more than is left).
+    /// Exceeding just this limit charges the whole `limit`, so the caller may handle the error.
+    pub fn with_limit<T>(&self, limit: u64, action: impl FnOnce() -> AnyResult<T>) -> AnyResult<T> {
+        let outer = self.limit.get();
+        let mut inner = self.used.get().saturating_add(limit);
+        if let Some(outer) = outer {
+            inner = inner.min(outer);
+        }
+
+        self.limit.set(Some(inner));
+        let res = action();
+        self.limit.set(outer);
+
+        if self.used.get() > inner && outer != Some(inner) {
+            self.used.set(inner);
+        }
+        res
+    }
+
+    /// Starts metering a new transaction
+    pub(crate) fn reset(&self) {
+        self.used.set(0);
+        self.limit.set(self.config.tx_gas_limit);
+    }
+}
+
+/// Storage charging all access to the `GasMeter`
+pub(crate) struct GasMeteredStorage<'a> {
+    storage: &'a mut dyn Storage,
+    meter: &'a GasMeter,
+}
+
+impl<'a> GasMeteredStorage<'a> {
+    pub fn new(storage: &'a mut dyn Storage, meter: &'a GasMeter) -> Self {
+        GasMeteredStorage { storage, meter }
+    }
+}
+
+impl<'a> Storage for GasMeteredStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.storage.get(key);
+        let len = key.len() + value.as_ref().map(Vec::len).unwrap_or_default();
+        self.meter
+            .consume(self.meter.config.read_cost_per_byte * len as u64);
+        value
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        let meter = self.meter;
+        let iter = self
+            .storage
+            .range(start, end, order)
+            .inspect(move |(k, v)| {
+                let read = meter.config.read_cost_per_byte * (k.len() + v.len()) as u64;
+                meter.consume(meter.config.iter_step_cost + read);
+            });
+        Box::new(iter)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.meter
+            .consume(self.meter.config.write_cost_per_byte * (key.len() + value.len()) as u64);
+        self.storage.set(key, value);
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.meter
+            .consume(self.meter.config.write_cost_per_byte * key.len() as u64);
+        self.storage.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::testing::MockStorage;
+
+    fn config() -> GasConfig {
+        GasConfig {
+            read_cost_per_byte: 1,
+            write_cost_per_byte: 10,
+            iter_step_cost: 100,
+            submsg_cost: 1000,
+            tx_gas_limit: Some(10_000),
+        }
+    }
+
+    #[test]
+    fn charges_storage_access() {
+        let meter = GasMeter::new(config());
+        let mut base = MockStorage::new();
+        let mut storage = GasMeteredStorage::new(&mut base, &meter);
+
+        storage.set(b"foo", b"bar");
+        assert_eq!(meter.used(), 60);
+        assert_eq!(storage.get(b"foo"), Some(b"bar".to_vec()));
+        assert_eq!(meter.used(), 66);
+        // missing values charge the key only
+        assert_eq!(storage.get(b"baz"), None);
+        assert_eq!(meter.used(), 69);
+        // only the records taken are charged
+        storage.set(b"foo2", b"bar");
+        let mut iter = storage.range(None, None, Order::Ascending);
+        iter.next().unwrap();
+        drop(iter);
+        assert_eq!(meter.used(), 245);
+        storage.remove(b"foo");
+        assert_eq!(meter.used(), 275);
+        meter.check().unwrap();
+    }
+
+    #[test]
+    fn nested_limits() {
+        let meter = GasMeter::new(config());
+        meter.consume(1000);
+
+        // running out of the inner limit charges it fully, but leaves the rest
+        let err = meter
+            .with_limit(2000, || {
+                meter.consume(2500);
+                meter.check()
+            })
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<Error>().unwrap(),
+            Error::OutOfGas {
+                used: 3500,
+                limit: 3000
+            }
+        );
+        assert_eq!(meter.used(), 3000);
+        meter.check().unwrap();
+
+        // the inner limit never exceeds what the outer one leaves
+        meter
+            .with_limit(100_000, || {
+                assert_eq!(meter.limit(), Some(10_000));
+                meter.consume(8000);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(meter.used(), 11_000);
+        meter.check().unwrap_err();
+
+        meter.reset();
+        assert_eq!(meter.used(), 0);
+        meter.check().unwrap();
+    }
+}
@@ -0,0 +1,532 @@
+use anyhow::{anyhow, bail, Result as AnyResult};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{
+    from_slice, to_binary, Addr, AllDelegationsResponse, Api, Binary, BlockInfo, CustomQuery,
+    Decimal, Empty, Event, GovMsg, Order, Querier, QueryRequest, StakingQuery, Storage, Timestamp,
+    Uint128, VoteOption,
+};
+use cosmwasm_storage::{prefixed, prefixed_read};
+use cw_storage_plus::{Item, Map};
+
+use crate::app::CosmosRouter;
+use crate::executor::AppResponse;
+use crate::module::FailingModule;
+use crate::Module;
+
+const PROPOSAL_COUNT: Item<u64> = Item::new("proposal_count");
+const PROPOSALS: Map<u64, Proposal> = Map::new("proposals");
+/// (proposal id, voter) -> vote
+const VOTES: Map<(u64, &Addr), Vec<WeightedVoteOption>> = Map::new("votes");
+
+pub const NAMESPACE_GOV: &[u8] = b"gov";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Proposal {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    /// Votes are accepted until this time
+    pub voting_end: Timestamp,
+    pub status: ProposalStatus,
+    /// Set once the proposal was tallied
+    pub tally: Option<TallyResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum ProposalStatus {
+    VotingPeriod,
+    Passed,
+    Rejected,
+}
+
+/// Voting power cast for each option
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct TallyResult {
+    pub yes: Uint128,
+    pub no: Uint128,
+    pub abstain: Uint128,
+    pub no_with_veto: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WeightedVoteOption {
+    pub option: VoteOption,
+    pub weight: Decimal,
+}
+
+#[derive(Clone, std::fmt::Debug, PartialEq, JsonSchema)]
+pub enum GovSudo {
+    /// Opens a proposal for `voting_period` seconds. The new proposal id is returned as data.
+    SubmitProposal {
+        title: String,
+        description: String,
+        voting_period: u64,
+    },
+    /// Casts a weighted vote on behalf of `voter`, as cosmwasm-std has no `GovMsg` for it
+    VoteWeighted {
+        voter: String,
+        proposal_id: u64,
+        options: Vec<WeightedVoteOption>,
+    },
+    /// Tallies the votes by the bonded tokens of each voter, once the voting period is over
+    Tally { proposal_id: u64 },
+}
+
+pub trait Gov: Module<ExecT = GovMsg, QueryT = Empty, SudoT = GovSudo> {}
+
+pub type FailingGov = FailingModule<GovMsg, Empty, GovSudo>;
+
+impl Gov for FailingGov {}
+
+/// Records proposals and votes. Contracts vote through `GovMsg::Vote`, proposals are
+/// submitted and tallied through `GovSudo`.
+#[derive(Default)]
+pub struct GovKeeper {}
+
+impl GovKeeper {
+    pub fn new() -> Self {
+        GovKeeper {}
+    }
+
+    pub fn proposal(&self, storage: &dyn Storage, proposal_id: u64) -> AnyResult<Option<Proposal>> {
+        let gov_storage = prefixed_read(storage, NAMESPACE_GOV);
+        Ok(PROPOSALS.may_load(&gov_storage, proposal_id)?)
+    }
+
+    /// The vote `voter` cast on the proposal, if any
+    pub fn vote(
+        &self,
+        storage: &dyn Storage,
+        proposal_id: u64,
+        voter: &Addr,
+    ) -> AnyResult<Option<Vec<WeightedVoteOption>>> {
+        let gov_storage = prefixed_read(storage, NAMESPACE_GOV);
+        Ok(VOTES.may_load(&gov_storage, (proposal_id, voter))?)
+    }
+
+    /// All votes cast on the proposal, ordered by voter
+    pub fn votes(
+        &self,
+        storage: &dyn Storage,
+        proposal_id: u64,
+    ) -> AnyResult<Vec<(Addr, Vec<WeightedVoteOption>)>> {
+        let gov_storage = prefixed_read(storage, NAMESPACE_GOV);
+        let votes = VOTES
+            .prefix(proposal_id)
+            .range(&gov_storage, None, None, Order::Ascending)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(votes)
+    }
+
+    fn submit_proposal(
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        title: String,
+        description: String,
+        voting_period: u64,
+    ) -> AnyResult<u64> {
+        let mut gov_storage = prefixed(storage, NAMESPACE_GOV);
+        let id = PROPOSAL_COUNT.may_load(&gov_storage)?.unwrap_or_default() + 1;
+        PROPOSAL_COUNT.save(&mut gov_storage, &id)?;
+        let proposal = Proposal {
+            id,
+            title,
+            description,
+            voting_end: block.time.plus_seconds(voting_period),
+            status: ProposalStatus::VotingPeriod,
+            tally: None,
+        };
+        PROPOSALS.save(&mut gov_storage, id, &proposal)?;
+        Ok(id)
+    }
+
+    fn cast_vote(
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        voter: &Addr,
+        proposal_id: u64,
+        options: Vec<WeightedVoteOption>,
+    ) -> AnyResult<AppResponse> {
+        let mut gov_storage = prefixed(storage, NAMESPACE_GOV);
+        let proposal = PROPOSALS
+            .may_load(&gov_storage, proposal_id)?
+            .ok_or_else(|| anyhow!("Unknown proposal {}", proposal_id))?;
+        if proposal.status != ProposalStatus::VotingPeriod || block.time >= proposal.voting_end {
+            bail!("Proposal {} is not in its voting period", proposal_id);
+        }
+        Self::validate_options(&options)?;
+
+        let option = options
+            .iter()
+            .map(|o| format!("{}:{}", option_str(&o.option), o.weight))
+            .collect::<Vec<_>>()
+            .join(",");
+        VOTES.save(&mut gov_storage, (proposal_id, voter), &options)?;
+        // see https://github.com/cosmos/cosmos-sdk/blob/v0.45.4/x/gov/keeper/vote.go#L50-L56
+        let events = vec![Event::new("proposal_vote")
+            .add_attribute("option", option)
+            .add_attribute("proposal_id", proposal_id.to_string())];
+        Ok(AppResponse { events, data: None })
+    }
+
+    fn validate_options(options: &[WeightedVoteOption]) -> AnyResult<()> {
+        if options.is_empty() {
+            bail!("A vote needs at least one option");
+        }
+        for (i, o) in options.iter().enumerate() {
+            if o.weight.is_zero() || o.weight > Decimal::one() {
+                bail!("Vote weights must be between 0 and 1");
+            }
+            if options[..i].iter().any(|prev| prev.option == o.option) {
+                bail!("Duplicated vote option {}", option_str(&o.option));
+            }
+        }
+        let total = options
+            .iter()
+            .fold(Decimal::zero(), |total, o| total + o.weight);
+        if total != Decimal::one() {
+            bail!("Vote weights must sum up to 1");
+        }
+        Ok(())
+    }
+
+    /// Voting power is the sum of all tokens the voter has bonded
+    fn voting_power<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        voter: &Addr,
+    ) -> AnyResult<Uint128> {
+        let request = QueryRequest::Staking(StakingQuery::AllDelegations {
+            delegator: voter.to_string(),
+        });
+        let res: AllDelegationsResponse = from_slice(&router.query(api, storage, block, request)?)?;
+        Ok(res.delegations.iter().map(|d| d.amount.amount).sum())
+    }
+
+    fn tally<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        proposal_id: u64,
+    ) -> AnyResult<Proposal> {
+        let gov_storage = prefixed_read(storage, NAMESPACE_GOV);
+        let mut proposal = PROPOSALS
+            .may_load(&gov_storage, proposal_id)?
+            .ok_or_else(|| anyhow!("Unknown proposal {}", proposal_id))?;
+        if proposal.status != ProposalStatus::VotingPeriod {
+            bail!("Proposal {} was already tallied", proposal_id);
+        }
+        if block.time < proposal.voting_end {
+            bail!("Voting period of proposal {} has not ended", proposal_id);
+        }
+        let votes = VOTES
+            .prefix(proposal_id)
+            .range(&gov_storage, None, None, Order::Ascending)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut tally = TallyResult::default();
+        for (voter, options) in votes {
+            let power = Self::voting_power(api, storage, router, block, &voter)?;
+            for o in options {
+                let share = power * o.weight;
+                match o.option {
+                    VoteOption::Yes => tally.yes += share,
+                    VoteOption::No => tally.no += share,
+                    VoteOption::Abstain => tally.abstain += share,
+                    VoteOption::NoWithVeto => tally.no_with_veto += share,
+                }
+            }
+        }
+
+        // sdk default threshold and veto, quorum is not checked
+        let total = tally.yes + tally.no + tally.abstain + tally.no_with_veto;
+        let vetoed = tally.no_with_veto > total * Decimal::permille(334);
+        let passed = !vetoed && tally.yes > (total - tally.abstain) * Decimal::percent(50);
+        proposal.status = if passed {
+            ProposalStatus::Passed
+        } else {
+            ProposalStatus::Rejected
+        };
+        proposal.tally = Some(tally);
+
+        let mut gov_storage = prefixed(storage, NAMESPACE_GOV);
+        PROPOSALS.save(&mut gov_storage, proposal_id, &proposal)?;
+        Ok(proposal)
+    }
+}
+
+fn option_str(option: &VoteOption) -> &'static str {
+    match option {
+        VoteOption::Yes => "yes",
+        VoteOption::No => "no",
+        VoteOption::Abstain => "abstain",
+        VoteOption::NoWithVeto => "no_with_veto",
+    }
+}
+
+impl Gov for GovKeeper {}
+
+impl Module for GovKeeper {
+    type ExecT = GovMsg;
+    type QueryT = Empty;
+    type SudoT = GovSudo;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: GovMsg,
+    ) -> AnyResult<AppResponse> {
+        match msg {
+            GovMsg::Vote { proposal_id, vote } => {
+                let options = vec![WeightedVoteOption {
+                    option: vote,
+                    weight: Decimal::one(),
+                }];
+                Self::cast_vote(storage, block, &sender, proposal_id, options)
+            }
+        }
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: GovSudo,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        match msg {
+            GovSudo::SubmitProposal {
+                title,
+                description,
+                voting_period,
+            } => {
+                let id = Self::submit_proposal(storage, block, title, description, voting_period)?;
+                let events = vec![
+                    Event::new("submit_proposal").add_attribute("proposal_id", id.to_string())
+                ];
+                Ok(AppResponse {
+                    events,
+                    data: Some(to_binary(&id)?),
+                })
+            }
+            GovSudo::VoteWeighted {
+                voter,
+                proposal_id,
+                options,
+            } => {
+                let voter = api.addr_validate(&voter)?;
+                Self::cast_vote(storage, block, &voter, proposal_id, options)
+            }
+            GovSudo::Tally { proposal_id } => {
+                let proposal = Self::tally(api, storage, router, block, proposal_id)?;
+                let result = match proposal.status {
+                    ProposalStatus::Passed => "proposal_passed",
+                    _ => "proposal_rejected",
+                };
+                let events = vec![Event::new("active_proposal")
+                    .add_attribute("proposal_id", proposal_id.to_string())
+                    .add_attribute("proposal_result", result)];
+                Ok(AppResponse { events, data: None })
+            }
+        }
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        _storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: Empty,
+    ) -> AnyResult<Binary> {
+        bail!("Unexpected gov query {:?}", request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{App, AppBuilder, Executor};
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::{coin, coins, from_binary, CosmosMsg, StakingMsg, Validator};
+
+    const VALIDATOR: &str = "testvaloper1";
+
+    fn setup_app(voters: &[(&str, u128)]) -> App {
+        let block = mock_env().block;
+        let mut app = AppBuilder::new().build(|router, _api, storage| {
+            for (voter, power) in voters {
+                router
+                    .bank
+                    .init_balance(storage, &Addr::unchecked(*voter), coins(*power, "TOKEN"))
+                    .unwrap();
+            }
+            let validator = Validator {
+                address: VALIDATOR.to_string(),
+                commission: Decimal::zero(),
+                max_commission: Decimal::one(),
+                max_change_rate: Decimal::one(),
+            };
+            router
+                .staking
+                .add_validator(storage, &block, validator)
+                .unwrap();
+        });
+        for (voter, power) in voters {
+            app.execute(
+                Addr::unchecked(*voter),
+                CosmosMsg::Staking(StakingMsg::Delegate {
+                    validator: VALIDATOR.to_string(),
+                    amount: coin(*power, "TOKEN"),
+                }),
+            )
+            .unwrap();
+        }
+        app
+    }
+
+    fn submit_proposal(app: &mut App) -> u64 {
+        let res = app
+            .sudo(
+                GovSudo::SubmitProposal {
+                    title: "Title".to_string(),
+                    description: "Description".to_string(),
+                    voting_period: 100,
+                }
+                .into(),
+            )
+            .unwrap();
+        from_binary(&res.data.unwrap()).unwrap()
+    }
+
+    fn vote(
+        app: &mut App,
+        voter: &str,
+        proposal_id: u64,
+        vote: VoteOption,
+    ) -> AnyResult<AppResponse> {
+        app.execute(
+            Addr::unchecked(voter),
+            CosmosMsg::Gov(GovMsg::Vote { proposal_id, vote }),
+        )
+    }
+
+    fn tally(app: &mut App, proposal_id: u64) -> Proposal {
+        app.update_block(|block| block.time = block.time.plus_seconds(100));
+        app.sudo(GovSudo::Tally { proposal_id }.into()).unwrap();
+        app.read_module(|router, _, storage| router.gov.proposal(storage, proposal_id))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn vote_and_tally() {
+        let mut app = setup_app(&[("alice", 600), ("bob", 300), ("carol", 100)]);
+        let id = submit_proposal(&mut app);
+        assert_eq!(id, 1);
+        assert_eq!(submit_proposal(&mut app), 2);
+
+        vote(&mut app, "alice", id, VoteOption::No).unwrap();
+        // voting again replaces the vote
+        vote(&mut app, "alice", id, VoteOption::Yes).unwrap();
+        vote(&mut app, "bob", id, VoteOption::No).unwrap();
+        app.sudo(
+            GovSudo::VoteWeighted {
+                voter: "carol".to_string(),
+                proposal_id: id,
+                options: vec![
+                    WeightedVoteOption {
+                        option: VoteOption::Abstain,
+                        weight: Decimal::percent(70),
+                    },
+                    WeightedVoteOption {
+                        option: VoteOption::NoWithVeto,
+                        weight: Decimal::percent(30),
+                    },
+                ],
+            }
+            .into(),
+        )
+        .unwrap();
+        vote(&mut app, "alice", 3, VoteOption::Yes).unwrap_err();
+
+        let votes = app
+            .read_module(|router, _, storage| router.gov.votes(storage, id))
+            .unwrap();
+        assert_eq!(votes.len(), 3);
+        assert_eq!(
+            votes[0],
+            (
+                Addr::unchecked("alice"),
+                vec![WeightedVoteOption {
+                    option: VoteOption::Yes,
+                    weight: Decimal::one()
+                }]
+            )
+        );
+
+        let proposal = tally(&mut app, id);
+        assert_eq!(proposal.status, ProposalStatus::Passed);
+        assert_eq!(
+            proposal.tally,
+            Some(TallyResult {
+                yes: Uint128::new(600),
+                no: Uint128::new(300),
+                abstain: Uint128::new(70),
+                no_with_veto: Uint128::new(30),
+            })
+        );
+
+        // voting is closed after the voting period
+        vote(&mut app, "bob", id, VoteOption::Yes).unwrap_err();
+        app.sudo(GovSudo::Tally { proposal_id: id }.into())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn invalid_weights_and_veto() {
+        let mut app = setup_app(&[("alice", 600), ("bob", 400)]);
+        let id = submit_proposal(&mut app);
+
+        let weighted = |options: Vec<(VoteOption, u64)>| GovSudo::VoteWeighted {
+            voter: "bob".to_string(),
+            proposal_id: id,
+            options: options
+                .into_iter()
+                .map(|(option, weight)| WeightedVoteOption {
+                    option,
+                    weight: Decimal::percent(weight),
+                })
+                .collect(),
+        };
+        app.sudo(weighted(vec![(VoteOption::Yes, 50)]).into())
+            .unwrap_err();
+        app.sudo(weighted(vec![(VoteOption::Yes, 50), (VoteOption::Yes, 50)]).into())
+            .unwrap_err();
+        app.sudo(weighted(vec![(VoteOption::Yes, 0), (VoteOption::No, 100)]).into())
+            .unwrap_err();
+        app.sudo(weighted(vec![]).into()).unwrap_err();
+
+        // a third of vetoes rejects the proposal despite a majority of yes
+        vote(&mut app, "alice", id, VoteOption::Yes).unwrap();
+        vote(&mut app, "bob", id, VoteOption::NoWithVeto).unwrap();
+        let proposal = tally(&mut app, id);
+        assert_eq!(proposal.status, ProposalStatus::Rejected);
+    }
+}
@@ -0,0 +1,1091 @@
+use anyhow::{anyhow, bail, Result as AnyResult};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use cosmwasm_std::{
+    coin, from_binary, to_binary, Addr, Api, BankMsg, Binary, BlockInfo, ChannelResponse,
+    CustomQuery, Event, IbcAcknowledgement, IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg,
+    IbcChannelOpenMsg, IbcEndpoint, IbcMsg, IbcOrder, IbcPacket, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcQuery, IbcTimeout, ListChannelsResponse, Order,
+    Querier, Storage, Uint128,
+};
+use cosmwasm_storage::{prefixed, prefixed_read};
+use cw_storage_plus::{Item, Map};
+
+use crate::app::CosmosRouter;
+use crate::bank::BankSudo;
+use crate::executor::AppResponse;
+use crate::module::FailingModule;
+use crate::transactions::transactional;
+use crate::wasm::{WasmIbcMsg, WasmIbcSudo};
+use crate::Module;
+
+const CHANNEL_COUNT: Item<u64> = Item::new("channel_count");
+/// channel id -> channel end, ids are unique over all ports
+const CHANNELS: Map<&str, ChannelInfo> = Map::new("channels");
+const NEXT_SEQUENCE: Map<&str, u64> = Map::new("next_sequence");
+/// (source channel id, sequence) -> packets sent but not relayed yet
+const PACKETS: Map<(&str, u64), IbcPacket> = Map::new("packets");
+/// ics20 voucher denom -> full denom trace
+const DENOM_TRACES: Map<&str, String> = Map::new("denom_traces");
+
+pub const NAMESPACE_IBC: &[u8] = b"ibc";
+
+/// Port of the ics20 transfer module, contracts are bound to `wasm.<contract address>`
+pub const TRANSFER_PORT: &str = "transfer";
+const ICS20_VERSION: &str = "ics20-1";
+const CONTRACT_PORT_PREFIX: &str = "wasm.";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct ChannelInfo {
+    channel: IbcChannel,
+    open: bool,
+}
+
+/// Packet data of ics20 transfers
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+struct FungibleTokenPacketData {
+    denom: String,
+    amount: Uint128,
+    sender: String,
+    receiver: String,
+}
+
+/// Acknowledgement of ics20 transfers, also written for contracts failing to receive a packet
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Ics20Ack {
+    Result(Binary),
+    Error(String),
+}
+
+#[derive(Clone, std::fmt::Debug, PartialEq, JsonSchema)]
+pub enum IbcSudo {
+    /// Runs the handshake of a channel between two ports of this app. `src_port` starts it.
+    /// The ids of the (src, dst) channel ends are returned as data.
+    OpenChannel {
+        src_port: String,
+        dst_port: String,
+        order: IbcOrder,
+        version: String,
+        connection_id: String,
+    },
+    /// Closes both ends of a channel, `channel_id` being the end that starts it
+    CloseChannel { channel_id: String },
+    /// Delivers all packets sent so far, then their acknowledgements or timeouts.
+    /// Packets sent while relaying wait for the next call.
+    RelayPackets {},
+}
+
+pub trait Ibc: Module<ExecT = IbcMsg, QueryT = IbcQuery, SudoT = IbcSudo> {}
+
+pub type FailingIbc = FailingModule<IbcMsg, IbcQuery, IbcSudo>;
+
+impl Ibc for FailingIbc {}
+
+/// Voucher denom of a token received over ics20 with the given trace,
+/// eg. `transfer/channel-1/uatom`
+pub fn ibc_denom(trace: &str) -> String {
+    format!(
+        "ibc/{}",
+        hex::encode_upper(Sha256::digest(trace.as_bytes()))
+    )
+}
+
+/// The local denom of a token known by its trace
+fn local_denom(trace: &str) -> String {
+    if trace.contains('/') {
+        ibc_denom(trace)
+    } else {
+        trace.to_string()
+    }
+}
+
+fn escrow_address(channel_id: &str) -> Addr {
+    Addr::unchecked(format!("ibc_escrow_{}", channel_id))
+}
+
+fn is_timed_out(timeout: &IbcTimeout, block: &BlockInfo) -> bool {
+    matches!(timeout.block(), Some(b) if block.height >= b.height)
+        || matches!(timeout.timestamp(), Some(t) if block.time >= t)
+}
+
+fn packet_event(ty: &str, packet: &IbcPacket) -> Event {
+    Event::new(ty)
+        .add_attribute("packet_src_port", &packet.src.port_id)
+        .add_attribute("packet_src_channel", &packet.src.channel_id)
+        .add_attribute("packet_dst_port", &packet.dest.port_id)
+        .add_attribute("packet_dst_channel", &packet.dest.channel_id)
+        .add_attribute("packet_sequence", packet.sequence.to_string())
+}
+
+/// Connects ports of a single app through in-memory channels. Contracts take part through
+/// their IBC entry points, the `transfer` port moves bank tokens like ics20 does.
+#[derive(Default)]
+pub struct IbcKeeper {}
+
+impl IbcKeeper {
+    pub fn new() -> Self {
+        IbcKeeper {}
+    }
+
+    /// Full denom trace of an ics20 voucher, if it was ever received
+    pub fn denom_trace(&self, storage: &dyn Storage, denom: &str) -> AnyResult<Option<String>> {
+        let ibc_storage = prefixed_read(storage, NAMESPACE_IBC);
+        Ok(DENOM_TRACES.may_load(&ibc_storage, denom)?)
+    }
+
+    fn load_channel(storage: &dyn Storage, channel_id: &str) -> AnyResult<ChannelInfo> {
+        let ibc_storage = prefixed_read(storage, NAMESPACE_IBC);
+        CHANNELS
+            .may_load(&ibc_storage, channel_id)?
+            .ok_or_else(|| anyhow!("Channel {} not found", channel_id))
+    }
+
+    fn call_contract<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        port_id: &str,
+        msg: WasmIbcMsg,
+    ) -> AnyResult<AppResponse> {
+        let contract = port_id
+            .strip_prefix(CONTRACT_PORT_PREFIX)
+            .ok_or_else(|| anyhow!("Port {} is not bound to a contract", port_id))?;
+        let sudo = WasmIbcSudo {
+            contract_addr: api.addr_validate(contract)?,
+            msg,
+        };
+        router.sudo(api, storage, block, sudo.into())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn open_channel<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        src_port: String,
+        dst_port: String,
+        order: IbcOrder,
+        version: String,
+        connection_id: String,
+    ) -> AnyResult<AppResponse> {
+        for port in [&src_port, &dst_port] {
+            if port != TRANSFER_PORT && !port.starts_with(CONTRACT_PORT_PREFIX) {
+                bail!("Port {} is not bound", port);
+            }
+        }
+        if (src_port == TRANSFER_PORT) != (dst_port == TRANSFER_PORT) {
+            bail!("The transfer port can only connect to another transfer port");
+        }
+        if src_port == TRANSFER_PORT && version != ICS20_VERSION {
+            bail!("Transfer channels must use version {}", ICS20_VERSION);
+        }
+
+        let mut ibc_storage = prefixed(storage, NAMESPACE_IBC);
+        let count = CHANNEL_COUNT.may_load(&ibc_storage)?.unwrap_or_default();
+        CHANNEL_COUNT.save(&mut ibc_storage, &(count + 2))?;
+        let src = IbcEndpoint {
+            port_id: src_port,
+            channel_id: format!("channel-{}", count),
+        };
+        let dst = IbcEndpoint {
+            port_id: dst_port,
+            channel_id: format!("channel-{}", count + 1),
+        };
+        let src_channel = IbcChannel::new(
+            src.clone(),
+            dst.clone(),
+            order.clone(),
+            version.clone(),
+            connection_id.clone(),
+        );
+        let dst_channel = IbcChannel::new(dst.clone(), src.clone(), order, &version, connection_id);
+
+        let mut events = vec![];
+        if src.port_id != TRANSFER_PORT {
+            let init = IbcChannelOpenMsg::new_init(src_channel.clone());
+            let res = Self::call_contract(
+                api,
+                storage,
+                router,
+                block,
+                &src.port_id,
+                WasmIbcMsg::ChannelOpen(init),
+            )?;
+            events.extend(res.events);
+            let try_msg = IbcChannelOpenMsg::new_try(dst_channel.clone(), &version);
+            let res = Self::call_contract(
+                api,
+                storage,
+                router,
+                block,
+                &dst.port_id,
+                WasmIbcMsg::ChannelOpen(try_msg),
+            )?;
+            events.extend(res.events);
+        }
+
+        // both ends are open once connected, so contracts may send packets while connecting
+        let mut ibc_storage = prefixed(storage, NAMESPACE_IBC);
+        for channel in [&src_channel, &dst_channel] {
+            let info = ChannelInfo {
+                channel: channel.clone(),
+                open: true,
+            };
+            CHANNELS.save(&mut ibc_storage, &channel.endpoint.channel_id, &info)?;
+        }
+
+        if src.port_id != TRANSFER_PORT {
+            let ack = IbcChannelConnectMsg::new_ack(src_channel, &version);
+            let res = Self::call_contract(
+                api,
+                storage,
+                router,
+                block,
+                &src.port_id,
+                WasmIbcMsg::ChannelConnect(ack),
+            )?;
+            events.extend(res.events);
+            let confirm = IbcChannelConnectMsg::new_confirm(dst_channel);
+            let res = Self::call_contract(
+                api,
+                storage,
+                router,
+                block,
+                &dst.port_id,
+                WasmIbcMsg::ChannelConnect(confirm),
+            )?;
+            events.extend(res.events);
+        }
+
+        for (end, counterparty) in [(&src, &dst), (&dst, &src)] {
+            events.push(
+                Event::new("channel_open")
+                    .add_attribute("port_id", &end.port_id)
+                    .add_attribute("channel_id", &end.channel_id)
+                    .add_attribute("counterparty_port_id", &counterparty.port_id)
+                    .add_attribute("counterparty_channel_id", &counterparty.channel_id),
+            );
+        }
+        Ok(AppResponse {
+            events,
+            data: Some(to_binary(&(src.channel_id, dst.channel_id))?),
+        })
+    }
+
+    fn close_channel<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        channel_id: &str,
+    ) -> AnyResult<AppResponse> {
+        let mut init = Self::load_channel(storage, channel_id)?;
+        if !init.open {
+            bail!("Channel {} is already closed", channel_id);
+        }
+        let mut confirm =
+            Self::load_channel(storage, &init.channel.counterparty_endpoint.channel_id)?;
+        init.open = false;
+        confirm.open = false;
+        let mut ibc_storage = prefixed(storage, NAMESPACE_IBC);
+        for info in [&init, &confirm] {
+            CHANNELS.save(&mut ibc_storage, &info.channel.endpoint.channel_id, info)?;
+        }
+
+        let mut events = vec![];
+        if init.channel.endpoint.port_id != TRANSFER_PORT {
+            let port_id = init.channel.endpoint.port_id.clone();
+            let close = IbcChannelCloseMsg::new_init(init.channel);
+            let res = Self::call_contract(
+                api,
+                storage,
+                router,
+                block,
+                &port_id,
+                WasmIbcMsg::ChannelClose(close),
+            )?;
+            events.extend(res.events);
+            let port_id = confirm.channel.endpoint.port_id.clone();
+            let close = IbcChannelCloseMsg::new_confirm(confirm.channel);
+            let res = Self::call_contract(
+                api,
+                storage,
+                router,
+                block,
+                &port_id,
+                WasmIbcMsg::ChannelClose(close),
+            )?;
+            events.extend(res.events);
+        }
+        events.push(Event::new("channel_close").add_attribute("channel_id", channel_id));
+        Ok(AppResponse { events, data: None })
+    }
+
+    fn send_packet(
+        storage: &mut dyn Storage,
+        port_id: &str,
+        channel_id: &str,
+        data: Binary,
+        timeout: IbcTimeout,
+    ) -> AnyResult<AppResponse> {
+        let info = Self::load_channel(storage, channel_id)?;
+        if !info.open {
+            bail!("Channel {} is closed", channel_id);
+        }
+        if info.channel.endpoint.port_id != port_id {
+            bail!("Channel {} is not bound to port {}", channel_id, port_id);
+        }
+
+        let mut ibc_storage = prefixed(storage, NAMESPACE_IBC);
+        let sequence = NEXT_SEQUENCE
+            .may_load(&ibc_storage, channel_id)?
+            .unwrap_or(1);
+        NEXT_SEQUENCE.save(&mut ibc_storage, channel_id, &(sequence + 1))?;
+        let packet = IbcPacket::new(
+            data,
+            info.channel.endpoint,
+            info.channel.counterparty_endpoint,
+            sequence,
+            timeout,
+        );
+        PACKETS.save(&mut ibc_storage, (channel_id, sequence), &packet)?;
+
+        let events = vec![packet_event("send_packet", &packet)];
+        Ok(AppResponse { events, data: None })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn transfer<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        channel_id: String,
+        to_address: String,
+        amount: cosmwasm_std::Coin,
+        timeout: IbcTimeout,
+    ) -> AnyResult<AppResponse> {
+        if amount.amount.is_zero() {
+            bail!("Cannot transfer zero tokens");
+        }
+        let trace = if amount.denom.starts_with("ibc/") {
+            let ibc_storage = prefixed_read(storage, NAMESPACE_IBC);
+            DENOM_TRACES
+                .may_load(&ibc_storage, &amount.denom)?
+                .ok_or_else(|| anyhow!("Unknown ibc denom {}", amount.denom))?
+        } else {
+            amount.denom.clone()
+        };
+
+        // vouchers going back where they came from are burned, other tokens are escrowed
+        let returning = trace.starts_with(&format!("{}/{}/", TRANSFER_PORT, channel_id));
+        let bank_msg = if returning {
+            BankMsg::Burn {
+                amount: vec![amount.clone()],
+            }
+        } else {
+            BankMsg::Send {
+                to_address: escrow_address(&channel_id).into(),
+                amount: vec![amount.clone()],
+            }
+        };
+        let data = to_binary(&FungibleTokenPacketData {
+            denom: trace,
+            amount: amount.amount,
+            sender: sender.to_string(),
+            receiver: to_address,
+        })?;
+        let res = Self::send_packet(storage, TRANSFER_PORT, &channel_id, data, timeout)?;
+        router.execute(api, storage, block, sender, bank_msg.into())?;
+        Ok(res)
+    }
+
+    /// Credits an incoming ics20 transfer, returning the acknowledgement
+    fn receive_transfer<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        packet: &IbcPacket,
+    ) -> AnyResult<Binary> {
+        let res = transactional(storage, |write_cache, _| -> AnyResult<()> {
+            let data: FungibleTokenPacketData = from_binary(&packet.data)?;
+            let receiver = api.addr_validate(&data.receiver)?;
+            let prefix = format!("{}/{}/", packet.src.port_id, packet.src.channel_id);
+            match data.denom.strip_prefix(&prefix) {
+                // tokens coming back are released from escrow
+                Some(trace) => {
+                    let msg = BankMsg::Send {
+                        to_address: receiver.into(),
+                        amount: vec![coin(data.amount.u128(), local_denom(trace))],
+                    };
+                    let escrow = escrow_address(&packet.dest.channel_id);
+                    router.execute(api, write_cache, block, escrow, msg.into())?;
+                }
+                // others are represented by vouchers
+                None => {
+                    let trace = format!(
+                        "{}/{}/{}",
+                        packet.dest.port_id, packet.dest.channel_id, data.denom
+                    );
+                    let denom = ibc_denom(&trace);
+                    let mut ibc_storage = prefixed(write_cache, NAMESPACE_IBC);
+                    DENOM_TRACES.save(&mut ibc_storage, &denom, &trace)?;
+                    let mint = BankSudo::Mint {
+                        to_address: receiver.into(),
+                        amount: vec![coin(data.amount.u128(), denom)],
+                    };
+                    router.sudo(api, write_cache, block, mint.into())?;
+                }
+            }
+            Ok(())
+        });
+        let ack = match res {
+            Ok(()) => Ics20Ack::Result(Binary::from(vec![1])),
+            Err(err) => Ics20Ack::Error(err.to_string()),
+        };
+        Ok(to_binary(&ack)?)
+    }
+
+    /// Gives the tokens of a failed ics20 transfer back to the sender
+    fn refund_transfer<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        packet: &IbcPacket,
+    ) -> AnyResult<AppResponse> {
+        let data: FungibleTokenPacketData = from_binary(&packet.data)?;
+        let amount = vec![coin(data.amount.u128(), local_denom(&data.denom))];
+        let prefix = format!("{}/{}/", packet.src.port_id, packet.src.channel_id);
+        if data.denom.starts_with(&prefix) {
+            let mint = BankSudo::Mint {
+                to_address: data.sender,
+                amount,
+            };
+            router.sudo(api, storage, block, mint.into())
+        } else {
+            let msg = BankMsg::Send {
+                to_address: data.sender,
+                amount,
+            };
+            let escrow = escrow_address(&packet.src.channel_id);
+            router.execute(api, storage, block, escrow, msg.into())
+        }
+    }
+
+    fn relay_packets<ExecC, QueryC: CustomQuery>(
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+    ) -> AnyResult<AppResponse> {
+        let packets = {
+            let mut ibc_storage = prefixed(storage, NAMESPACE_IBC);
+            let packets = PACKETS
+                .range(&ibc_storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, packet)| packet))
+                .collect::<Result<Vec<_>, _>>()?;
+            for packet in packets.iter() {
+                PACKETS.remove(&mut ibc_storage, (&packet.src.channel_id, packet.sequence));
+            }
+            packets
+        };
+
+        let mut events = vec![];
+        for packet in packets {
+            let dest = Self::load_channel(storage, &packet.dest.channel_id)?;
+            if !dest.open || is_timed_out(&packet.timeout, block) {
+                events.push(packet_event("timeout_packet", &packet));
+                let res = if packet.src.port_id == TRANSFER_PORT {
+                    Self::refund_transfer(api, storage, router, block, &packet)?
+                } else {
+                    let timeout = IbcPacketTimeoutMsg::new(packet.clone());
+                    Self::call_contract(
+                        api,
+                        storage,
+                        router,
+                        block,
+                        &packet.src.port_id,
+                        WasmIbcMsg::PacketTimeout(timeout),
+                    )?
+                };
+                events.extend(res.events);
+                continue;
+            }
+
+            events.push(packet_event("recv_packet", &packet));
+            let ack = if packet.dest.port_id == TRANSFER_PORT {
+                Self::receive_transfer(api, storage, router, block, &packet)?
+            } else {
+                // a failing contract writes an error acknowledgement, its changes are reverted
+                let res = transactional(storage, |write_cache, _| {
+                    let receive = IbcPacketReceiveMsg::new(packet.clone());
+                    Self::call_contract(
+                        api,
+                        write_cache,
+                        router,
+                        block,
+                        &packet.dest.port_id,
+                        WasmIbcMsg::PacketReceive(receive),
+                    )
+                });
+                match res {
+                    Ok(res) => {
+                        events.extend(res.events);
+                        res.data.unwrap_or_default()
+                    }
+                    Err(err) => to_binary(&Ics20Ack::Error(err.to_string()))?,
+                }
+            };
+            events.push(
+                packet_event("write_acknowledgement", &packet)
+                    .add_attribute("packet_ack", ack.to_base64()),
+            );
+
+            events.push(packet_event("acknowledge_packet", &packet));
+            if packet.src.port_id == TRANSFER_PORT {
+                if let Ics20Ack::Error(_) = from_binary(&ack)? {
+                    let res = Self::refund_transfer(api, storage, router, block, &packet)?;
+                    events.extend(res.events);
+                }
+            } else {
+                let port_id = packet.src.port_id.clone();
+                let ack = IbcPacketAckMsg::new(IbcAcknowledgement::new(ack), packet);
+                let res = Self::call_contract(
+                    api,
+                    storage,
+                    router,
+                    block,
+                    &port_id,
+                    WasmIbcMsg::PacketAck(ack),
+                )?;
+                events.extend(res.events);
+            }
+        }
+        Ok(AppResponse { events, data: None })
+    }
+}
+
+impl Ibc for IbcKeeper {}
+
+impl Module for IbcKeeper {
+    type ExecT = IbcMsg;
+    type QueryT = IbcQuery;
+    type SudoT = IbcSudo;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: IbcMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        match msg {
+            IbcMsg::Transfer {
+                channel_id,
+                to_address,
+                amount,
+                timeout,
+            } => {
+                let info = Self::load_channel(storage, &channel_id)?;
+                if info.channel.endpoint.port_id != TRANSFER_PORT {
+                    bail!("Channel {} is not a transfer channel", channel_id);
+                }
+                Self::transfer(
+                    api, storage, router, block, sender, channel_id, to_address, amount, timeout,
+                )
+            }
+            IbcMsg::SendPacket {
+                channel_id,
+                data,
+                timeout,
+            } => {
+                let port_id = format!("{}{}", CONTRACT_PORT_PREFIX, sender);
+                Self::send_packet(storage, &port_id, &channel_id, data, timeout)
+            }
+            IbcMsg::CloseChannel { channel_id } => {
+                let info = Self::load_channel(storage, &channel_id)?;
+                if info.channel.endpoint.port_id != format!("{}{}", CONTRACT_PORT_PREFIX, sender) {
+                    bail!(
+                        "Channel {} is not bound to the port of {}",
+                        channel_id,
+                        sender
+                    );
+                }
+                Self::close_channel(api, storage, router, block, &channel_id)
+            }
+            m => bail!("Unsupported ibc message: {:?}", m),
+        }
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: IbcSudo,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        match msg {
+            IbcSudo::OpenChannel {
+                src_port,
+                dst_port,
+                order,
+                version,
+                connection_id,
+            } => Self::open_channel(
+                api,
+                storage,
+                router,
+                block,
+                src_port,
+                dst_port,
+                order,
+                version,
+                connection_id,
+            ),
+            IbcSudo::CloseChannel { channel_id } => {
+                Self::close_channel(api, storage, router, block, &channel_id)
+            }
+            IbcSudo::RelayPackets {} => Self::relay_packets(api, storage, router, block),
+        }
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: IbcQuery,
+    ) -> AnyResult<Binary> {
+        let ibc_storage = prefixed_read(storage, NAMESPACE_IBC);
+        match request {
+            IbcQuery::ListChannels { port_id } => {
+                // queries do not know the calling contract, so the port cannot default to it
+                let port_id =
+                    port_id.ok_or_else(|| anyhow!("ListChannels needs the port_id to be set"))?;
+                let channels = CHANNELS
+                    .range(&ibc_storage, None, None, Order::Ascending)
+                    .filter_map(|item| match item {
+                        Ok((_, info)) if info.open && info.channel.endpoint.port_id == port_id => {
+                            Some(Ok(info.channel))
+                        }
+                        Ok(_) => None,
+                        Err(err) => Some(Err(err)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(to_binary(&ListChannelsResponse { channels })?)
+            }
+            IbcQuery::Channel {
+                channel_id,
+                port_id,
+            } => {
+                let channel = CHANNELS
+                    .may_load(&ibc_storage, &channel_id)?
+                    .filter(|info| {
+                        info.open
+                            && match &port_id {
+                                Some(port_id) => &info.channel.endpoint.port_id == port_id,
+                                None => true,
+                            }
+                    })
+                    .map(|info| info.channel);
+                Ok(to_binary(&ChannelResponse { channel })?)
+            }
+            q => bail!(
+                "Unsupported ibc query: {:?}, contracts are bound to port wasm.<contract address>",
+                q
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_helpers::contracts::ping::{self, ExecMsg, State};
+    use crate::test_helpers::EmptyMsg;
+    use crate::{App, Executor};
+    use cosmwasm_std::{coins, IbcTimeoutBlock};
+
+    fn open_channel(
+        app: &mut App,
+        src_port: &str,
+        dst_port: &str,
+        version: &str,
+    ) -> (String, String) {
+        let res = app
+            .sudo(
+                IbcSudo::OpenChannel {
+                    src_port: src_port.to_string(),
+                    dst_port: dst_port.to_string(),
+                    order: IbcOrder::Unordered,
+                    version: version.to_string(),
+                    connection_id: "connection-0".to_string(),
+                }
+                .into(),
+            )
+            .unwrap();
+        from_binary(&res.data.unwrap()).unwrap()
+    }
+
+    fn relay(app: &mut App) -> AppResponse {
+        app.sudo(IbcSudo::RelayPackets {}.into()).unwrap()
+    }
+
+    fn timeout_at(app: &App, height: u64) -> IbcTimeout {
+        IbcTimeout::with_block(IbcTimeoutBlock {
+            revision: 0,
+            height: app.block_info().height + height,
+        })
+    }
+
+    fn ping_state(app: &App, contract: &Addr) -> State {
+        app.wrap().query_wasm_smart(contract, &EmptyMsg {}).unwrap()
+    }
+
+    fn setup_pings(app: &mut App) -> (Addr, Addr, String, String) {
+        let owner = Addr::unchecked("owner");
+        let code_id = app.store_code(ping::contract());
+        let mut instantiate = || {
+            app.instantiate_contract(code_id, owner.clone(), &EmptyMsg {}, &[], "ping", None)
+                .unwrap()
+        };
+        let (a, b) = (instantiate(), instantiate());
+        let (src, dst) = open_channel(
+            app,
+            &format!("wasm.{}", a),
+            &format!("wasm.{}", b),
+            "ping-1",
+        );
+        (a, b, src, dst)
+    }
+
+    #[test]
+    fn contracts_exchange_packets() {
+        let mut app = App::default();
+        let (a, b, src, dst) = setup_pings(&mut app);
+        assert_eq!(ping_state(&app, &a).channels, vec![src.clone()]);
+        assert_eq!(ping_state(&app, &b).channels, vec![dst]);
+
+        let msg = ExecMsg {
+            channel_id: src.clone(),
+            data: "hello".to_string(),
+            timeout: timeout_at(&app, 10),
+        };
+        let res = app
+            .execute_contract(Addr::unchecked("owner"), a.clone(), &msg, &[])
+            .unwrap();
+        assert!(res.has_event(&packet_event(
+            "send_packet",
+            &IbcPacket::new(
+                to_binary("hello").unwrap(),
+                IbcEndpoint {
+                    port_id: format!("wasm.{}", a),
+                    channel_id: src.clone()
+                },
+                IbcEndpoint {
+                    port_id: format!("wasm.{}", b),
+                    channel_id: "channel-1".to_string()
+                },
+                1,
+                timeout_at(&app, 10),
+            )
+        )));
+        // nothing is delivered before relaying
+        assert_eq!(ping_state(&app, &b).received, Vec::<String>::new());
+
+        relay(&mut app);
+        assert_eq!(ping_state(&app, &b).received, vec!["hello".to_string()]);
+        assert_eq!(
+            ping_state(&app, &a).acks,
+            vec![to_binary("pong: hello").unwrap()]
+        );
+        // packets are relayed only once
+        let res = relay(&mut app);
+        assert_eq!(res.events, vec![]);
+
+        // a failing receive is reverted and acknowledged with an error
+        let msg = ExecMsg {
+            channel_id: src,
+            data: "fail".to_string(),
+            timeout: timeout_at(&app, 10),
+        };
+        app.execute_contract(Addr::unchecked("owner"), a.clone(), &msg, &[])
+            .unwrap();
+        relay(&mut app);
+        assert_eq!(ping_state(&app, &b).received, vec!["hello".to_string()]);
+        let acks = ping_state(&app, &a).acks;
+        assert_eq!(acks.len(), 2);
+        match from_binary(&acks[1]).unwrap() {
+            Ics20Ack::Error(err) => assert!(err.contains("Asked to fail"), "{}", err),
+            ack => panic!("Unexpected ack: {:?}", ack),
+        }
+    }
+
+    #[test]
+    fn packets_time_out() {
+        let mut app = App::default();
+        let (a, b, src, _) = setup_pings(&mut app);
+
+        let msg = ExecMsg {
+            channel_id: src.clone(),
+            data: "late".to_string(),
+            timeout: timeout_at(&app, 1),
+        };
+        app.execute_contract(Addr::unchecked("owner"), a.clone(), &msg, &[])
+            .unwrap();
+        app.update_block(crate::next_block);
+        relay(&mut app);
+        assert_eq!(ping_state(&app, &b).received, Vec::<String>::new());
+        assert_eq!(ping_state(&app, &a).timeouts, vec!["late".to_string()]);
+
+        // packets to a closed channel time out as well
+        let msg = ExecMsg {
+            channel_id: src.clone(),
+            data: "closed".to_string(),
+            timeout: timeout_at(&app, 10),
+        };
+        app.execute_contract(Addr::unchecked("owner"), a.clone(), &msg, &[])
+            .unwrap();
+        app.sudo(
+            IbcSudo::CloseChannel {
+                channel_id: "channel-1".to_string(),
+            }
+            .into(),
+        )
+        .unwrap();
+        assert_eq!(ping_state(&app, &a).channels, Vec::<String>::new());
+        assert_eq!(ping_state(&app, &b).channels, Vec::<String>::new());
+        relay(&mut app);
+        assert_eq!(
+            ping_state(&app, &a).timeouts,
+            vec!["late".to_string(), "closed".to_string()]
+        );
+
+        // and nothing can be sent anymore
+        app.execute_contract(Addr::unchecked("owner"), a, &msg, &[])
+            .unwrap_err();
+        let channel: ChannelResponse = app
+            .wrap()
+            .query(
+                &IbcQuery::Channel {
+                    channel_id: src,
+                    port_id: None,
+                }
+                .into(),
+            )
+            .unwrap();
+        assert_eq!(channel.channel, None);
+    }
+
+    #[test]
+    fn contracts_only_send_on_their_channels() {
+        let mut app = App::default();
+        let (a, b, src, dst) = setup_pings(&mut app);
+        let msg = ExecMsg {
+            channel_id: src,
+            data: "hijack".to_string(),
+            timeout: timeout_at(&app, 10),
+        };
+        app.execute_contract(Addr::unchecked("owner"), b.clone(), &msg, &[])
+            .unwrap_err();
+
+        let channels: ListChannelsResponse = app
+            .wrap()
+            .query(
+                &IbcQuery::ListChannels {
+                    port_id: Some(format!("wasm.{}", b)),
+                }
+                .into(),
+            )
+            .unwrap();
+        assert_eq!(channels.channels.len(), 1);
+        assert_eq!(channels.channels[0].endpoint.channel_id, dst);
+        assert_eq!(
+            channels.channels[0].counterparty_endpoint.port_id,
+            format!("wasm.{}", a)
+        );
+    }
+
+    #[test]
+    fn ics20_transfer_round_trip() {
+        let owner = Addr::unchecked("owner");
+        let mut app = App::new(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1000, "uatom"))
+                .unwrap();
+        });
+        let (src, dst) = open_channel(&mut app, TRANSFER_PORT, TRANSFER_PORT, ICS20_VERSION);
+        let voucher = ibc_denom(&format!("transfer/{}/uatom", dst));
+
+        let transfer = IbcMsg::Transfer {
+            channel_id: src.clone(),
+            to_address: "receiver".to_string(),
+            amount: coin(300, "uatom"),
+            timeout: timeout_at(&app, 10),
+        };
+        app.execute(owner.clone(), transfer.into()).unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(&owner, "uatom")
+                .unwrap()
+                .amount
+                .u128(),
+            700
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(escrow_address(&src), "uatom")
+                .unwrap()
+                .amount
+                .u128(),
+            300
+        );
+        relay(&mut app);
+        assert_eq!(
+            app.wrap()
+                .query_balance("receiver", &voucher)
+                .unwrap()
+                .amount
+                .u128(),
+            300
+        );
+        let trace = app.read_module(|router, _, storage| router.ibc.denom_trace(storage, &voucher));
+        assert_eq!(trace.unwrap(), Some(format!("transfer/{}/uatom", dst)));
+
+        // vouchers going back are burned and the escrow released
+        let transfer = IbcMsg::Transfer {
+            channel_id: dst,
+            to_address: owner.to_string(),
+            amount: coin(100, &voucher),
+            timeout: timeout_at(&app, 10),
+        };
+        app.execute(Addr::unchecked("receiver"), transfer.into())
+            .unwrap();
+        relay(&mut app);
+        assert_eq!(
+            app.wrap()
+                .query_balance("receiver", &voucher)
+                .unwrap()
+                .amount
+                .u128(),
+            200
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(&owner, "uatom")
+                .unwrap()
+                .amount
+                .u128(),
+            800
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(escrow_address(&src), "uatom")
+                .unwrap()
+                .amount
+                .u128(),
+            200
+        );
+    }
+
+    #[test]
+    fn failed_ics20_transfers_are_refunded() {
+        let owner = Addr::unchecked("owner");
+        let mut app = App::new(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1000, "uatom"))
+                .unwrap();
+        });
+        let (src, _) = open_channel(&mut app, TRANSFER_PORT, TRANSFER_PORT, ICS20_VERSION);
+
+        // timeout
+        let transfer = IbcMsg::Transfer {
+            channel_id: src.clone(),
+            to_address: "receiver".to_string(),
+            amount: coin(300, "uatom"),
+            timeout: timeout_at(&app, 1),
+        };
+        app.execute(owner.clone(), transfer.into()).unwrap();
+        app.update_block(crate::next_block);
+        relay(&mut app);
+        assert_eq!(
+            app.wrap()
+                .query_balance(&owner, "uatom")
+                .unwrap()
+                .amount
+                .u128(),
+            1000
+        );
+
+        // error acknowledgement, the receiver is no valid address
+        let transfer = IbcMsg::Transfer {
+            channel_id: src,
+            to_address: "x".to_string(),
+            amount: coin(300, "uatom"),
+            timeout: timeout_at(&app, 10),
+        };
+        app.execute(owner.clone(), transfer.into()).unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(&owner, "uatom")
+                .unwrap()
+                .amount
+                .u128(),
+            700
+        );
+        relay(&mut app);
+        assert_eq!(
+            app.wrap()
+                .query_balance(&owner, "uatom")
+                .unwrap()
+                .amount
+                .u128(),
+            1000
+        );
+    }
+
+    #[test]
+    fn transfer_port_only_connects_to_itself() {
+        let mut app = App::default();
+        let err = app
+            .sudo(
+                IbcSudo::OpenChannel {
+                    src_port: TRANSFER_PORT.to_string(),
+                    dst_port: "wasm.contract0".to_string(),
+                    order: IbcOrder::Unordered,
+                    version: ICS20_VERSION.to_string(),
+                    connection_id: "connection-0".to_string(),
+                }
+                .into(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The transfer port can only connect to another transfer port"
+        );
+    }
+}
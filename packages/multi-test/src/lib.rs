@@ -6,6 +6,7 @@
 //!
 //! To understand the design of this module, please refer to `../DESIGN.md`
 
+mod addresses;
 mod app;
 mod bank;
 #[allow(clippy::type_complexity)]
@@ -13,19 +14,49 @@ mod contracts;
 pub mod custom_handler;
 pub mod error;
 mod executor;
+mod gas;
+#[cfg(feature = "stargate")]
+mod gov;
+#[cfg(feature = "stargate")]
+mod ibc;
 mod module;
 mod staking;
+#[cfg(feature = "stargate")]
+mod stargate;
 mod test_helpers;
 mod transactions;
+mod vm;
 mod wasm;
 
+pub use crate::addresses::{
+    AddressGenerator, Bech32AddressGenerator, Bech32Api, SimpleAddressGenerator,
+};
 pub use crate::app::{
     custom_app, next_block, App, AppBuilder, BasicApp, BasicAppBuilder, CosmosRouter, Router,
     SudoMsg,
 };
-pub use crate::bank::{Bank, BankKeeper, BankSudo};
+pub use crate::bank::{Bank, BankKeeper, BankSudo, DenomMetadata, DenomUnit};
 pub use crate::contracts::{Contract, ContractWrapper};
 pub use crate::executor::{AppResponse, Executor};
+pub use crate::gas::{GasConfig, GasMeter};
+#[cfg(feature = "stargate")]
+pub use crate::gov::{
+    FailingGov, Gov, GovKeeper, GovSudo, Proposal, ProposalStatus, TallyResult, WeightedVoteOption,
+};
+#[cfg(feature = "stargate")]
+pub use crate::ibc::{ibc_denom, FailingIbc, Ibc, IbcKeeper, IbcSudo, TRANSFER_PORT};
 pub use crate::module::Module;
-pub use crate::staking::{FailingDistribution, FailingStaking, Staking, StakingSudo};
+pub use crate::staking::{
+    DelegationRewardsResponse, DelegationTotalRewardsResponse, DelegatorReward, Distribution,
+    DistributionKeeper, DistributionQuery, FailingDistribution, FailingStaking, StakeKeeper,
+    Staking, StakingInfo, StakingSudo,
+};
+#[cfg(feature = "stargate")]
+pub use crate::stargate::{
+    FailingStargate, Stargate, StargateExecFn, StargateKeeper, StargateMsg, StargateQuery,
+    StargateQueryFn,
+};
+pub use crate::vm::WasmContract;
 pub use crate::wasm::{Wasm, WasmKeeper, WasmSudo};
+#[cfg(feature = "stargate")]
+pub use crate::wasm::{WasmIbcMsg, WasmIbcSudo};
@@ -1,16 +1,148 @@
-use cosmwasm_std::{Decimal, DistributionMsg, Empty, StakingMsg, StakingQuery};
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, bail, Result as AnyResult};
 use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{
+    coin, to_binary, Addr, AllDelegationsResponse, AllValidatorsResponse, Api, BankMsg, Binary,
+    BlockInfo, BondedDenomResponse, Coin, CustomQuery, Decimal, Delegation, DelegationResponse,
+    DistributionMsg, Empty, Event, FullDelegation, Order, Querier, StakingMsg, StakingQuery,
+    Storage, Timestamp, Uint128, Validator, ValidatorResponse,
+};
+use cosmwasm_storage::{prefixed, prefixed_read};
+use cw_storage_plus::{Item, Map};
+use serde::de::DeserializeOwned;
 
+use crate::app::CosmosRouter;
+use crate::bank::BankSudo;
+use crate::executor::AppResponse;
 use crate::module::FailingModule;
 use crate::Module;
 
-// We need to expand on this, but we will need this to properly test out staking
+/// General parameters of the staking module
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingInfo {
+    /// The denom of the staking token
+    pub bonded_denom: String,
+    /// Time between unbonding and receiving tokens, in seconds
+    pub unbonding_time: u64,
+    /// Interest rate per year (60 * 60 * 24 * 365 seconds)
+    pub apr: Decimal,
+    /// Maximum number of pending unbondings per (delegator, validator) pair and of pending
+    /// redelegations per (delegator, source, destination) triple
+    pub max_entries: u32,
+}
+
+impl Default for StakingInfo {
+    fn default() -> Self {
+        StakingInfo {
+            bonded_denom: "TOKEN".to_string(),
+            unbonding_time: 60,
+            apr: Decimal::percent(10),
+            max_entries: 7,
+        }
+    }
+}
+
+/// Stake a delegator has with a single validator, and the rewards it earned so far
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+struct Shares {
+    stake: Uint128,
+    rewards: Decimal,
+}
+
+/// Operational data about a validator
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct ValidatorInfo {
+    /// Delegators currently staking with this validator
+    stakers: BTreeSet<Addr>,
+    /// Sum of the stake of all stakers
+    stake: Uint128,
+    /// Block time rewards were last distributed to the stakers
+    prev_rewards_calculation: Timestamp,
+    /// Jailed validators earn no rewards and are not listed in `AllValidators`
+    jailed: bool,
+}
+
+impl ValidatorInfo {
+    fn new(block_time: Timestamp) -> Self {
+        ValidatorInfo {
+            stakers: BTreeSet::new(),
+            stake: Uint128::zero(),
+            prev_rewards_calculation: block_time,
+            jailed: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Unbonding {
+    delegator: Addr,
+    validator: String,
+    amount: Uint128,
+    payout_at: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Redelegation {
+    delegator: Addr,
+    src_validator: String,
+    dst_validator: String,
+    amount: Uint128,
+    completion_time: Timestamp,
+}
+
+const STAKING_INFO: Item<StakingInfo> = Item::new("staking_info");
+/// (delegator, validator) -> shares
+const STAKES: Map<(&Addr, &str), Shares> = Map::new("stakes");
+const VALIDATOR_MAP: Map<&str, Validator> = Map::new("validator_map");
+const VALIDATOR_INFO: Map<&str, ValidatorInfo> = Map::new("validator_info");
+const UNBONDING_QUEUE: Item<Vec<Unbonding>> = Item::new("unbonding_queue");
+const REDELEGATIONS: Item<Vec<Redelegation>> = Item::new("redelegations");
+/// Where a delegator's rewards are sent, if not to the delegator itself
+const WITHDRAW_ADDRESS: Map<&Addr, Addr> = Map::new("withdraw_address");
+
+pub const NAMESPACE_STAKING: &[u8] = b"staking";
+pub const NAMESPACE_DISTRIBUTION: &[u8] = b"distribution";
+
+const YEAR: u64 = 60 * 60 * 24 * 365;
+
 #[derive(Clone, std::fmt::Debug, PartialEq, JsonSchema)]
 pub enum StakingSudo {
+    /// Slashes the given percentage of the validator's stake, including tokens
+    /// still unbonding or redelegated away from it.
     Slash {
         validator: String,
         percentage: Decimal,
     },
+    /// Adds a validator available for delegations
+    AddValidator {
+        validator: Validator,
+    },
+    /// Removes a validator nobody is bonded to anymore.
+    /// Rewards not withdrawn from it are forfeited.
+    RemoveValidator {
+        validator: String,
+    },
+    /// Stops the validator from earning rewards and hides it from `AllValidators`
+    Jail {
+        validator: String,
+    },
+    Unjail {
+        validator: String,
+    },
+    /// Sets the validator's commission, up to its `max_commission`
+    ChangeCommission {
+        validator: String,
+        commission: Decimal,
+    },
+    /// Replaces the staking parameters. Pending unbondings keep their completion time.
+    SetParams {
+        params: StakingInfo,
+    },
+    /// Pays out all unbondings that completed by the current block time
+    ProcessQueue {},
 }
 
 pub trait Staking: Module<ExecT = StakingMsg, QueryT = StakingQuery, SudoT = StakingSudo> {}
@@ -19,8 +151,1405 @@ pub type FailingStaking = FailingModule<StakingMsg, StakingQuery, StakingSudo>;
 
 impl Staking for FailingStaking {}
 
-pub trait Distribution: Module<ExecT = DistributionMsg, QueryT = Empty, SudoT = Empty> {}
+/// Queries of the distribution module. cosmwasm-std has no `QueryRequest` variant for them,
+/// so they are only available to tests, through `App::query_distribution`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionQuery {
+    /// Rewards the delegator earned on the validator and did not withdraw yet.
+    /// Returns `DelegationRewardsResponse`.
+    DelegationRewards {
+        delegator: String,
+        validator: String,
+    },
+    /// Rewards the delegator earned on each of its validators.
+    /// Returns `DelegationTotalRewardsResponse`.
+    DelegationTotalRewards { delegator: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegationRewardsResponse {
+    pub rewards: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegatorReward {
+    pub validator: String,
+    pub reward: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegationTotalRewardsResponse {
+    pub rewards: Vec<DelegatorReward>,
+    pub total: Vec<Coin>,
+}
 
-pub type FailingDistribution = FailingModule<DistributionMsg, Empty, Empty>;
+pub trait Distribution:
+    Module<ExecT = DistributionMsg, QueryT = DistributionQuery, SudoT = Empty>
+{
+}
+
+pub type FailingDistribution = FailingModule<DistributionMsg, DistributionQuery, Empty>;
 
 impl Distribution for FailingDistribution {}
+
+pub struct StakeKeeper {
+    /// Account holding all bonded and unbonding tokens
+    module_addr: Addr,
+}
+
+impl Default for StakeKeeper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StakeKeeper {
+    pub fn new() -> Self {
+        StakeKeeper {
+            module_addr: Addr::unchecked("staking_module"),
+        }
+    }
+
+    // this is an "admin" function to set the staking parameters in genesis
+    pub fn setup(&self, storage: &mut dyn Storage, staking_info: StakingInfo) -> AnyResult<()> {
+        let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+        STAKING_INFO.save(&mut staking_storage, &staking_info)?;
+        Ok(())
+    }
+
+    // this is an "admin" function to add validators available for delegations in genesis
+    pub fn add_validator(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        validator: Validator,
+    ) -> AnyResult<()> {
+        let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+        if validator.commission > validator.max_commission {
+            bail!("Commission cannot be more than the max commission");
+        }
+        if VALIDATOR_MAP.has(&staking_storage, &validator.address) {
+            bail!(
+                "Cannot add validator {}, since a validator with that address already exists",
+                validator.address
+            );
+        }
+        VALIDATOR_INFO.save(
+            &mut staking_storage,
+            &validator.address,
+            &ValidatorInfo::new(block.time),
+        )?;
+        VALIDATOR_MAP.save(&mut staking_storage, &validator.address, &validator)?;
+        Ok(())
+    }
+
+    /// Pays out all unbondings completed by `block.time` and forgets completed redelegations.
+    /// This runs before every staking message, call it directly to complete unbondings
+    /// without sending one.
+    pub fn process_queue<ExecC, QueryC: CustomQuery>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+    ) -> AnyResult<AppResponse> {
+        let (denom, matured) = {
+            let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+            let (matured, pending): (Vec<_>, Vec<_>) = UNBONDING_QUEUE
+                .may_load(&staking_storage)?
+                .unwrap_or_default()
+                .into_iter()
+                .partition(|u| u.payout_at <= block.time);
+            UNBONDING_QUEUE.save(&mut staking_storage, &pending)?;
+
+            let mut redelegations = REDELEGATIONS
+                .may_load(&staking_storage)?
+                .unwrap_or_default();
+            redelegations.retain(|r| r.completion_time > block.time);
+            REDELEGATIONS.save(&mut staking_storage, &redelegations)?;
+
+            let denom = Self::get_staking_info(&staking_storage)?.bonded_denom;
+            (denom, matured)
+        };
+
+        let mut events = vec![];
+        for unbonding in matured {
+            // fully slashed unbondings have nothing left to pay out
+            if unbonding.amount.is_zero() {
+                continue;
+            }
+            let amount = coin(unbonding.amount.u128(), &denom);
+            events.push(
+                Event::new("complete_unbonding")
+                    .add_attribute("validator", &unbonding.validator)
+                    .add_attribute("delegator", &unbonding.delegator)
+                    .add_attribute("amount", format!("{}{}", amount.amount, amount.denom)),
+            );
+            router.execute(
+                api,
+                storage,
+                block,
+                self.module_addr.clone(),
+                BankMsg::Send {
+                    to_address: unbonding.delegator.into(),
+                    amount: vec![amount],
+                }
+                .into(),
+            )?;
+        }
+        Ok(AppResponse { events, data: None })
+    }
+
+    fn get_staking_info(staking_storage: &dyn Storage) -> AnyResult<StakingInfo> {
+        Ok(STAKING_INFO.may_load(staking_storage)?.unwrap_or_default())
+    }
+
+    fn get_validator(staking_storage: &dyn Storage, validator: &str) -> AnyResult<Validator> {
+        VALIDATOR_MAP
+            .may_load(staking_storage, validator)?
+            .ok_or_else(|| anyhow!("Validator {} not found", validator))
+    }
+
+    /// Rewards earned by `stake` between `since` and `current_time`, after validator commission
+    fn calculate_rewards(
+        current_time: Timestamp,
+        since: Timestamp,
+        stake: Uint128,
+        apr: Decimal,
+        commission: Decimal,
+    ) -> Decimal {
+        let elapsed = current_time.seconds().saturating_sub(since.seconds());
+        Decimal::from_ratio(stake.u128() * elapsed as u128, YEAR)
+            * apr
+            * (Decimal::one() - commission)
+    }
+
+    /// Distributes the rewards a validator earned since the last calculation to its stakers
+    fn update_rewards(
+        staking_storage: &mut dyn Storage,
+        block: &BlockInfo,
+        validator: &str,
+    ) -> AnyResult<()> {
+        let staking_info = Self::get_staking_info(staking_storage)?;
+        let validator_obj = Self::get_validator(staking_storage, validator)?;
+        let mut info = VALIDATOR_INFO.load(staking_storage, validator)?;
+        if info.prev_rewards_calculation >= block.time {
+            return Ok(());
+        }
+
+        let new_rewards = if info.jailed {
+            Decimal::zero()
+        } else {
+            Self::calculate_rewards(
+                block.time,
+                info.prev_rewards_calculation,
+                info.stake,
+                staking_info.apr,
+                validator_obj.commission,
+            )
+        };
+        info.prev_rewards_calculation = block.time;
+        VALIDATOR_INFO.save(staking_storage, validator, &info)?;
+        if new_rewards.is_zero() {
+            return Ok(());
+        }
+
+        for staker in info.stakers.iter() {
+            STAKES.update(
+                staking_storage,
+                (staker, validator),
+                |shares| -> AnyResult<_> {
+                    let mut shares = shares.ok_or_else(|| anyhow!("Staker has no shares"))?;
+                    shares.rewards += new_rewards * Decimal::from_ratio(shares.stake, info.stake);
+                    Ok(shares)
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Rewards of the delegator on the validator, including the ones not distributed yet
+    fn get_rewards(
+        staking_storage: &dyn Storage,
+        block: &BlockInfo,
+        delegator: &Addr,
+        validator: &str,
+    ) -> AnyResult<Option<Coin>> {
+        let shares = match STAKES.may_load(staking_storage, (delegator, validator))? {
+            Some(shares) => shares,
+            None => return Ok(None),
+        };
+        let staking_info = Self::get_staking_info(staking_storage)?;
+        let validator_obj = Self::get_validator(staking_storage, validator)?;
+        let info = VALIDATOR_INFO.load(staking_storage, validator)?;
+
+        let pending = if info.stake.is_zero() || info.jailed {
+            Decimal::zero()
+        } else {
+            Self::calculate_rewards(
+                block.time,
+                info.prev_rewards_calculation,
+                info.stake,
+                staking_info.apr,
+                validator_obj.commission,
+            ) * Decimal::from_ratio(shares.stake, info.stake)
+        };
+        let amount = Uint128::new(1) * (shares.rewards + pending);
+        Ok(Some(coin(amount.u128(), staking_info.bonded_denom)))
+    }
+
+    fn add_stake(
+        staking_storage: &mut dyn Storage,
+        block: &BlockInfo,
+        delegator: &Addr,
+        validator: &str,
+        amount: Uint128,
+    ) -> AnyResult<()> {
+        Self::update_rewards(staking_storage, block, validator)?;
+
+        let mut shares = STAKES
+            .may_load(staking_storage, (delegator, validator))?
+            .unwrap_or_default();
+        shares.stake += amount;
+        STAKES.save(staking_storage, (delegator, validator), &shares)?;
+
+        let mut info = VALIDATOR_INFO.load(staking_storage, validator)?;
+        info.stake += amount;
+        info.stakers.insert(delegator.clone());
+        VALIDATOR_INFO.save(staking_storage, validator, &info)?;
+        Ok(())
+    }
+
+    fn remove_stake(
+        staking_storage: &mut dyn Storage,
+        block: &BlockInfo,
+        delegator: &Addr,
+        validator: &str,
+        amount: Uint128,
+    ) -> AnyResult<()> {
+        Self::update_rewards(staking_storage, block, validator)?;
+
+        let mut shares = STAKES
+            .may_load(staking_storage, (delegator, validator))?
+            .filter(|shares| shares.stake >= amount)
+            .ok_or_else(|| anyhow!("Invalid shares amount"))?;
+        shares.stake -= amount;
+        STAKES.save(staking_storage, (delegator, validator), &shares)?;
+
+        let mut info = VALIDATOR_INFO.load(staking_storage, validator)?;
+        info.stake -= amount;
+        // rewards stay with the shares until withdrawn
+        if shares.stake.is_zero() {
+            info.stakers.remove(delegator);
+        }
+        VALIDATOR_INFO.save(staking_storage, validator, &info)?;
+        Ok(())
+    }
+
+    fn validate_amount(staking_info: &StakingInfo, amount: &Coin) -> AnyResult<()> {
+        if amount.amount.is_zero() {
+            bail!("Cannot delegate, undelegate or redelegate zero tokens");
+        }
+        if amount.denom != staking_info.bonded_denom {
+            bail!(
+                "Cannot stake {}, only {} can be staked",
+                amount.denom,
+                staking_info.bonded_denom
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether tokens redelegated to `validator` by `delegator` are still locked there
+    fn has_receiving_redelegation(
+        staking_storage: &dyn Storage,
+        block: &BlockInfo,
+        delegator: &Addr,
+        validator: &str,
+    ) -> AnyResult<bool> {
+        Ok(REDELEGATIONS
+            .may_load(staking_storage)?
+            .unwrap_or_default()
+            .iter()
+            .any(|r| {
+                &r.delegator == delegator
+                    && r.dst_validator == validator
+                    && r.completion_time > block.time
+            }))
+    }
+
+    /// Slashes the validator's stakers, its unbondings and what was redelegated away from it.
+    /// Returns the slashed amount.
+    fn slash(
+        staking_storage: &mut dyn Storage,
+        block: &BlockInfo,
+        validator: &str,
+        percentage: Decimal,
+    ) -> AnyResult<Uint128> {
+        if percentage > Decimal::one() {
+            bail!("Slash percentage must be between 0 and 1");
+        }
+        Self::update_rewards(staking_storage, block, validator)?;
+
+        let mut info = VALIDATOR_INFO.load(staking_storage, validator)?;
+        let mut slashed_stake = Uint128::zero();
+        for staker in info.stakers.iter() {
+            let mut shares = STAKES.load(staking_storage, (staker, validator))?;
+            let amount = shares.stake * percentage;
+            shares.stake -= amount;
+            slashed_stake += amount;
+            STAKES.save(staking_storage, (staker, validator), &shares)?;
+        }
+        info.stake -= slashed_stake;
+        VALIDATOR_INFO.save(staking_storage, validator, &info)?;
+        let mut slashed = slashed_stake;
+
+        let mut queue = UNBONDING_QUEUE
+            .may_load(staking_storage)?
+            .unwrap_or_default();
+        for unbonding in queue.iter_mut().filter(|u| u.validator == validator) {
+            let amount = unbonding.amount * percentage;
+            unbonding.amount -= amount;
+            slashed += amount;
+        }
+        UNBONDING_QUEUE.save(staking_storage, &queue)?;
+
+        // tokens redelegated away are slashed at their destination
+        let redelegations = REDELEGATIONS.may_load(staking_storage)?.unwrap_or_default();
+        for r in redelegations
+            .iter()
+            .filter(|r| r.src_validator == validator && r.completion_time > block.time)
+        {
+            let stake = STAKES
+                .may_load(staking_storage, (&r.delegator, &r.dst_validator))?
+                .unwrap_or_default()
+                .stake;
+            let amount = (r.amount * percentage).min(stake);
+            if amount.is_zero() {
+                continue;
+            }
+            Self::remove_stake(
+                staking_storage,
+                block,
+                &r.delegator,
+                &r.dst_validator,
+                amount,
+            )?;
+            slashed += amount;
+        }
+        Ok(slashed)
+    }
+
+    fn remove_validator(staking_storage: &mut dyn Storage, validator: &str) -> AnyResult<()> {
+        let info = VALIDATOR_INFO
+            .may_load(staking_storage, validator)?
+            .ok_or_else(|| anyhow!("Validator {} not found", validator))?;
+        if !info.stake.is_zero() {
+            bail!(
+                "Cannot remove validator {} while tokens are bonded to it",
+                validator
+            );
+        }
+        let delegators = STAKES
+            .keys(staking_storage, None, None, Order::Ascending)
+            .filter(|key| !matches!(key, Ok((_, val)) if val != validator))
+            .map(|key| key.map(|(delegator, _)| delegator))
+            .collect::<Result<Vec<_>, _>>()?;
+        for delegator in delegators {
+            STAKES.remove(staking_storage, (&delegator, validator));
+        }
+        VALIDATOR_INFO.remove(staking_storage, validator);
+        VALIDATOR_MAP.remove(staking_storage, validator);
+        Ok(())
+    }
+
+    fn set_jailed(
+        staking_storage: &mut dyn Storage,
+        block: &BlockInfo,
+        validator: &str,
+        jailed: bool,
+    ) -> AnyResult<()> {
+        Self::update_rewards(staking_storage, block, validator)?;
+        let mut info = VALIDATOR_INFO.load(staking_storage, validator)?;
+        if info.jailed == jailed {
+            bail!(
+                "Validator {} is {}",
+                validator,
+                if jailed {
+                    "already jailed"
+                } else {
+                    "not jailed"
+                }
+            );
+        }
+        info.jailed = jailed;
+        VALIDATOR_INFO.save(staking_storage, validator, &info)?;
+        Ok(())
+    }
+
+    fn change_commission(
+        staking_storage: &mut dyn Storage,
+        block: &BlockInfo,
+        validator: &str,
+        commission: Decimal,
+    ) -> AnyResult<()> {
+        let mut validator_obj = Self::get_validator(staking_storage, validator)?;
+        if commission > validator_obj.max_commission {
+            bail!("Commission cannot be more than the max commission");
+        }
+        // rewards until now are earned with the old commission
+        Self::update_rewards(staking_storage, block, validator)?;
+        validator_obj.commission = commission;
+        VALIDATOR_MAP.save(staking_storage, validator, &validator_obj)?;
+        Ok(())
+    }
+
+    fn full_delegation(
+        staking_storage: &dyn Storage,
+        block: &BlockInfo,
+        delegator: Addr,
+        validator: String,
+    ) -> AnyResult<Option<FullDelegation>> {
+        let staking_info = Self::get_staking_info(staking_storage)?;
+        let shares = match STAKES.may_load(staking_storage, (&delegator, &validator))? {
+            Some(shares) if !shares.stake.is_zero() => shares,
+            _ => return Ok(None),
+        };
+        let can_redelegate =
+            if Self::has_receiving_redelegation(staking_storage, block, &delegator, &validator)? {
+                Uint128::zero()
+            } else {
+                shares.stake
+            };
+        let accumulated_rewards =
+            Self::get_rewards(staking_storage, block, &delegator, &validator)?
+                .into_iter()
+                .collect();
+        Ok(Some(FullDelegation {
+            delegator,
+            validator,
+            amount: coin(shares.stake.u128(), &staking_info.bonded_denom),
+            can_redelegate: coin(can_redelegate.u128(), staking_info.bonded_denom),
+            accumulated_rewards,
+        }))
+    }
+}
+
+impl Staking for StakeKeeper {}
+
+impl Module for StakeKeeper {
+    type ExecT = StakingMsg;
+    type QueryT = StakingQuery;
+    type SudoT = StakingSudo;
+
+    fn execute<ExecC, QueryC: CustomQuery>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: StakingMsg,
+    ) -> AnyResult<AppResponse> {
+        let mut events = self.process_queue(api, storage, router, block)?.events;
+        let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+        let staking_info = Self::get_staking_info(&staking_storage)?;
+        match msg {
+            StakingMsg::Delegate { validator, amount } => {
+                Self::validate_amount(&staking_info, &amount)?;
+                Self::get_validator(&staking_storage, &validator)?;
+                // see https://github.com/cosmos/cosmos-sdk/blob/v0.45.4/x/staking/keeper/msg_server.go#L227-L233
+                events.push(
+                    Event::new("delegate")
+                        .add_attribute("validator", &validator)
+                        .add_attribute("amount", format!("{}{}", amount.amount, amount.denom))
+                        .add_attribute("new_shares", amount.amount.to_string()),
+                );
+                Self::add_stake(
+                    &mut staking_storage,
+                    block,
+                    &sender,
+                    &validator,
+                    amount.amount,
+                )?;
+                // move the tokens from the delegator to the module account
+                router.execute(
+                    api,
+                    storage,
+                    block,
+                    sender,
+                    BankMsg::Send {
+                        to_address: self.module_addr.to_string(),
+                        amount: vec![amount],
+                    }
+                    .into(),
+                )?;
+                Ok(AppResponse { events, data: None })
+            }
+            StakingMsg::Undelegate { validator, amount } => {
+                Self::validate_amount(&staking_info, &amount)?;
+                let mut queue = UNBONDING_QUEUE
+                    .may_load(&staking_storage)?
+                    .unwrap_or_default();
+                let entries = queue
+                    .iter()
+                    .filter(|u| u.delegator == sender && u.validator == validator)
+                    .count();
+                if entries >= staking_info.max_entries as usize {
+                    bail!("Too many unbonding delegation entries for (delegator, validator) tuple");
+                }
+                Self::remove_stake(
+                    &mut staking_storage,
+                    block,
+                    &sender,
+                    &validator,
+                    amount.amount,
+                )?;
+                let payout_at = block.time.plus_seconds(staking_info.unbonding_time);
+                queue.push(Unbonding {
+                    delegator: sender,
+                    validator: validator.clone(),
+                    amount: amount.amount,
+                    payout_at,
+                });
+                UNBONDING_QUEUE.save(&mut staking_storage, &queue)?;
+                // see https://github.com/cosmos/cosmos-sdk/blob/v0.45.4/x/staking/keeper/msg_server.go#L378-L383
+                events.push(
+                    Event::new("unbond")
+                        .add_attribute("validator", &validator)
+                        .add_attribute("amount", format!("{}{}", amount.amount, amount.denom))
+                        .add_attribute("completion_time", payout_at.seconds().to_string()),
+                );
+                Ok(AppResponse { events, data: None })
+            }
+            StakingMsg::Redelegate {
+                src_validator,
+                dst_validator,
+                amount,
+            } => {
+                Self::validate_amount(&staking_info, &amount)?;
+                Self::get_validator(&staking_storage, &dst_validator)?;
+                if src_validator == dst_validator {
+                    bail!("Cannot redelegate to the same validator");
+                }
+                if Self::has_receiving_redelegation(
+                    &staking_storage,
+                    block,
+                    &sender,
+                    &src_validator,
+                )? {
+                    bail!("Redelegation to this validator already in progress; first redelegation to this validator must complete before next redelegation");
+                }
+                let mut redelegations = REDELEGATIONS
+                    .may_load(&staking_storage)?
+                    .unwrap_or_default();
+                let entries = redelegations
+                    .iter()
+                    .filter(|r| {
+                        r.delegator == sender
+                            && r.src_validator == src_validator
+                            && r.dst_validator == dst_validator
+                    })
+                    .count();
+                if entries >= staking_info.max_entries as usize {
+                    bail!("Too many redelegation entries for (delegator, src-validator, dst-validator) tuple");
+                }
+                Self::remove_stake(
+                    &mut staking_storage,
+                    block,
+                    &sender,
+                    &src_validator,
+                    amount.amount,
+                )?;
+                Self::add_stake(
+                    &mut staking_storage,
+                    block,
+                    &sender,
+                    &dst_validator,
+                    amount.amount,
+                )?;
+
+                let completion_time = block.time.plus_seconds(staking_info.unbonding_time);
+                redelegations.push(Redelegation {
+                    delegator: sender,
+                    src_validator: src_validator.clone(),
+                    dst_validator: dst_validator.clone(),
+                    amount: amount.amount,
+                    completion_time,
+                });
+                REDELEGATIONS.save(&mut staking_storage, &redelegations)?;
+                // see https://github.com/cosmos/cosmos-sdk/blob/v0.45.4/x/staking/keeper/msg_server.go#L316-L322
+                events.push(
+                    Event::new("redelegate")
+                        .add_attribute("source_validator", &src_validator)
+                        .add_attribute("destination_validator", &dst_validator)
+                        .add_attribute("amount", format!("{}{}", amount.amount, amount.denom))
+                        .add_attribute("completion_time", completion_time.seconds().to_string()),
+                );
+                Ok(AppResponse { events, data: None })
+            }
+            m => bail!("Unsupported staking message: {:?}", m),
+        }
+    }
+
+    fn sudo<ExecC, QueryC: CustomQuery>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: StakingSudo,
+    ) -> AnyResult<AppResponse> {
+        let events = self.process_queue(api, storage, router, block)?.events;
+        match msg {
+            StakingSudo::Slash {
+                validator,
+                percentage,
+            } => {
+                let (slashed, denom) = {
+                    let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+                    let slashed = Self::slash(&mut staking_storage, block, &validator, percentage)?;
+                    (
+                        slashed,
+                        Self::get_staking_info(&staking_storage)?.bonded_denom,
+                    )
+                };
+                // slashed tokens are burned from the module account
+                if !slashed.is_zero() {
+                    router.execute(
+                        api,
+                        storage,
+                        block,
+                        self.module_addr.clone(),
+                        BankMsg::Burn {
+                            amount: vec![coin(slashed.u128(), denom)],
+                        }
+                        .into(),
+                    )?;
+                }
+                Ok(AppResponse { events, data: None })
+            }
+            StakingSudo::AddValidator { validator } => {
+                self.add_validator(storage, block, validator)?;
+                Ok(AppResponse { events, data: None })
+            }
+            StakingSudo::RemoveValidator { validator } => {
+                let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+                Self::remove_validator(&mut staking_storage, &validator)?;
+                Ok(AppResponse { events, data: None })
+            }
+            StakingSudo::Jail { validator } => {
+                let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+                Self::set_jailed(&mut staking_storage, block, &validator, true)?;
+                Ok(AppResponse { events, data: None })
+            }
+            StakingSudo::Unjail { validator } => {
+                let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+                Self::set_jailed(&mut staking_storage, block, &validator, false)?;
+                Ok(AppResponse { events, data: None })
+            }
+            StakingSudo::ChangeCommission {
+                validator,
+                commission,
+            } => {
+                let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+                Self::change_commission(&mut staking_storage, block, &validator, commission)?;
+                Ok(AppResponse { events, data: None })
+            }
+            StakingSudo::SetParams { params } => {
+                let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+                // rewards until now are earned under the old parameters
+                let validators = VALIDATOR_MAP
+                    .keys(&staking_storage, None, None, Order::Ascending)
+                    .collect::<Result<Vec<_>, _>>()?;
+                for validator in validators {
+                    Self::update_rewards(&mut staking_storage, block, &validator)?;
+                }
+                STAKING_INFO.save(&mut staking_storage, &params)?;
+                Ok(AppResponse { events, data: None })
+            }
+            // matured unbondings were just paid out above
+            StakingSudo::ProcessQueue {} => Ok(AppResponse { events, data: None }),
+        }
+    }
+
+    fn query(
+        &self,
+        api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        block: &BlockInfo,
+        request: StakingQuery,
+    ) -> AnyResult<Binary> {
+        let staking_storage = prefixed_read(storage, NAMESPACE_STAKING);
+        match request {
+            StakingQuery::BondedDenom {} => {
+                let denom = Self::get_staking_info(&staking_storage)?.bonded_denom;
+                Ok(to_binary(&BondedDenomResponse { denom })?)
+            }
+            StakingQuery::AllDelegations { delegator } => {
+                let delegator = api.addr_validate(&delegator)?;
+                let validators = STAKES
+                    .prefix(&delegator)
+                    .keys(&staking_storage, None, None, Order::Ascending)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut delegations = vec![];
+                for validator in validators {
+                    if let Some(delegation) = Self::full_delegation(
+                        &staking_storage,
+                        block,
+                        delegator.clone(),
+                        validator,
+                    )? {
+                        delegations.push(Delegation::from(delegation));
+                    }
+                }
+                Ok(to_binary(&AllDelegationsResponse { delegations })?)
+            }
+            StakingQuery::Delegation {
+                delegator,
+                validator,
+            } => {
+                let delegator = api.addr_validate(&delegator)?;
+                let delegation =
+                    Self::full_delegation(&staking_storage, block, delegator, validator)?;
+                Ok(to_binary(&DelegationResponse { delegation })?)
+            }
+            StakingQuery::AllValidators {} => {
+                // like the chain, only list validators in the active set
+                let mut validators = vec![];
+                for item in VALIDATOR_MAP.range(&staking_storage, None, None, Order::Ascending) {
+                    let (address, validator) = item?;
+                    if !VALIDATOR_INFO.load(&staking_storage, &address)?.jailed {
+                        validators.push(validator);
+                    }
+                }
+                Ok(to_binary(&AllValidatorsResponse { validators })?)
+            }
+            StakingQuery::Validator { address } => {
+                let validator = VALIDATOR_MAP.may_load(&staking_storage, &address)?;
+                Ok(to_binary(&ValidatorResponse { validator })?)
+            }
+            q => bail!("Unsupported staking query: {:?}", q),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DistributionKeeper {}
+
+impl DistributionKeeper {
+    pub fn new() -> Self {
+        DistributionKeeper {}
+    }
+
+    /// Takes the whole tokens out of the rewards the delegator earned on the validator
+    fn remove_rewards(
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        delegator: &Addr,
+        validator: &str,
+    ) -> AnyResult<Coin> {
+        let mut staking_storage = prefixed(storage, NAMESPACE_STAKING);
+        StakeKeeper::update_rewards(&mut staking_storage, block, validator)?;
+        let mut shares = STAKES
+            .may_load(&staking_storage, (delegator, validator))?
+            .ok_or_else(|| anyhow!("No delegation for ({}, {})", delegator, validator))?;
+        let amount = Uint128::new(1) * shares.rewards;
+        shares.rewards -= Decimal::from_ratio(amount, 1u128);
+        STAKES.save(&mut staking_storage, (delegator, validator), &shares)?;
+
+        let denom = StakeKeeper::get_staking_info(&staking_storage)?.bonded_denom;
+        Ok(coin(amount.u128(), denom))
+    }
+}
+
+impl Distribution for DistributionKeeper {}
+
+impl Module for DistributionKeeper {
+    type ExecT = DistributionMsg;
+    type QueryT = DistributionQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: DistributionMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        match msg {
+            DistributionMsg::WithdrawDelegatorReward { validator } => {
+                let rewards = Self::remove_rewards(storage, block, &sender, &validator)?;
+                let recipient = WITHDRAW_ADDRESS
+                    .may_load(&prefixed_read(storage, NAMESPACE_DISTRIBUTION), &sender)?
+                    .unwrap_or_else(|| sender.clone());
+                // see https://github.com/cosmos/cosmos-sdk/blob/v0.45.4/x/distribution/keeper/keeper.go#L90-L95
+                let events = vec![Event::new("withdraw_delegator_reward")
+                    .add_attribute("validator", &validator)
+                    .add_attribute("sender", &sender)
+                    .add_attribute("amount", format!("{}{}", rewards.amount, rewards.denom))];
+                // rewards are minted, there is no fee pool to take them from
+                if !rewards.amount.is_zero() {
+                    router.sudo(
+                        api,
+                        storage,
+                        block,
+                        BankSudo::Mint {
+                            to_address: recipient.into(),
+                            amount: vec![rewards],
+                        }
+                        .into(),
+                    )?;
+                }
+                Ok(AppResponse { events, data: None })
+            }
+            DistributionMsg::SetWithdrawAddress { address } => {
+                let address = api.addr_validate(&address)?;
+                let mut distribution_storage = prefixed(storage, NAMESPACE_DISTRIBUTION);
+                WITHDRAW_ADDRESS.save(&mut distribution_storage, &sender, &address)?;
+                // see https://github.com/cosmos/cosmos-sdk/blob/v0.45.4/x/distribution/keeper/keeper.go#L74
+                let events =
+                    vec![Event::new("set_withdraw_address")
+                        .add_attribute("withdraw_address", &address)];
+                Ok(AppResponse { events, data: None })
+            }
+            m => bail!("Unsupported distribution message: {:?}", m),
+        }
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Empty,
+    ) -> AnyResult<AppResponse> {
+        bail!("Unexpected sudo msg {:?}", msg)
+    }
+
+    fn query(
+        &self,
+        api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        block: &BlockInfo,
+        request: DistributionQuery,
+    ) -> AnyResult<Binary> {
+        let staking_storage = prefixed_read(storage, NAMESPACE_STAKING);
+        match request {
+            DistributionQuery::DelegationRewards {
+                delegator,
+                validator,
+            } => {
+                let delegator = api.addr_validate(&delegator)?;
+                let rewards =
+                    StakeKeeper::get_rewards(&staking_storage, block, &delegator, &validator)?
+                        .into_iter()
+                        .collect();
+                Ok(to_binary(&DelegationRewardsResponse { rewards })?)
+            }
+            DistributionQuery::DelegationTotalRewards { delegator } => {
+                let delegator = api.addr_validate(&delegator)?;
+                let validators = STAKES
+                    .prefix(&delegator)
+                    .keys(&staking_storage, None, None, Order::Ascending)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut rewards = vec![];
+                let mut total = Uint128::zero();
+                let mut denom = None;
+                for validator in validators {
+                    let reward =
+                        StakeKeeper::get_rewards(&staking_storage, block, &delegator, &validator)?;
+                    if let Some(reward) = &reward {
+                        total += reward.amount;
+                        denom = Some(reward.denom.clone());
+                    }
+                    rewards.push(DelegatorReward {
+                        validator,
+                        reward: reward.into_iter().collect(),
+                    });
+                }
+                // all rewards are paid in the bonded denom
+                let total = denom
+                    .map(|denom| coin(total.u128(), denom))
+                    .into_iter()
+                    .collect();
+                Ok(to_binary(&DelegationTotalRewardsResponse {
+                    rewards,
+                    total,
+                })?)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{App, AppBuilder, Executor};
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::{coins, CosmosMsg};
+
+    const VALIDATOR: &str = "testvaloper1";
+    const VALIDATOR2: &str = "testvaloper2";
+
+    fn validator(address: &str, commission: Decimal) -> Validator {
+        Validator {
+            address: address.to_string(),
+            commission,
+            max_commission: Decimal::one(),
+            max_change_rate: Decimal::one(),
+        }
+    }
+
+    fn setup_app(delegator: &Addr, funds: u128) -> App {
+        let block = mock_env().block;
+        AppBuilder::new().build(|router, _api, storage| {
+            router
+                .bank
+                .init_balance(storage, delegator, coins(funds, "TOKEN"))
+                .unwrap();
+            router
+                .staking
+                .add_validator(storage, &block, validator(VALIDATOR, Decimal::percent(10)))
+                .unwrap();
+            router
+                .staking
+                .add_validator(storage, &block, validator(VALIDATOR2, Decimal::zero()))
+                .unwrap();
+        })
+    }
+
+    fn staking(app: &mut App, sender: &Addr, msg: StakingMsg) -> AnyResult<AppResponse> {
+        app.execute(sender.clone(), CosmosMsg::Staking(msg))
+    }
+
+    fn delegate(app: &mut App, sender: &Addr, validator: &str, amount: u128) {
+        staking(
+            app,
+            sender,
+            StakingMsg::Delegate {
+                validator: validator.to_string(),
+                amount: coin(amount, "TOKEN"),
+            },
+        )
+        .unwrap();
+    }
+
+    fn balance(app: &App, address: &Addr) -> u128 {
+        app.wrap()
+            .query_balance(address, "TOKEN")
+            .unwrap()
+            .amount
+            .u128()
+    }
+
+    fn delegation(app: &App, delegator: &Addr, validator: &str) -> Option<FullDelegation> {
+        app.wrap().query_delegation(delegator, validator).unwrap()
+    }
+
+    #[test]
+    fn validators_and_bonded_denom() {
+        let delegator = Addr::unchecked("delegator");
+        let app = setup_app(&delegator, 1000);
+
+        let denom = app.wrap().query_bonded_denom().unwrap();
+        assert_eq!(denom, "TOKEN");
+
+        let validators = app.wrap().query_all_validators().unwrap();
+        assert_eq!(
+            validators,
+            vec![
+                validator(VALIDATOR, Decimal::percent(10)),
+                validator(VALIDATOR2, Decimal::zero())
+            ]
+        );
+        let val = app.wrap().query_validator(VALIDATOR2).unwrap();
+        assert_eq!(val, Some(validator(VALIDATOR2, Decimal::zero())));
+        let val = app.wrap().query_validator("nonexistent").unwrap();
+        assert_eq!(val, None);
+    }
+
+    #[test]
+    fn delegate_and_unbond() {
+        let delegator = Addr::unchecked("delegator");
+        let mut app = setup_app(&delegator, 1000);
+
+        delegate(&mut app, &delegator, VALIDATOR, 600);
+        assert_eq!(balance(&app, &delegator), 400);
+        let full = delegation(&app, &delegator, VALIDATOR).unwrap();
+        assert_eq!(full.amount, coin(600, "TOKEN"));
+        assert_eq!(full.can_redelegate, coin(600, "TOKEN"));
+
+        // wrong denom, unknown validator and too much unbonding are rejected
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Delegate {
+                validator: VALIDATOR.to_string(),
+                amount: coin(100, "FAKE"),
+            },
+        )
+        .unwrap_err();
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Delegate {
+                validator: "nonexistent".to_string(),
+                amount: coin(100, "TOKEN"),
+            },
+        )
+        .unwrap_err();
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Undelegate {
+                validator: VALIDATOR.to_string(),
+                amount: coin(601, "TOKEN"),
+            },
+        )
+        .unwrap_err();
+
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Undelegate {
+                validator: VALIDATOR.to_string(),
+                amount: coin(200, "TOKEN"),
+            },
+        )
+        .unwrap();
+        let delegations = app.wrap().query_all_delegations(&delegator).unwrap();
+        assert_eq!(delegations.len(), 1);
+        assert_eq!(delegations[0].amount, coin(400, "TOKEN"));
+
+        // tokens are only paid out once the unbonding time passed
+        app.update_block(|block| block.time = block.time.plus_seconds(30));
+        delegate(&mut app, &delegator, VALIDATOR2, 100);
+        assert_eq!(balance(&app, &delegator), 300);
+
+        app.update_block(|block| block.time = block.time.plus_seconds(30));
+        delegate(&mut app, &delegator, VALIDATOR2, 100);
+        assert_eq!(balance(&app, &delegator), 400);
+        assert_eq!(
+            app.wrap().query_all_delegations(&delegator).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn rewards_with_commission() {
+        let delegator = Addr::unchecked("delegator");
+        let other = Addr::unchecked("other");
+        let mut app = setup_app(&delegator, 2000);
+        app.send_tokens(delegator.clone(), other.clone(), &coins(1000, "TOKEN"))
+            .unwrap();
+
+        delegate(&mut app, &delegator, VALIDATOR, 1000);
+        delegate(&mut app, &other, VALIDATOR, 1000);
+
+        // 10% apr minus 10% commission
+        app.update_block(|block| block.time = block.time.plus_seconds(YEAR));
+        let full = delegation(&app, &delegator, VALIDATOR).unwrap();
+        assert_eq!(full.accumulated_rewards, vec![coin(90, "TOKEN")]);
+
+        // rewards go to the withdraw address, if set
+        let withdraw = Addr::unchecked("withdraw");
+        app.execute(
+            delegator.clone(),
+            CosmosMsg::Distribution(DistributionMsg::SetWithdrawAddress {
+                address: withdraw.to_string(),
+            }),
+        )
+        .unwrap();
+        app.execute(
+            delegator.clone(),
+            CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward {
+                validator: VALIDATOR.to_string(),
+            }),
+        )
+        .unwrap();
+        assert_eq!(balance(&app, &withdraw), 90);
+        let full = delegation(&app, &delegator, VALIDATOR).unwrap();
+        assert_eq!(full.accumulated_rewards, vec![coin(0, "TOKEN")]);
+
+        // the other delegator is unaffected
+        let full = delegation(&app, &other, VALIDATOR).unwrap();
+        assert_eq!(full.accumulated_rewards, vec![coin(90, "TOKEN")]);
+    }
+
+    #[test]
+    fn query_delegation_rewards() {
+        let delegator = Addr::unchecked("delegator");
+        let mut app = setup_app(&delegator, 2000);
+        delegate(&mut app, &delegator, VALIDATOR, 1000);
+
+        app.update_block(|block| block.time = block.time.plus_seconds(YEAR));
+        let res: DelegationRewardsResponse = app
+            .query_distribution(DistributionQuery::DelegationRewards {
+                delegator: delegator.to_string(),
+                validator: VALIDATOR.to_string(),
+            })
+            .unwrap();
+        assert_eq!(res.rewards, vec![coin(90, "TOKEN")]);
+
+        let res: DelegationTotalRewardsResponse = app
+            .query_distribution(DistributionQuery::DelegationTotalRewards {
+                delegator: delegator.to_string(),
+            })
+            .unwrap();
+        assert_eq!(
+            res.rewards,
+            vec![DelegatorReward {
+                validator: VALIDATOR.to_string(),
+                reward: vec![coin(90, "TOKEN")],
+            }]
+        );
+        assert_eq!(res.total, vec![coin(90, "TOKEN")]);
+
+        // no delegation, no rewards
+        let res: DelegationRewardsResponse = app
+            .query_distribution(DistributionQuery::DelegationRewards {
+                delegator: "other".to_string(),
+                validator: VALIDATOR.to_string(),
+            })
+            .unwrap();
+        assert_eq!(res.rewards, vec![]);
+    }
+
+    #[test]
+    fn redelegation() {
+        let delegator = Addr::unchecked("delegator");
+        let mut app = setup_app(&delegator, 1000);
+        delegate(&mut app, &delegator, VALIDATOR, 1000);
+
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Redelegate {
+                src_validator: VALIDATOR.to_string(),
+                dst_validator: VALIDATOR2.to_string(),
+                amount: coin(400, "TOKEN"),
+            },
+        )
+        .unwrap();
+        let src = delegation(&app, &delegator, VALIDATOR).unwrap();
+        assert_eq!(src.amount, coin(600, "TOKEN"));
+        let dst = delegation(&app, &delegator, VALIDATOR2).unwrap();
+        assert_eq!(dst.amount, coin(400, "TOKEN"));
+        assert_eq!(dst.can_redelegate, coin(0, "TOKEN"));
+
+        // cannot hop on while the first redelegation is in progress
+        let hop = StakingMsg::Redelegate {
+            src_validator: VALIDATOR2.to_string(),
+            dst_validator: VALIDATOR.to_string(),
+            amount: coin(100, "TOKEN"),
+        };
+        staking(&mut app, &delegator, hop.clone()).unwrap_err();
+
+        app.update_block(|block| block.time = block.time.plus_seconds(60));
+        let dst = delegation(&app, &delegator, VALIDATOR2).unwrap();
+        assert_eq!(dst.can_redelegate, coin(400, "TOKEN"));
+        staking(&mut app, &delegator, hop).unwrap();
+    }
+
+    #[test]
+    fn slashing() {
+        let delegator = Addr::unchecked("delegator");
+        let mut app = setup_app(&delegator, 1000);
+        delegate(&mut app, &delegator, VALIDATOR, 1000);
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Undelegate {
+                validator: VALIDATOR.to_string(),
+                amount: coin(200, "TOKEN"),
+            },
+        )
+        .unwrap();
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Redelegate {
+                src_validator: VALIDATOR.to_string(),
+                dst_validator: VALIDATOR2.to_string(),
+                amount: coin(400, "TOKEN"),
+            },
+        )
+        .unwrap();
+
+        app.sudo(
+            StakingSudo::Slash {
+                validator: VALIDATOR.to_string(),
+                percentage: Decimal::percent(50),
+            }
+            .into(),
+        )
+        .unwrap();
+
+        // bonded, unbonding and redelegated tokens are all slashed
+        let src = delegation(&app, &delegator, VALIDATOR).unwrap();
+        assert_eq!(src.amount, coin(200, "TOKEN"));
+        let dst = delegation(&app, &delegator, VALIDATOR2).unwrap();
+        assert_eq!(dst.amount, coin(200, "TOKEN"));
+        let module_balance = balance(&app, &Addr::unchecked("staking_module"));
+        assert_eq!(module_balance, 500);
+
+        app.update_block(|block| block.time = block.time.plus_seconds(60));
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Undelegate {
+                validator: VALIDATOR.to_string(),
+                amount: coin(200, "TOKEN"),
+            },
+        )
+        .unwrap();
+        assert_eq!(balance(&app, &delegator), 100);
+    }
+
+    #[test]
+    fn jailing_and_commission() {
+        let delegator = Addr::unchecked("delegator");
+        let mut app = setup_app(&delegator, 1000);
+        delegate(&mut app, &delegator, VALIDATOR2, 1000);
+
+        app.sudo(
+            StakingSudo::Jail {
+                validator: VALIDATOR2.to_string(),
+            }
+            .into(),
+        )
+        .unwrap();
+        let validators = app.wrap().query_all_validators().unwrap();
+        assert_eq!(validators, vec![validator(VALIDATOR, Decimal::percent(10))]);
+
+        // no rewards while jailed
+        app.update_block(|block| block.time = block.time.plus_seconds(YEAR));
+        let full = delegation(&app, &delegator, VALIDATOR2).unwrap();
+        assert_eq!(full.accumulated_rewards, vec![coin(0, "TOKEN")]);
+
+        app.sudo(
+            StakingSudo::Unjail {
+                validator: VALIDATOR2.to_string(),
+            }
+            .into(),
+        )
+        .unwrap();
+        app.sudo(
+            StakingSudo::ChangeCommission {
+                validator: VALIDATOR2.to_string(),
+                commission: Decimal::percent(50),
+            }
+            .into(),
+        )
+        .unwrap();
+        app.update_block(|block| block.time = block.time.plus_seconds(YEAR));
+        let full = delegation(&app, &delegator, VALIDATOR2).unwrap();
+        assert_eq!(full.accumulated_rewards, vec![coin(50, "TOKEN")]);
+
+        // commission is capped
+        let mut capped = validator("testvaloper3", Decimal::percent(10));
+        capped.max_commission = Decimal::percent(20);
+        app.sudo(StakingSudo::AddValidator { validator: capped }.into())
+            .unwrap();
+        app.sudo(
+            StakingSudo::ChangeCommission {
+                validator: "testvaloper3".to_string(),
+                commission: Decimal::percent(21),
+            }
+            .into(),
+        )
+        .unwrap_err();
+
+        // validators can only be removed once nobody is bonded to them
+        let remove = StakingSudo::RemoveValidator {
+            validator: VALIDATOR2.to_string(),
+        };
+        app.sudo(remove.clone().into()).unwrap_err();
+        staking(
+            &mut app,
+            &delegator,
+            StakingMsg::Undelegate {
+                validator: VALIDATOR2.to_string(),
+                amount: coin(1000, "TOKEN"),
+            },
+        )
+        .unwrap();
+        app.sudo(remove.into()).unwrap();
+        assert_eq!(app.wrap().query_validator(VALIDATOR2).unwrap(), None);
+        assert_eq!(delegation(&app, &delegator, VALIDATOR2), None);
+    }
+
+    #[test]
+    fn params_and_max_entries() {
+        let delegator = Addr::unchecked("delegator");
+        let mut app = setup_app(&delegator, 1000);
+        delegate(&mut app, &delegator, VALIDATOR, 1000);
+
+        app.sudo(
+            StakingSudo::SetParams {
+                params: StakingInfo {
+                    unbonding_time: 100,
+                    max_entries: 2,
+                    ..StakingInfo::default()
+                },
+            }
+            .into(),
+        )
+        .unwrap();
+
+        let unbond = StakingMsg::Undelegate {
+            validator: VALIDATOR.to_string(),
+            amount: coin(100, "TOKEN"),
+        };
+        staking(&mut app, &delegator, unbond.clone()).unwrap();
+        staking(&mut app, &delegator, unbond.clone()).unwrap();
+        let err = staking(&mut app, &delegator, unbond.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Too many unbonding delegation entries for (delegator, validator) tuple"
+        );
+
+        let redelegate = StakingMsg::Redelegate {
+            src_validator: VALIDATOR.to_string(),
+            dst_validator: VALIDATOR2.to_string(),
+            amount: coin(100, "TOKEN"),
+        };
+        staking(&mut app, &delegator, redelegate.clone()).unwrap();
+        staking(&mut app, &delegator, redelegate.clone()).unwrap();
+        staking(&mut app, &delegator, redelegate).unwrap_err();
+
+        // matured unbondings can be paid out without sending a staking message
+        app.update_block(|block| block.time = block.time.plus_seconds(99));
+        app.sudo(StakingSudo::ProcessQueue {}.into()).unwrap();
+        assert_eq!(balance(&app, &delegator), 0);
+        app.update_block(|block| block.time = block.time.plus_seconds(1));
+        app.sudo(StakingSudo::ProcessQueue {}.into()).unwrap();
+        assert_eq!(balance(&app, &delegator), 200);
+        staking(&mut app, &delegator, unbond).unwrap();
+
+        // the bonded denom can be changed
+        app.sudo(
+            StakingSudo::SetParams {
+                params: StakingInfo {
+                    bonded_denom: "STAKE".to_string(),
+                    ..StakingInfo::default()
+                },
+            }
+            .into(),
+        )
+        .unwrap();
+        assert_eq!(app.wrap().query_bonded_denom().unwrap(), "STAKE");
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result as AnyResult};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+use cosmwasm_std::{Addr, Api, Binary, BlockInfo, CustomQuery, Empty, Querier, Storage};
+
+use crate::app::CosmosRouter;
+use crate::executor::AppResponse;
+use crate::module::FailingModule;
+use crate::Module;
+
+/// Protobuf encoded message, as sent by `CosmosMsg::Stargate`
+#[derive(Clone, std::fmt::Debug, PartialEq, JsonSchema)]
+pub struct StargateMsg {
+    pub type_url: String,
+    pub value: Binary,
+}
+
+/// Protobuf encoded query, as sent by `QueryRequest::Stargate`
+#[derive(Clone, std::fmt::Debug, PartialEq, JsonSchema)]
+pub struct StargateQuery {
+    pub path: String,
+    pub data: Binary,
+}
+
+pub trait Stargate: Module<ExecT = StargateMsg, QueryT = StargateQuery, SudoT = Empty> {}
+
+pub type FailingStargate = FailingModule<StargateMsg, StargateQuery, Empty>;
+
+impl Stargate for FailingStargate {}
+
+/// Handles a message of one type url. Gets the sender and the protobuf encoded message,
+/// returns the protobuf encoded response which becomes the data of the execution.
+pub type StargateExecFn =
+    Box<dyn Fn(&dyn Api, &mut dyn Storage, &BlockInfo, Addr, Binary) -> AnyResult<Binary>>;
+
+/// Handles queries of one path. Gets the protobuf encoded request, returns the encoded response.
+pub type StargateQueryFn =
+    Box<dyn Fn(&dyn Api, &dyn Storage, &BlockInfo, Binary) -> AnyResult<Binary>>;
+
+/// Dispatches stargate messages and queries to the handlers registered for them.
+/// Anything without a handler fails with an error naming its type url or path.
+#[derive(Default)]
+pub struct StargateKeeper {
+    execs: HashMap<String, StargateExecFn>,
+    queries: HashMap<String, StargateQueryFn>,
+}
+
+impl StargateKeeper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler of messages with the given type url, eg. `/cosmos.authz.v1beta1.MsgExec`.
+    /// Replaces the previous handler of this type url.
+    pub fn register_exec<F>(&mut self, type_url: impl Into<String>, handler: F)
+    where
+        F: Fn(&dyn Api, &mut dyn Storage, &BlockInfo, Addr, Binary) -> AnyResult<Binary> + 'static,
+    {
+        self.execs.insert(type_url.into(), Box::new(handler));
+    }
+
+    /// Registers the handler of queries with the given path, eg. `/cosmos.bank.v1beta1.Query/Balance`.
+    /// Replaces the previous handler of this path.
+    pub fn register_query<F>(&mut self, path: impl Into<String>, handler: F)
+    where
+        F: Fn(&dyn Api, &dyn Storage, &BlockInfo, Binary) -> AnyResult<Binary> + 'static,
+    {
+        self.queries.insert(path.into(), Box::new(handler));
+    }
+
+    /// Builder flavour of `register_exec`
+    pub fn with_exec<F>(mut self, type_url: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&dyn Api, &mut dyn Storage, &BlockInfo, Addr, Binary) -> AnyResult<Binary> + 'static,
+    {
+        self.register_exec(type_url, handler);
+        self
+    }
+
+    /// Builder flavour of `register_query`
+    pub fn with_query<F>(mut self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&dyn Api, &dyn Storage, &BlockInfo, Binary) -> AnyResult<Binary> + 'static,
+    {
+        self.register_query(path, handler);
+        self
+    }
+}
+
+impl Stargate for StargateKeeper {}
+
+impl Module for StargateKeeper {
+    type ExecT = StargateMsg;
+    type QueryT = StargateQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: StargateMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let handler = self.execs.get(&msg.type_url).ok_or_else(|| {
+            anyhow!(
+                "No stargate handler registered for type url {}",
+                msg.type_url
+            )
+        })?;
+        let data = handler(api, storage, block, sender, msg.value)?;
+        Ok(AppResponse {
+            events: vec![],
+            data: Some(data).filter(|data| !data.is_empty()),
+        })
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Empty,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("Unexpected sudo msg {:?}", msg)
+    }
+
+    fn query(
+        &self,
+        api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        block: &BlockInfo,
+        request: StargateQuery,
+    ) -> AnyResult<Binary> {
+        let handler = self.queries.get(&request.path).ok_or_else(|| {
+            anyhow!(
+                "No stargate query handler registered for path {}",
+                request.path
+            )
+        })?;
+        handler(api, storage, block, request.data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{AppBuilder, Executor};
+    use cosmwasm_std::{CosmosMsg, QueryRequest};
+
+    const MSG_STORE: &str = "/test.v1.MsgStore";
+    const QUERY_LOAD: &str = "/test.v1.Query/Load";
+
+    #[test]
+    fn dispatches_to_registered_handlers() {
+        let stargate = StargateKeeper::new()
+            .with_exec(MSG_STORE, |_, storage, _, sender, value| {
+                storage.set(sender.as_bytes(), &value);
+                Ok(Binary::from(b"stored".to_vec()))
+            })
+            .with_query(QUERY_LOAD, |_, storage, _, data| {
+                Ok(storage.get(&data).unwrap_or_default().into())
+            });
+        let mut app = AppBuilder::new()
+            .with_stargate(stargate)
+            .build(|_, _, _| {});
+
+        let msg: CosmosMsg = CosmosMsg::Stargate {
+            type_url: MSG_STORE.to_string(),
+            value: Binary::from(b"proto".to_vec()),
+        };
+        let res = app.execute(Addr::unchecked("owner"), msg).unwrap();
+        assert_eq!(res.data, Some(Binary::from(b"stored".to_vec())));
+
+        let request: QueryRequest<Empty> = QueryRequest::Stargate {
+            path: QUERY_LOAD.to_string(),
+            data: Binary::from(b"owner".to_vec()),
+        };
+        let raw = app
+            .wrap()
+            .raw_query(&cosmwasm_std::to_vec(&request).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(raw, Binary::from(b"proto".to_vec()));
+    }
+
+    #[test]
+    fn unregistered_type_urls_fail() {
+        let mut app = AppBuilder::new().build(|_, _, _| {});
+        let msg: CosmosMsg = CosmosMsg::Stargate {
+            type_url: MSG_STORE.to_string(),
+            value: Binary::default(),
+        };
+        let err = app.execute(Addr::unchecked("owner"), msg).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No stargate handler registered for type url /test.v1.MsgStore"
+        );
+
+        let request: QueryRequest<Empty> = QueryRequest::Stargate {
+            path: QUERY_LOAD.to_string(),
+            data: Binary::default(),
+        };
+        let err = app.wrap().query::<Binary>(&request).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("No stargate query handler registered for path /test.v1.Query/Load"),
+            "{}",
+            err
+        );
+    }
+}
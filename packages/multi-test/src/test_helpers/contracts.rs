@@ -5,4 +5,6 @@ pub mod echo;
 pub mod error;
 pub mod hackatom;
 pub mod payout;
+#[cfg(feature = "stargate")]
+pub mod ping;
 pub mod reflect;
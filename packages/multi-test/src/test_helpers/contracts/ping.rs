@@ -0,0 +1,134 @@
+//! Contract sending strings over IBC channels and recording everything that comes back.
+//! Receiving "fail" errors, so error acknowledgements can be tested.
+
+use cosmwasm_std::{
+    from_binary, to_binary, Binary, Deps, DepsMut, Empty, Env, IbcBasicResponse,
+    IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcMsg, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout, MessageInfo,
+    Response, StdError, StdResult,
+};
+use cw_storage_plus::Item;
+use serde::{Deserialize, Serialize};
+
+use crate::test_helpers::EmptyMsg;
+use crate::{Contract, ContractWrapper};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecMsg {
+    pub channel_id: String,
+    pub data: String,
+    pub timeout: IbcTimeout,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct State {
+    /// open channels of the contract
+    pub channels: Vec<String>,
+    pub received: Vec<String>,
+    /// raw acknowledgements of sent packets
+    pub acks: Vec<Binary>,
+    /// data of sent packets which timed out
+    pub timeouts: Vec<String>,
+}
+
+const STATE: Item<State> = Item::new("state");
+
+fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: EmptyMsg,
+) -> Result<Response, StdError> {
+    STATE.save(deps.storage, &State::default())?;
+    Ok(Response::default())
+}
+
+fn execute(_deps: DepsMut, _env: Env, _info: MessageInfo, msg: ExecMsg) -> StdResult<Response> {
+    let msg = IbcMsg::SendPacket {
+        channel_id: msg.channel_id,
+        data: to_binary(&msg.data)?,
+        timeout: msg.timeout,
+    };
+    Ok(Response::new().add_message(msg))
+}
+
+fn query(deps: Deps, _env: Env, _msg: EmptyMsg) -> StdResult<Binary> {
+    to_binary(&STATE.load(deps.storage)?)
+}
+
+fn channel_open(_deps: DepsMut, _env: Env, _msg: IbcChannelOpenMsg) -> StdResult<()> {
+    Ok(())
+}
+
+fn channel_connect(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcChannelConnectMsg,
+) -> StdResult<IbcBasicResponse> {
+    let channel_id = msg.channel().endpoint.channel_id.clone();
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.channels.push(channel_id);
+        Ok(state)
+    })?;
+    Ok(IbcBasicResponse::new().add_attribute("action", "connect"))
+}
+
+fn channel_close(deps: DepsMut, _env: Env, msg: IbcChannelCloseMsg) -> StdResult<IbcBasicResponse> {
+    let channel_id = &msg.channel().endpoint.channel_id;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.channels.retain(|id| id != channel_id);
+        Ok(state)
+    })?;
+    Ok(IbcBasicResponse::new())
+}
+
+fn packet_receive(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketReceiveMsg,
+) -> StdResult<IbcReceiveResponse> {
+    let data: String = from_binary(&msg.packet.data)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.received.push(data.clone());
+        Ok(state)
+    })?;
+    if data == "fail" {
+        return Err(StdError::generic_err("Asked to fail"));
+    }
+    Ok(IbcReceiveResponse::new()
+        .set_ack(to_binary(&format!("pong: {}", data))?)
+        .add_attribute("action", "receive"))
+}
+
+fn packet_ack(deps: DepsMut, _env: Env, msg: IbcPacketAckMsg) -> StdResult<IbcBasicResponse> {
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.acks.push(msg.acknowledgement.data);
+        Ok(state)
+    })?;
+    Ok(IbcBasicResponse::new().add_attribute("action", "ack"))
+}
+
+fn packet_timeout(
+    deps: DepsMut,
+    _env: Env,
+    msg: IbcPacketTimeoutMsg,
+) -> StdResult<IbcBasicResponse> {
+    let data: String = from_binary(&msg.packet.data)?;
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.timeouts.push(data);
+        Ok(state)
+    })?;
+    Ok(IbcBasicResponse::new().add_attribute("action", "timeout"))
+}
+
+pub fn contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(execute, instantiate, query).with_ibc(
+        channel_open,
+        channel_connect,
+        channel_close,
+        packet_receive,
+        packet_ack,
+        packet_timeout,
+    );
+    Box::new(contract)
+}
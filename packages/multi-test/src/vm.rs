@@ -0,0 +1,964 @@
+//! Runs contracts compiled to wasm, so the binaries deployed on chain can be tested instead of
+//! their Rust sources. Implements the imports and calls the exports of CosmWasm 1.0 contracts
+//! (`interface_version_8`) on the wasmi interpreter.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{anyhow, bail, Context, Result as AnyResult};
+use cosmwasm_std::{
+    from_slice, to_vec, Api, Binary, ContractResult, CustomQuery, Deps, DepsMut, Env, MessageInfo,
+    Order, Querier, RecoverPubkeyError, Reply, Response, Storage, VerificationError,
+};
+#[cfg(feature = "stargate")]
+use cosmwasm_std::{
+    IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse,
+};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+#[cfg(feature = "stargate")]
+use serde::Serialize;
+use wasmi::core::Trap;
+use wasmi::{
+    AsContext, AsContextMut, Caller, Engine, Linker, Memory, Module, Store, TypedFunc, Value,
+};
+
+use crate::contracts::Contract;
+
+const INTERFACE_VERSION: &str = "interface_version_8";
+
+// limits on the data read from the contract, as the VM of wasmd has them
+const MAX_LENGTH_DB_KEY: usize = 64 * 1024;
+const MAX_LENGTH_DB_VALUE: usize = 128 * 1024;
+const MAX_LENGTH_ADDRESS: usize = 256;
+const MAX_LENGTH_QUERY_CHAIN_REQUEST: usize = 64 * 1024;
+const MAX_LENGTH_CRYPTO_INPUT: usize = 256 * 1024;
+const MAX_LENGTH_DEBUG: usize = 2 * 1024;
+const MAX_LENGTH_ABORT: usize = 2 * 1024;
+const MAX_LENGTH_RESULT: usize = 64 * 1024 * 1024;
+
+type DebugHandler = Rc<dyn Fn(&str)>;
+
+/// Contract running a compiled wasm binary, eg. an artifact of `cosmwasm/rust-optimizer`.
+///
+/// Every call gets a fresh instance of the module, like on chain. Custom messages and queries
+/// are passed to and from the contract as json, so they have to match what the binary expects.
+pub struct WasmContract {
+    engine: Engine,
+    module: Module,
+    debug_handler: Option<DebugHandler>,
+}
+
+impl WasmContract {
+    /// Compiles the wasm code, failing if it is not a CosmWasm 1.0 contract
+    pub fn new(wasm: &[u8]) -> AnyResult<Self> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, wasm).map_err(|err| anyhow!(err.to_string()))?;
+
+        let exports: Vec<_> = module.exports().map(|export| export.name()).collect();
+        if !exports.contains(&INTERFACE_VERSION) {
+            bail!(
+                "Wasm contract has an unsupported interface version, only {} is supported",
+                INTERFACE_VERSION
+            );
+        }
+        for export in ["memory", "allocate", "deallocate"] {
+            if !exports.contains(&export) {
+                bail!("Wasm contract doesn't have required export: {}", export);
+            }
+        }
+        Ok(WasmContract {
+            engine,
+            module,
+            debug_handler: None,
+        })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> AnyResult<Self> {
+        let path = path.as_ref();
+        let wasm = std::fs::read(path)
+            .with_context(|| format!("Cannot read wasm code from {}", path.display()))?;
+        Self::new(&wasm)
+    }
+
+    /// Passes the messages of `deps.api.debug` to the handler, they are dropped by default
+    pub fn with_debug_handler(mut self, handler: impl Fn(&str) + 'static) -> Self {
+        self.debug_handler = Some(Rc::new(handler));
+        self
+    }
+
+    fn has_entry_point(&self, entry_point: &str) -> bool {
+        self.module
+            .exports()
+            .any(|export| export.name() == entry_point)
+    }
+
+    /// Calls the entry point with the arguments written to contract memory,
+    /// returns what the contract wrote as its result
+    fn call(&self, mut host: Host, entry_point: &str, args: &[&[u8]]) -> AnyResult<Vec<u8>> {
+        if !self.has_entry_point(entry_point) {
+            bail!("{} not implemented for contract", entry_point);
+        }
+        host.debug_handler = self.debug_handler.clone();
+
+        let mut store = Store::new(&self.engine, host);
+        let instance = linker(&self.engine)?
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|err| anyhow!(err.to_string()))?;
+        let exports = Exports {
+            memory: instance
+                .get_memory(&store, "memory")
+                .ok_or_else(|| anyhow!("Wasm contract doesn't export memory"))?,
+            allocate: instance
+                .get_typed_func(&store, "allocate")
+                .map_err(|err| anyhow!(err.to_string()))?,
+        };
+        let func = instance
+            .get_func(&store, entry_point)
+            .ok_or_else(|| anyhow!("Wasm contract doesn't export {}", entry_point))?;
+
+        let params = args
+            .iter()
+            .map(|arg| Ok(Value::I32(exports.allocate(&mut store, arg)? as i32)))
+            .collect::<Result<Vec<_>, Trap>>()
+            .map_err(|err| anyhow!(err.to_string()))?;
+        let mut results = [Value::I32(0)];
+        func.call(&mut store, &params, &mut results)
+            .map_err(|err| anyhow!("Error calling {}: {}", entry_point, err))?;
+
+        match results[0] {
+            Value::I32(ptr) => read_region(&store, exports.memory, ptr as u32, MAX_LENGTH_RESULT)
+                .map_err(|err| anyhow!(err.to_string())),
+            _ => bail!("{} returned no region", entry_point),
+        }
+    }
+
+    fn call_mut<Q, R>(&self, deps: DepsMut<Q>, entry_point: &str, args: &[&[u8]]) -> AnyResult<R>
+    where
+        Q: CustomQuery,
+        R: DeserializeOwned,
+    {
+        let DepsMut {
+            storage,
+            api,
+            querier,
+        } = deps;
+        let host = Host::new(HostStorage::ReadWrite(storage), api, &*querier);
+        parse_result(&self.call(host, entry_point, args)?)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn call_ibc<Q, M, R>(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        entry_point: &str,
+        msg: M,
+    ) -> AnyResult<R>
+    where
+        Q: CustomQuery,
+        M: Serialize,
+        R: DeserializeOwned,
+    {
+        self.call_mut(deps, entry_point, &[&to_vec(&env)?, &to_vec(&msg)?])
+    }
+}
+
+impl fmt::Debug for WasmContract {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmContract").finish()
+    }
+}
+
+impl<T, Q> Contract<T, Q> for WasmContract
+where
+    T: Clone + fmt::Debug + PartialEq + JsonSchema + DeserializeOwned,
+    Q: CustomQuery,
+{
+    fn execute(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        info: MessageInfo,
+        msg: Vec<u8>,
+    ) -> AnyResult<Response<T>> {
+        self.call_mut(deps, "execute", &[&to_vec(&env)?, &to_vec(&info)?, &msg])
+    }
+
+    fn instantiate(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        info: MessageInfo,
+        msg: Vec<u8>,
+    ) -> AnyResult<Response<T>> {
+        self.call_mut(
+            deps,
+            "instantiate",
+            &[&to_vec(&env)?, &to_vec(&info)?, &msg],
+        )
+    }
+
+    fn query(&self, deps: Deps<Q>, env: Env, msg: Vec<u8>) -> AnyResult<Binary> {
+        let host = Host::new(
+            HostStorage::ReadOnly(deps.storage),
+            deps.api,
+            &*deps.querier,
+        );
+        parse_result(&self.call(host, "query", &[&to_vec(&env)?, &msg])?)
+    }
+
+    fn sudo(&self, deps: DepsMut<Q>, env: Env, msg: Vec<u8>) -> AnyResult<Response<T>> {
+        self.call_mut(deps, "sudo", &[&to_vec(&env)?, &msg])
+    }
+
+    fn reply(&self, deps: DepsMut<Q>, env: Env, msg: Reply) -> AnyResult<Response<T>> {
+        self.call_mut(deps, "reply", &[&to_vec(&env)?, &to_vec(&msg)?])
+    }
+
+    fn migrate(&self, deps: DepsMut<Q>, env: Env, msg: Vec<u8>) -> AnyResult<Response<T>> {
+        self.call_mut(deps, "migrate", &[&to_vec(&env)?, &msg])
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_open(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcChannelOpenMsg,
+    ) -> AnyResult<()> {
+        self.call_ibc(deps, env, "ibc_channel_open", msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_connect(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcChannelConnectMsg,
+    ) -> AnyResult<IbcBasicResponse<T>> {
+        self.call_ibc(deps, env, "ibc_channel_connect", msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_channel_close(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcChannelCloseMsg,
+    ) -> AnyResult<IbcBasicResponse<T>> {
+        self.call_ibc(deps, env, "ibc_channel_close", msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_receive(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcPacketReceiveMsg,
+    ) -> AnyResult<IbcReceiveResponse<T>> {
+        self.call_ibc(deps, env, "ibc_packet_receive", msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_ack(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcPacketAckMsg,
+    ) -> AnyResult<IbcBasicResponse<T>> {
+        self.call_ibc(deps, env, "ibc_packet_ack", msg)
+    }
+
+    #[cfg(feature = "stargate")]
+    fn ibc_packet_timeout(
+        &self,
+        deps: DepsMut<Q>,
+        env: Env,
+        msg: IbcPacketTimeoutMsg,
+    ) -> AnyResult<IbcBasicResponse<T>> {
+        self.call_ibc(deps, env, "ibc_packet_timeout", msg)
+    }
+}
+
+fn parse_result<R: DeserializeOwned>(result: &[u8]) -> AnyResult<R> {
+    from_slice::<ContractResult<R>>(result)?
+        .into_result()
+        .map_err(|err| anyhow!(err))
+}
+
+/// State the imports of a contract work on
+struct Host<'a> {
+    storage: HostStorage<'a>,
+    api: &'a dyn Api,
+    querier: &'a dyn Querier,
+    iterators: Vec<Scan>,
+    debug_handler: Option<DebugHandler>,
+}
+
+impl<'a> Host<'a> {
+    fn new(storage: HostStorage<'a>, api: &'a dyn Api, querier: &'a dyn Querier) -> Self {
+        Host {
+            storage,
+            api,
+            querier,
+            iterators: vec![],
+            debug_handler: None,
+        }
+    }
+
+    fn storage(&self) -> &dyn Storage {
+        match &self.storage {
+            HostStorage::ReadOnly(storage) => *storage,
+            HostStorage::ReadWrite(storage) => &**storage,
+        }
+    }
+
+    fn storage_mut(&mut self) -> Result<&mut dyn Storage, Trap> {
+        match &mut self.storage {
+            HostStorage::ReadOnly(_) => Err(Trap::new(
+                "Write access to storage is not allowed in queries",
+            )),
+            HostStorage::ReadWrite(storage) => Ok(&mut **storage),
+        }
+    }
+}
+
+enum HostStorage<'a> {
+    ReadOnly(&'a dyn Storage),
+    ReadWrite(&'a mut dyn Storage),
+}
+
+/// Iterator opened by `db_scan`. It only keeps its bounds, which move past every record
+/// returned, so the storage stays writable while iterating.
+struct Scan {
+    start: Option<Vec<u8>>,
+    end: Option<Vec<u8>>,
+    order: Order,
+}
+
+impl Scan {
+    fn next(&mut self, storage: &dyn Storage) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (key, value) = storage
+            .range(self.start.as_deref(), self.end.as_deref(), self.order)
+            .next()?;
+        match self.order {
+            // the smallest key after this one
+            Order::Ascending => self.start = Some([key.as_slice(), &[0]].concat()),
+            Order::Descending => self.end = Some(key.clone()),
+        }
+        Some((key, value))
+    }
+}
+
+/// Exports of the contract the host needs to pass data to it
+struct Exports {
+    memory: Memory,
+    allocate: TypedFunc<u32, u32>,
+}
+
+impl Exports {
+    fn of<T>(caller: &Caller<'_, T>) -> Result<Self, Trap> {
+        let memory = caller
+            .get_export("memory")
+            .and_then(|export| export.into_memory())
+            .ok_or_else(|| Trap::new("Wasm contract doesn't export memory"))?;
+        let allocate = caller
+            .get_export("allocate")
+            .and_then(|export| export.into_func())
+            .ok_or_else(|| Trap::new("Wasm contract doesn't export allocate"))?
+            .typed(caller)
+            .map_err(|err| Trap::new(err.to_string()))?;
+        Ok(Exports { memory, allocate })
+    }
+
+    /// Copies the data to a new region allocated by the contract, returns the region pointer
+    fn allocate(&self, mut ctx: impl AsContextMut, data: &[u8]) -> Result<u32, Trap> {
+        let ptr = self.allocate.call(&mut ctx, data.len() as u32)?;
+        write_region(&mut ctx, self.memory, ptr, data)?;
+        Ok(ptr)
+    }
+}
+
+/// Region of contract memory, as described by `cosmwasm_std::memory::Region`
+struct Region {
+    offset: u32,
+    capacity: u32,
+    length: u32,
+}
+
+impl Region {
+    fn read(ctx: impl AsContext, memory: Memory, ptr: u32) -> Result<Self, Trap> {
+        let mut data = [0; 12];
+        memory
+            .read(&ctx, ptr as usize, &mut data)
+            .map_err(|err| Trap::new(err.to_string()))?;
+        let field = |idx: usize| {
+            u32::from_le_bytes([data[idx], data[idx + 1], data[idx + 2], data[idx + 3]])
+        };
+        let region = Region {
+            offset: field(0),
+            capacity: field(4),
+            length: field(8),
+        };
+        if region.length > region.capacity {
+            return Err(Trap::new("Region length exceeds its capacity"));
+        }
+        Ok(region)
+    }
+}
+
+fn read_region(
+    ctx: impl AsContext,
+    memory: Memory,
+    ptr: u32,
+    max_length: usize,
+) -> Result<Vec<u8>, Trap> {
+    let region = Region::read(&ctx, memory, ptr)?;
+    if region.length as usize > max_length {
+        return Err(Trap::new(format!(
+            "Region length {} exceeds limit {}",
+            region.length, max_length
+        )));
+    }
+    let mut data = vec![0; region.length as usize];
+    memory
+        .read(&ctx, region.offset as usize, &mut data)
+        .map_err(|err| Trap::new(err.to_string()))?;
+    Ok(data)
+}
+
+/// Reads a region which may be a null pointer
+fn read_optional_region(
+    ctx: impl AsContext,
+    memory: Memory,
+    ptr: u32,
+    max_length: usize,
+) -> Result<Option<Vec<u8>>, Trap> {
+    match ptr {
+        0 => Ok(None),
+        ptr => read_region(ctx, memory, ptr, max_length).map(Some),
+    }
+}
+
+fn write_region(
+    mut ctx: impl AsContextMut,
+    memory: Memory,
+    ptr: u32,
+    data: &[u8],
+) -> Result<(), Trap> {
+    let region = Region::read(&ctx, memory, ptr)?;
+    if data.len() > region.capacity as usize {
+        return Err(Trap::new(format!(
+            "Region too small, capacity {} for {} bytes",
+            region.capacity,
+            data.len()
+        )));
+    }
+    memory
+        .write(&mut ctx, region.offset as usize, data)
+        .and_then(|_| {
+            memory.write(
+                &mut ctx,
+                ptr as usize + 8,
+                &(data.len() as u32).to_le_bytes(),
+            )
+        })
+        .map_err(|err| Trap::new(err.to_string()))
+}
+
+/// Reverses `cosmwasm_std::sections::encode_sections`
+fn decode_sections(mut data: &[u8]) -> Result<Vec<&[u8]>, Trap> {
+    let mut sections = vec![];
+    while !data.is_empty() {
+        let invalid = || Trap::new("Invalid sections encoding");
+        let len_start = data.len().checked_sub(4).ok_or_else(invalid)?;
+        let mut len = [0; 4];
+        len.copy_from_slice(&data[len_start..]);
+        let start = len_start
+            .checked_sub(u32::from_be_bytes(len) as usize)
+            .ok_or_else(invalid)?;
+        sections.push(&data[start..len_start]);
+        data = &data[..start];
+    }
+    sections.reverse();
+    Ok(sections)
+}
+
+fn encode_sections(sections: &[&[u8]]) -> Vec<u8> {
+    sections
+        .iter()
+        .flat_map(|section| [*section, &(section.len() as u32).to_be_bytes()].concat())
+        .collect()
+}
+
+fn verification_result(result: Result<bool, VerificationError>) -> u32 {
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(VerificationError::InvalidHashFormat) => 3,
+        Err(VerificationError::InvalidSignatureFormat) => 4,
+        Err(VerificationError::InvalidPubkeyFormat) => 5,
+        Err(VerificationError::InvalidRecoveryParam) => 6,
+        Err(VerificationError::BatchErr) => 7,
+        Err(VerificationError::GenericErr) => 10,
+        Err(VerificationError::UnknownErr { error_code, .. }) => error_code,
+    }
+}
+
+fn recover_pubkey_error(err: RecoverPubkeyError) -> u32 {
+    match err {
+        RecoverPubkeyError::InvalidHashFormat => 3,
+        RecoverPubkeyError::InvalidSignatureFormat => 4,
+        RecoverPubkeyError::InvalidRecoveryParam => 6,
+        RecoverPubkeyError::UnknownErr { error_code, .. } => error_code,
+    }
+}
+
+/// Defines the imports CosmWasm 1.0 contracts may use
+fn linker<'a>(engine: &Engine) -> AnyResult<Linker<Host<'a>>> {
+    let mut linker = Linker::new(engine);
+    let link_err = |err: wasmi::errors::LinkerError| anyhow!(err.to_string());
+
+    linker
+        .func_wrap(
+            "env",
+            "db_read",
+            |mut caller: Caller<'_, Host<'a>>, key_ptr: u32| -> Result<u32, Trap> {
+                let exports = Exports::of(&caller)?;
+                let key = read_region(&caller, exports.memory, key_ptr, MAX_LENGTH_DB_KEY)?;
+                match caller.data().storage().get(&key) {
+                    Some(value) => exports.allocate(&mut caller, &value),
+                    None => Ok(0),
+                }
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "db_write",
+            |mut caller: Caller<'_, Host<'a>>, key_ptr: u32, value_ptr: u32| -> Result<(), Trap> {
+                let exports = Exports::of(&caller)?;
+                let key = read_region(&caller, exports.memory, key_ptr, MAX_LENGTH_DB_KEY)?;
+                let value = read_region(&caller, exports.memory, value_ptr, MAX_LENGTH_DB_VALUE)?;
+                caller.data_mut().storage_mut()?.set(&key, &value);
+                Ok(())
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "db_remove",
+            |mut caller: Caller<'_, Host<'a>>, key_ptr: u32| -> Result<(), Trap> {
+                let exports = Exports::of(&caller)?;
+                let key = read_region(&caller, exports.memory, key_ptr, MAX_LENGTH_DB_KEY)?;
+                caller.data_mut().storage_mut()?.remove(&key);
+                Ok(())
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "db_scan",
+            |mut caller: Caller<'_, Host<'a>>,
+             start_ptr: u32,
+             end_ptr: u32,
+             order: i32|
+             -> Result<u32, Trap> {
+                let exports = Exports::of(&caller)?;
+                let start =
+                    read_optional_region(&caller, exports.memory, start_ptr, MAX_LENGTH_DB_KEY)?;
+                let end =
+                    read_optional_region(&caller, exports.memory, end_ptr, MAX_LENGTH_DB_KEY)?;
+                let order = match order {
+                    1 => Order::Ascending,
+                    2 => Order::Descending,
+                    order => return Err(Trap::new(format!("Invalid iteration order {}", order))),
+                };
+                let iterators = &mut caller.data_mut().iterators;
+                iterators.push(Scan { start, end, order });
+                Ok(iterators.len() as u32 - 1)
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "db_next",
+            |mut caller: Caller<'_, Host<'a>>, iterator_id: u32| -> Result<u32, Trap> {
+                let exports = Exports::of(&caller)?;
+                let host = caller.data_mut();
+                let storage = match &host.storage {
+                    HostStorage::ReadOnly(storage) => *storage,
+                    HostStorage::ReadWrite(storage) => &**storage,
+                };
+                let record = host
+                    .iterators
+                    .get_mut(iterator_id as usize)
+                    .ok_or_else(|| Trap::new(format!("Iterator {} does not exist", iterator_id)))?
+                    .next(storage);
+                // an empty key ends the iteration
+                let (key, value) = record.unwrap_or_default();
+                exports.allocate(&mut caller, &encode_sections(&[&key, &value]))
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "addr_validate",
+            |mut caller: Caller<'_, Host<'a>>, source_ptr: u32| -> Result<u32, Trap> {
+                let exports = Exports::of(&caller)?;
+                let source = read_region(&caller, exports.memory, source_ptr, MAX_LENGTH_ADDRESS)?;
+                let result = String::from_utf8(source)
+                    .map_err(|_| "Input is not valid UTF-8".to_owned())
+                    .and_then(|source| {
+                        caller
+                            .data()
+                            .api
+                            .addr_validate(&source)
+                            .map_err(|err| err.to_string())
+                    });
+                match result {
+                    Ok(_) => Ok(0),
+                    Err(err) => exports.allocate(&mut caller, err.as_bytes()),
+                }
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "addr_canonicalize",
+            |mut caller: Caller<'_, Host<'a>>,
+             source_ptr: u32,
+             destination_ptr: u32|
+             -> Result<u32, Trap> {
+                let exports = Exports::of(&caller)?;
+                let source = read_region(&caller, exports.memory, source_ptr, MAX_LENGTH_ADDRESS)?;
+                let result = String::from_utf8(source)
+                    .map_err(|_| "Input is not valid UTF-8".to_owned())
+                    .and_then(|source| {
+                        caller
+                            .data()
+                            .api
+                            .addr_canonicalize(&source)
+                            .map_err(|err| err.to_string())
+                    });
+                match result {
+                    Ok(canonical) => {
+                        write_region(&mut caller, exports.memory, destination_ptr, &canonical)?;
+                        Ok(0)
+                    }
+                    Err(err) => exports.allocate(&mut caller, err.as_bytes()),
+                }
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "addr_humanize",
+            |mut caller: Caller<'_, Host<'a>>,
+             source_ptr: u32,
+             destination_ptr: u32|
+             -> Result<u32, Trap> {
+                let exports = Exports::of(&caller)?;
+                let source = read_region(&caller, exports.memory, source_ptr, MAX_LENGTH_ADDRESS)?;
+                let result = caller.data().api.addr_humanize(&source.into());
+                match result {
+                    Ok(human) => {
+                        write_region(
+                            &mut caller,
+                            exports.memory,
+                            destination_ptr,
+                            human.as_bytes(),
+                        )?;
+                        Ok(0)
+                    }
+                    Err(err) => exports.allocate(&mut caller, err.to_string().as_bytes()),
+                }
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "secp256k1_verify",
+            |caller: Caller<'_, Host<'a>>,
+             hash_ptr: u32,
+             signature_ptr: u32,
+             pubkey_ptr: u32|
+             -> Result<u32, Trap> {
+                let memory = Exports::of(&caller)?.memory;
+                let hash = read_region(&caller, memory, hash_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let signature =
+                    read_region(&caller, memory, signature_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let pubkey = read_region(&caller, memory, pubkey_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let result = caller
+                    .data()
+                    .api
+                    .secp256k1_verify(&hash, &signature, &pubkey);
+                Ok(verification_result(result))
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "secp256k1_recover_pubkey",
+            |mut caller: Caller<'_, Host<'a>>,
+             hash_ptr: u32,
+             signature_ptr: u32,
+             recovery_param: u32|
+             -> Result<u64, Trap> {
+                let exports = Exports::of(&caller)?;
+                let memory = exports.memory;
+                let hash = read_region(&caller, memory, hash_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let signature =
+                    read_region(&caller, memory, signature_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let recovery_param = match u8::try_from(recovery_param) {
+                    Ok(param) => param,
+                    Err(_) => return Ok(6 << 32),
+                };
+                let result =
+                    caller
+                        .data()
+                        .api
+                        .secp256k1_recover_pubkey(&hash, &signature, recovery_param);
+                // the error code goes into the high half, the pointer into the low one
+                match result {
+                    Ok(pubkey) => Ok(exports.allocate(&mut caller, &pubkey)? as u64),
+                    Err(err) => Ok((recover_pubkey_error(err) as u64) << 32),
+                }
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "ed25519_verify",
+            |caller: Caller<'_, Host<'a>>,
+             message_ptr: u32,
+             signature_ptr: u32,
+             pubkey_ptr: u32|
+             -> Result<u32, Trap> {
+                let memory = Exports::of(&caller)?.memory;
+                let message = read_region(&caller, memory, message_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let signature =
+                    read_region(&caller, memory, signature_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let pubkey = read_region(&caller, memory, pubkey_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let result = caller
+                    .data()
+                    .api
+                    .ed25519_verify(&message, &signature, &pubkey);
+                Ok(verification_result(result))
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "ed25519_batch_verify",
+            |caller: Caller<'_, Host<'a>>,
+             messages_ptr: u32,
+             signatures_ptr: u32,
+             pubkeys_ptr: u32|
+             -> Result<u32, Trap> {
+                let memory = Exports::of(&caller)?.memory;
+                let messages = read_region(&caller, memory, messages_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let signatures =
+                    read_region(&caller, memory, signatures_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let pubkeys = read_region(&caller, memory, pubkeys_ptr, MAX_LENGTH_CRYPTO_INPUT)?;
+                let result = caller.data().api.ed25519_batch_verify(
+                    &decode_sections(&messages)?,
+                    &decode_sections(&signatures)?,
+                    &decode_sections(&pubkeys)?,
+                );
+                Ok(verification_result(result))
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "debug",
+            |caller: Caller<'_, Host<'a>>, source_ptr: u32| -> Result<(), Trap> {
+                let memory = Exports::of(&caller)?.memory;
+                let message = read_region(&caller, memory, source_ptr, MAX_LENGTH_DEBUG)?;
+                if let Some(handler) = &caller.data().debug_handler {
+                    handler(&String::from_utf8_lossy(&message));
+                }
+                Ok(())
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "abort",
+            |caller: Caller<'_, Host<'a>>, source_ptr: u32| -> Result<(), Trap> {
+                let memory = Exports::of(&caller)?.memory;
+                let message = read_region(&caller, memory, source_ptr, MAX_LENGTH_ABORT)?;
+                Err(Trap::new(format!(
+                    "Aborted: {}",
+                    String::from_utf8_lossy(&message)
+                )))
+            },
+        )
+        .map_err(link_err)?;
+    linker
+        .func_wrap(
+            "env",
+            "query_chain",
+            |mut caller: Caller<'_, Host<'a>>, request_ptr: u32| -> Result<u32, Trap> {
+                let exports = Exports::of(&caller)?;
+                let request = read_region(
+                    &caller,
+                    exports.memory,
+                    request_ptr,
+                    MAX_LENGTH_QUERY_CHAIN_REQUEST,
+                )?;
+                let result = caller.data().querier.raw_query(&request);
+                let result = to_vec(&result).map_err(|err| Trap::new(err.to_string()))?;
+                exports.allocate(&mut caller, &result)
+            },
+        )
+        .map_err(link_err)?;
+
+    Ok(linker)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use cosmwasm_std::{Addr, Empty, Uint128};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{App, Executor};
+
+    const CW20_BASE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/cw20_base.wasm");
+
+    #[derive(Serialize, Debug)]
+    struct Cw20Coin {
+        address: String,
+        amount: Uint128,
+    }
+
+    #[derive(Serialize, Debug)]
+    struct InstantiateMsg {
+        name: String,
+        symbol: String,
+        decimals: u8,
+        initial_balances: Vec<Cw20Coin>,
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "snake_case")]
+    enum ExecuteMsg {
+        Transfer { recipient: String, amount: Uint128 },
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "snake_case")]
+    enum QueryMsg {
+        Balance { address: String },
+        AllAccounts {},
+    }
+
+    #[derive(Deserialize)]
+    struct BalanceResponse {
+        balance: Uint128,
+    }
+
+    #[derive(Deserialize)]
+    struct AllAccountsResponse {
+        accounts: Vec<String>,
+    }
+
+    fn balance(app: &App, token: &Addr, address: &str) -> u128 {
+        let res: BalanceResponse = app
+            .wrap()
+            .query_wasm_smart(
+                token,
+                &QueryMsg::Balance {
+                    address: address.to_owned(),
+                },
+            )
+            .unwrap();
+        res.balance.u128()
+    }
+
+    #[test]
+    fn runs_cw20_base() {
+        let mut app = App::default();
+        let contract = WasmContract::from_file(CW20_BASE).unwrap();
+        let code_id = app.store_code(Box::new(contract));
+
+        let owner = Addr::unchecked("owner");
+        let init_msg = InstantiateMsg {
+            name: "Darc token".to_owned(),
+            symbol: "DARC".to_owned(),
+            decimals: 6,
+            initial_balances: vec![Cw20Coin {
+                address: owner.to_string(),
+                amount: Uint128::new(1000),
+            }],
+        };
+        let token = app
+            .instantiate_contract(code_id, owner.clone(), &init_msg, &[], "cw20", None)
+            .unwrap();
+        assert_eq!(balance(&app, &token, "owner"), 1000);
+
+        let transfer = |amount| ExecuteMsg::Transfer {
+            recipient: "recipient".to_owned(),
+            amount: Uint128::new(amount),
+        };
+        let res = app
+            .execute_contract(owner.clone(), token.clone(), &transfer(300), &[])
+            .unwrap();
+        assert_eq!(res.custom_attrs(1)[0].value, "transfer");
+        assert_eq!(balance(&app, &token, "owner"), 700);
+        assert_eq!(balance(&app, &token, "recipient"), 300);
+
+        // iterating the storage
+        let res: AllAccountsResponse = app
+            .wrap()
+            .query_wasm_smart(&token, &QueryMsg::AllAccounts {})
+            .unwrap();
+        assert_eq!(res.accounts, ["owner", "recipient"]);
+
+        // errors of the contract are returned
+        let err = app
+            .execute_contract(owner, token.clone(), &transfer(1000), &[])
+            .unwrap_err();
+        assert!(
+            err.root_cause().to_string().contains("Overflow"),
+            "{:?}",
+            err
+        );
+        assert_eq!(balance(&app, &token, "owner"), 700);
+
+        // entry points the contract doesn't export fail
+        let err = app.wasm_sudo(token, &Empty {}).unwrap_err();
+        assert_eq!(err.to_string(), "sudo not implemented for contract");
+    }
+
+    #[test]
+    fn rejects_invalid_code() {
+        let err = WasmContract::new(b"\0asm\x01\0\0\0").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Wasm contract has an unsupported interface version, only interface_version_8 is supported"
+        );
+        WasmContract::new(b"not wasm").unwrap_err();
+    }
+}
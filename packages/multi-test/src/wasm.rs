@@ -3,23 +3,31 @@ use std::fmt;
 use std::ops::Deref;
 
 use cosmwasm_std::{
-    to_binary, Addr, Api, Attribute, BankMsg, Binary, BlockInfo, Coin, ContractInfo,
-    ContractInfoResponse, CustomQuery, Deps, DepsMut, Env, Event, MessageInfo, Order, Querier,
-    QuerierWrapper, Record, Reply, ReplyOn, Response, StdResult, Storage, SubMsg, SubMsgResponse,
-    SubMsgResult, TransactionInfo, WasmMsg, WasmQuery,
+    to_binary, Addr, Api, Attribute, BankMsg, Binary, BlockInfo, Coin, ContractInfo, CustomQuery,
+    Deps, DepsMut, Env, Event, MessageInfo, Order, Querier, QuerierWrapper, Record, Reply, ReplyOn,
+    Response, StdResult, Storage, SubMsg, SubMsgResponse, SubMsgResult, TransactionInfo, WasmMsg,
+    WasmQuery,
+};
+#[cfg(feature = "stargate")]
+use cosmwasm_std::{
+    IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
 };
 use cosmwasm_storage::{prefixed, prefixed_read, PrefixedStorage, ReadonlyPrefixedStorage};
 use prost::Message;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use cw_storage_plus::Map;
+use cw_storage_plus::{Item, Map};
 
+use crate::addresses::{AddressGenerator, SimpleAddressGenerator};
 use crate::app::{CosmosRouter, RouterQuerier};
 use crate::contracts::Contract;
 use crate::error::Error;
 use crate::executor::AppResponse;
+use crate::gas::GasMeteredStorage;
 use crate::transactions::transactional;
 use cosmwasm_std::testing::mock_wasmd_attr;
 
@@ -27,6 +35,8 @@ use anyhow::{bail, Context, Result as AnyResult};
 
 // Contract state is kept in Storage, separate from the contracts themselves
 const CONTRACTS: Map<&Addr, ContractData> = Map::new("contracts");
+/// Number of contracts instantiated so far, the last instance id
+const CONTRACT_COUNT: Item<u64> = Item::new("contract_count");
 
 pub const NAMESPACE_WASM: &[u8] = b"wasm";
 const CONTRACT_ATTR: &str = "_contract_addr";
@@ -46,6 +56,27 @@ impl WasmSudo {
     }
 }
 
+/// Calls one of a contract's IBC entry points. This is how the IBC module reaches contracts,
+/// it cannot be called via CosmosMsg.
+#[cfg(feature = "stargate")]
+#[derive(Clone, std::fmt::Debug, PartialEq, JsonSchema)]
+pub struct WasmIbcSudo {
+    pub contract_addr: Addr,
+    pub msg: WasmIbcMsg,
+}
+
+#[cfg(feature = "stargate")]
+#[derive(Clone, std::fmt::Debug, PartialEq, JsonSchema)]
+pub enum WasmIbcMsg {
+    ChannelOpen(IbcChannelOpenMsg),
+    ChannelConnect(IbcChannelConnectMsg),
+    ChannelClose(IbcChannelCloseMsg),
+    /// The acknowledgement written by the contract is returned as data
+    PacketReceive(IbcPacketReceiveMsg),
+    PacketAck(IbcPacketAckMsg),
+    PacketTimeout(IbcPacketTimeoutMsg),
+}
+
 /// Contract Data includes information about contract, equivalent of `ContractInfo` in wasmd
 /// interface.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -62,6 +93,17 @@ pub struct ContractData {
     pub created: u64,
 }
 
+/// Wire format of `ContractInfoResponse`, which is `#[non_exhaustive]`. Its `new` is
+/// deprecated in later cosmwasm-std versions and `default` is missing before 1.2.
+#[derive(Serialize)]
+struct ContractInfoQueryResponse {
+    code_id: u64,
+    creator: String,
+    admin: Option<String>,
+    pinned: bool,
+    ibc_port: Option<String>,
+}
+
 pub trait Wasm<ExecC, QueryC> {
     /// Handles all WasmQuery requests
     fn query(
@@ -94,12 +136,28 @@ pub trait Wasm<ExecC, QueryC> {
         block: &BlockInfo,
         msg: Binary,
     ) -> AnyResult<AppResponse>;
+
+    /// Calls the IBC entry points of a contract
+    #[cfg(feature = "stargate")]
+    fn ibc(
+        &self,
+        _api: &dyn Api,
+        _contract_addr: Addr,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: WasmIbcMsg,
+    ) -> AnyResult<AppResponse> {
+        bail!("Cannot call {:?}, ibc is not supported", msg)
+    }
 }
 
 pub struct WasmKeeper<ExecC, QueryC> {
     /// code is in-memory lookup that stands in for wasm code
     /// this can only be edited on the WasmRouter, and just read in caches
     codes: HashMap<usize, Box<dyn Contract<ExecC, QueryC>>>,
+    /// picks the addresses of new contracts
+    address_generator: Box<dyn AddressGenerator>,
     /// Just markers to make type elision fork when using it as `Wasm` trait
     _p: std::marker::PhantomData<QueryC>,
 }
@@ -108,6 +166,7 @@ impl<ExecC, QueryC> Default for WasmKeeper<ExecC, QueryC> {
     fn default() -> Self {
         Self {
             codes: HashMap::default(),
+            address_generator: Box::new(SimpleAddressGenerator::default()),
             _p: std::marker::PhantomData,
         }
     }
@@ -138,8 +197,13 @@ where
             WasmQuery::ContractInfo { contract_addr } => {
                 let addr = api.addr_validate(&contract_addr)?;
                 let contract = self.load_contract(storage, &addr)?;
-                let mut res = ContractInfoResponse::new(contract.code_id as u64, contract.creator);
-                res.admin = contract.admin.map(|x| x.into());
+                let res = ContractInfoQueryResponse {
+                    code_id: contract.code_id as u64,
+                    creator: contract.creator.into(),
+                    admin: contract.admin.map(|x| x.into()),
+                    pinned: false,
+                    ibc_port: None,
+                };
                 to_binary(&res).map_err(Into::into)
             }
             query => bail!(Error::UnsupportedWasmQuery(query)),
@@ -177,6 +241,26 @@ where
         let (res, msgs) = self.build_app_response(&contract, custom_event, res);
         self.process_response(api, router, storage, block, contract, res, msgs)
     }
+
+    #[cfg(feature = "stargate")]
+    fn ibc(
+        &self,
+        api: &dyn Api,
+        contract: Addr,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: WasmIbcMsg,
+    ) -> AnyResult<AppResponse> {
+        let (entry_point, res, ack) =
+            self.call_ibc(contract.clone(), api, storage, router, block, msg)?;
+        let custom_event = Event::new(entry_point).add_attribute(CONTRACT_ATTR, &contract);
+        let (res, msgs) = self.build_app_response(&contract, custom_event, res);
+        let mut res = self.process_response(api, router, storage, block, contract, res, msgs)?;
+        // the acknowledgement is not overwritten by submessages
+        res.data = ack;
+        Ok(res)
+    }
 }
 
 impl<ExecC, QueryC> WasmKeeper<ExecC, QueryC> {
@@ -276,6 +360,13 @@ where
         Self::default()
     }
 
+    /// Replaces how the addresses of new contracts are picked, eg. by a
+    /// `Bech32AddressGenerator` when using `Bech32Api`
+    pub fn with_address_generator(mut self, generator: impl AddressGenerator + 'static) -> Self {
+        self.address_generator = Box::new(generator);
+        self
+    }
+
     pub fn query_smart(
         &self,
         address: Addr,
@@ -376,65 +467,8 @@ where
                 res.data = execute_response(res.data);
                 Ok(res)
             }
-            WasmMsg::Instantiate {
-                admin,
-                code_id,
-                msg,
-                funds,
-                label,
-            } => {
-                if label.is_empty() {
-                    bail!("Label is required on all contracts");
-                }
-
-                let contract_addr = self.register_contract(
-                    storage,
-                    code_id as usize,
-                    sender.clone(),
-                    admin.map(Addr::unchecked),
-                    label,
-                    block.height,
-                )?;
-
-                // move the cash
-                self.send(
-                    api,
-                    storage,
-                    router,
-                    block,
-                    sender.clone(),
-                    contract_addr.clone().into(),
-                    &funds,
-                )?;
-
-                // then call the contract
-                let info = MessageInfo { sender, funds };
-                let res = self.call_instantiate(
-                    contract_addr.clone(),
-                    api,
-                    storage,
-                    router,
-                    block,
-                    info,
-                    msg.to_vec(),
-                )?;
-
-                let custom_event = Event::new("instantiate")
-                    .add_attribute(CONTRACT_ATTR, &contract_addr)
-                    .add_attribute("code_id", code_id.to_string());
-
-                let (res, msgs) = self.build_app_response(&contract_addr, custom_event, res);
-                let mut res = self.process_response(
-                    api,
-                    router,
-                    storage,
-                    block,
-                    contract_addr.clone(),
-                    res,
-                    msgs,
-                )?;
-                res.data = Some(instantiate_response(res.data, &contract_addr));
-                Ok(res)
+            msg @ WasmMsg::Instantiate { .. } => {
+                self.instantiate(api, storage, router, block, sender, msg, None)
             }
             WasmMsg::Migrate {
                 contract_addr,
@@ -497,13 +531,33 @@ where
         msg: SubMsg<ExecC>,
     ) -> AnyResult<AppResponse> {
         let SubMsg {
-            msg, id, reply_on, ..
+            msg,
+            id,
+            reply_on,
+            gas_limit,
         } = msg;
 
+        let gas = router.gas_meter();
+        if let Some(gas) = gas {
+            gas.consume(gas.config().submsg_cost);
+            gas.check()?;
+        }
+
         // execute in cache
-        let res = transactional(storage, |write_cache, _| {
-            router.execute(api, write_cache, block, contract.clone(), msg)
-        });
+        let execute = || {
+            transactional(storage, |write_cache, _| {
+                router.execute(api, write_cache, block, contract.clone(), msg)
+            })
+        };
+        let res = match (gas, gas_limit) {
+            (Some(gas), Some(limit)) => gas.with_limit(limit, execute),
+            _ => execute(),
+        };
+        // only running out of the submessage's own gas limit can be handled in reply,
+        // if the gas of the whole call is used up, it fails without reply
+        if let (Some(gas), Err(_)) = (gas, &res) {
+            gas.check()?;
+        }
 
         // call reply if meaningful
         if let Ok(mut r) = res {
@@ -529,9 +583,14 @@ where
             Ok(r)
         } else if let Err(e) = res {
             if matches!(reply_on, ReplyOn::Always | ReplyOn::Error) {
+                // running out of gas is reported as such, not as the failing message
+                let err = match e.downcast_ref::<Error>() {
+                    Some(err @ Error::OutOfGas { .. }) => err.to_string(),
+                    _ => e.to_string(),
+                };
                 let reply = Reply {
                     id,
-                    result: SubMsgResult::Err(e.to_string()),
+                    result: SubMsgResult::Err(err),
                 };
                 self._reply(api, router, storage, block, contract, reply)
             } else {
@@ -621,7 +680,9 @@ where
         response: AppResponse,
         messages: Vec<SubMsg<ExecC>>,
     ) -> AnyResult<AppResponse> {
-        let AppResponse { mut events, data } = response;
+        let AppResponse {
+            mut events, data, ..
+        } = response;
 
         // recurse in all messages
         let data = messages.into_iter().try_fold(data, |data, resend| {
@@ -634,6 +695,102 @@ where
         Ok(AppResponse { events, data })
     }
 
+    /// Instantiates a contract like `WasmMsg::Instantiate` does, but at the address predictable
+    /// from the code, the sender and `salt`, see `predictable_address`.
+    /// `msg` has to be a `WasmMsg::Instantiate`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn instantiate2(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: WasmMsg,
+        salt: Binary,
+    ) -> AnyResult<AppResponse> {
+        self.instantiate(api, storage, router, block, sender, msg, Some(salt))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn instantiate(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: WasmMsg,
+        salt: Option<Binary>,
+    ) -> AnyResult<AppResponse> {
+        let (admin, code_id, msg, funds, label) = match msg {
+            WasmMsg::Instantiate {
+                admin,
+                code_id,
+                msg,
+                funds,
+                label,
+            } => (admin, code_id, msg, funds, label),
+            msg => bail!("Expected an instantiate message, got {:?}", msg),
+        };
+        if label.is_empty() {
+            bail!("Label is required on all contracts");
+        }
+
+        let contract_addr = match salt {
+            Some(salt) => self.predictable_address(api, code_id as usize, &sender, &salt)?,
+            None => self.next_address(storage, code_id as usize)?,
+        };
+        let contract_addr = self.register_contract_at(
+            storage,
+            contract_addr,
+            code_id as usize,
+            sender.clone(),
+            admin.map(Addr::unchecked),
+            label,
+            block.height,
+        )?;
+        // move the cash
+        self.send(
+            api,
+            storage,
+            router,
+            block,
+            sender.clone(),
+            contract_addr.clone().into(),
+            &funds,
+        )?;
+
+        // then call the contract
+        let info = MessageInfo { sender, funds };
+        let res = self.call_instantiate(
+            contract_addr.clone(),
+            api,
+            storage,
+            router,
+            block,
+            info,
+            msg.to_vec(),
+        )?;
+
+        let custom_event = Event::new("instantiate")
+            .add_attribute(CONTRACT_ATTR, &contract_addr)
+            .add_attribute("code_id", code_id.to_string());
+
+        let (res, msgs) = self.build_app_response(&contract_addr, custom_event, res);
+        let mut res = self.process_response(
+            api,
+            router,
+            storage,
+            block,
+            contract_addr.clone(),
+            res,
+            msgs,
+        )?;
+        res.data = Some(instantiate_response(res.data, &contract_addr));
+        Ok(res)
+    }
+
     /// This just creates an address and empty storage instance, returning the new address
     /// You must call init after this to set up the contract properly.
     /// These are separated into two steps to have cleaner return values.
@@ -645,12 +802,27 @@ where
         admin: impl Into<Option<Addr>>,
         label: String,
         created: u64,
+    ) -> AnyResult<Addr> {
+        let addr = self.next_address(storage, code_id)?;
+        self.register_contract_at(storage, addr, code_id, creator, admin, label, created)
+    }
+
+    fn register_contract_at(
+        &self,
+        storage: &mut dyn Storage,
+        addr: Addr,
+        code_id: usize,
+        creator: Addr,
+        admin: impl Into<Option<Addr>>,
+        label: String,
+        created: u64,
     ) -> AnyResult<Addr> {
         if !self.codes.contains_key(&code_id) {
             bail!("Cannot init contract with unregistered code id");
         }
-
-        let addr = self.next_address(&prefixed_read(storage, NAMESPACE_WASM));
+        if self.load_contract(storage, &addr).is_ok() {
+            bail!("Contract address {} is already taken", addr);
+        }
 
         let info = ContractData {
             code_id,
@@ -760,6 +932,60 @@ where
         )?)
     }
 
+    /// Returns the name of the entry point called, its response and the acknowledgement if
+    /// a packet was received
+    #[cfg(feature = "stargate")]
+    pub fn call_ibc(
+        &self,
+        address: Addr,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: WasmIbcMsg,
+    ) -> AnyResult<(&'static str, Response<ExecC>, Option<Binary>)> {
+        let (entry_point, res, ack) = self.with_storage(
+            api,
+            storage,
+            router,
+            block,
+            address,
+            |contract, deps, env| {
+                Ok(match msg {
+                    WasmIbcMsg::ChannelOpen(msg) => {
+                        contract.ibc_channel_open(deps, env, msg)?;
+                        ("ibc_channel_open", Response::new(), None)
+                    }
+                    WasmIbcMsg::ChannelConnect(msg) => {
+                        let res = contract.ibc_channel_connect(deps, env, msg)?;
+                        ("ibc_channel_connect", ibc_basic_response(res), None)
+                    }
+                    WasmIbcMsg::ChannelClose(msg) => {
+                        let res = contract.ibc_channel_close(deps, env, msg)?;
+                        ("ibc_channel_close", ibc_basic_response(res), None)
+                    }
+                    WasmIbcMsg::PacketReceive(msg) => {
+                        let res = contract.ibc_packet_receive(deps, env, msg)?;
+                        let response = Response::new()
+                            .add_submessages(res.messages)
+                            .add_attributes(res.attributes)
+                            .add_events(res.events);
+                        ("ibc_packet_receive", response, Some(res.acknowledgement))
+                    }
+                    WasmIbcMsg::PacketAck(msg) => {
+                        let res = contract.ibc_packet_ack(deps, env, msg)?;
+                        ("ibc_packet_ack", ibc_basic_response(res), None)
+                    }
+                    WasmIbcMsg::PacketTimeout(msg) => {
+                        let res = contract.ibc_packet_timeout(deps, env, msg)?;
+                        ("ibc_packet_timeout", ibc_basic_response(res), None)
+                    }
+                })
+            },
+        )?;
+        Ok((entry_point, Self::verify_response(res)?, ack))
+    }
+
     fn get_env<T: Into<Addr>>(&self, address: T, block: &BlockInfo) -> Env {
         Env {
             block: block.clone(),
@@ -823,15 +1049,28 @@ where
         // and this is the only way I know how to do so.
         transactional(storage, |write_cache, read_store| {
             let mut contract_storage = self.contract_storage(write_cache, &address);
+            let mut metered_storage;
+            let storage: &mut dyn Storage = match router.gas_meter() {
+                Some(gas) => {
+                    metered_storage = GasMeteredStorage::new(contract_storage.as_mut(), gas);
+                    &mut metered_storage
+                }
+                None => contract_storage.as_mut(),
+            };
             let querier = RouterQuerier::new(router, api, read_store, block);
             let env = self.get_env(address, block);
 
             let deps = DepsMut {
-                storage: contract_storage.as_mut(),
+                storage,
                 api: api.deref(),
                 querier: QuerierWrapper::new(&querier),
             };
-            action(handler, deps, env)
+            let res = action(handler, deps, env);
+            // the contract would have been stopped when running out of gas, whatever its result
+            if let Some(gas) = router.gas_meter() {
+                gas.check()?;
+            }
+            res
         })
     }
 
@@ -846,16 +1085,47 @@ where
             .map_err(Into::into)
     }
 
-    // FIXME: better addr generation
-    fn next_address(&self, storage: &dyn Storage) -> Addr {
-        // FIXME: quite inefficient if we actually had 100s of contracts
-        let count = CONTRACTS
-            .range_raw(storage, None, None, Order::Ascending)
-            .count();
-        // we make this longer so it is not rejected by tests
-        // it is lowercase to be compatible with the MockApi implementation of cosmwasm-std >= 1.0.0-beta8
-        Addr::unchecked(format!("contract{}", count))
+    /// Address of the next contract instance, counting it as instantiated
+    fn next_address(&self, storage: &mut dyn Storage, code_id: usize) -> AnyResult<Addr> {
+        let mut wasm_storage = prefixed(storage, NAMESPACE_WASM);
+        let instance_id = CONTRACT_COUNT.may_load(&wasm_storage)?.unwrap_or_default() + 1;
+        CONTRACT_COUNT.save(&mut wasm_storage, &instance_id)?;
+        self.address_generator
+            .contract_address(code_id as u64, instance_id)
     }
+
+    /// Stand-in for the sha256 checksum of the wasm code, which contracts in memory do not have
+    pub fn code_checksum(&self, code_id: usize) -> AnyResult<Vec<u8>> {
+        if !self.codes.contains_key(&code_id) {
+            bail!("Unregistered code id {}", code_id);
+        }
+        Ok(Sha256::digest(&(code_id as u64).to_be_bytes()).to_vec())
+    }
+
+    /// Address a contract of the code gets when `creator` instantiates it with `salt`
+    pub fn predictable_address(
+        &self,
+        api: &dyn Api,
+        code_id: usize,
+        creator: &Addr,
+        salt: &[u8],
+    ) -> AnyResult<Addr> {
+        let checksum = self.code_checksum(code_id)?;
+        let creator = api.addr_canonicalize(creator.as_str())?;
+        self.address_generator
+            .predictable_contract_address(&checksum, &creator, salt)
+    }
+}
+
+#[cfg(feature = "stargate")]
+fn ibc_basic_response<T>(res: IbcBasicResponse<T>) -> Response<T>
+where
+    T: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    Response::new()
+        .add_submessages(res.messages)
+        .add_attributes(res.attributes)
+        .add_events(res.events)
 }
 
 // TODO: replace with code in utils
@@ -901,7 +1171,9 @@ fn execute_response(data: Option<Binary>) -> Option<Binary> {
 #[cfg(test)]
 mod test {
     use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockQuerier, MockStorage};
-    use cosmwasm_std::{coin, from_slice, to_vec, BankMsg, Coin, CosmosMsg, Empty, StdError};
+    use cosmwasm_std::{
+        coin, from_slice, to_vec, BankMsg, Coin, ContractInfoResponse, CosmosMsg, Empty, StdError,
+    };
 
     use crate::app::Router;
     use crate::bank::BankKeeper;
@@ -910,7 +1182,17 @@ mod test {
     use crate::transactions::StorageTransaction;
 
     use super::*;
+    #[cfg(not(feature = "stargate"))]
+    use crate::app::{
+        GovKeeper as FailingGov, IbcKeeper as FailingIbc, StargateKeeper as FailingStargate,
+    };
+    #[cfg(feature = "stargate")]
+    use crate::gov::FailingGov;
+    #[cfg(feature = "stargate")]
+    use crate::ibc::FailingIbc;
     use crate::staking::{FailingDistribution, FailingStaking};
+    #[cfg(feature = "stargate")]
+    use crate::stargate::FailingStargate;
 
     /// Type alias for default build `Router` to make its reference in typical scenario
     type BasicRouter<ExecC = Empty, QueryC = Empty> = Router<
@@ -919,6 +1201,9 @@ mod test {
         WasmKeeper<ExecC, QueryC>,
         FailingStaking,
         FailingDistribution,
+        FailingGov,
+        FailingIbc,
+        FailingStargate,
     >;
 
     fn mock_router() -> BasicRouter {
@@ -928,6 +1213,10 @@ mod test {
             custom: FailingModule::new(),
             staking: FailingStaking::new(),
             distribution: FailingDistribution::new(),
+            gov: FailingGov::new(),
+            ibc: FailingIbc::new(),
+            stargate: FailingStargate::new(),
+            gas: None,
         }
     }
 
@@ -1050,9 +1339,12 @@ mod test {
             .query(&api, &wasm_storage, &querier, &block, query)
             .unwrap();
 
-        let mut expected = ContractInfoResponse::new(code_id as u64, "foobar");
-        expected.admin = Some("admin".to_owned());
-        assert_eq!(expected, from_slice(&info).unwrap());
+        let info: ContractInfoResponse = from_slice(&info).unwrap();
+        assert_eq!(info.code_id, code_id as u64);
+        assert_eq!(info.creator, "foobar");
+        assert_eq!(info.admin, Some("admin".to_owned()));
+        assert!(!info.pinned);
+        assert_eq!(info.ibc_port, None);
     }
 
     #[test]